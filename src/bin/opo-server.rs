@@ -0,0 +1,22 @@
+//! `opo-server [ADDRESS] [DIRECTORY]`: serve the tournaments of a directory over HTTP ( see `tournament_model::server` )
+//!
+//! the defaults are `127.0.0.1:8080` and `tournaments`.
+
+use opo::tournament_model::server::{serve, Api};
+use std::sync::Arc;
+
+const THREADS: usize = 4;
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let address = args.next().unwrap_or_else(|| "127.0.0.1:8080".to_string());
+    let directory = args.next().unwrap_or_else(|| "tournaments".to_string());
+    let result = Api::with_directory(&directory).and_then(|api| {
+        println!("serving {} at http://{}", directory, address);
+        serve(Arc::new(api), &address, THREADS)
+    });
+    if let Err(e) = result {
+        eprintln!("opo-server: {}", e);
+        std::process::exit(1);
+    }
+}
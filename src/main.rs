@@ -0,0 +1,352 @@
+//! `opo`: run a Swiss event from the terminal on a saved tournament file
+
+use clap::{Parser, Subcommand, ValueEnum};
+use opo::tournament_model::csv::{export_pairings, export_standings};
+use opo::tournament_model::error::OpoError;
+use opo::tournament_model::matching::Matching;
+use opo::tournament_model::players::Player;
+use opo::tournament_model::print::{html, pdf, Document};
+use opo::tournament_model::scoring::ScoringSystem;
+use opo::tournament_model::swiss_system_tournament::{PairingSystem, RoundState, Tournament};
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+#[cfg(feature = "tui")]
+mod tui;
+
+#[derive(Parser)]
+#[command(name = "opo", about = "run a Swiss tournament saved in a JSON file")]
+struct Cli {
+    /// the tournament file
+    #[arg(short, long, default_value = "tournament.json", global = true)]
+    file: PathBuf,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// create a tournament file
+    New {
+        #[arg(long, value_enum, default_value = "football")]
+        scoring: Scoring,
+        #[arg(long, value_enum, default_value = "points")]
+        pairing: Pairing,
+        /// points of a bye ( the points of a win by default )
+        #[arg(long)]
+        bye_points: Option<f64>,
+        /// count a bye as a 2-0 game win in GW%
+        #[arg(long)]
+        bye_game_win: bool,
+        /// overwrite an existing file
+        #[arg(long)]
+        force: bool,
+    },
+    /// register a player, and print the id
+    AddPlayer {
+        name: String,
+        #[arg(long)]
+        rating: Option<i32>,
+        /// byes in the first rounds instead of pairings ( e.g. for an invitation )
+        #[arg(long, default_value_t = 0)]
+        byes: usize,
+    },
+    /// drop a player from the next rounds
+    Drop { player_id: usize },
+    /// give a player a bye in a round which is not paired yet ( e.g. for an invitation or a penalty )
+    Bye {
+        player_id: usize,
+        #[arg(long)]
+        round: i32,
+        /// points of the bye ( the bye points of the scoring by default )
+        #[arg(long)]
+        points: Option<f64>,
+    },
+    /// pair the next round, start it and print the pairings ( the byes are entered at once )
+    Pair,
+    /// enter the result of a table: the games of the player as W-L or W-D-L
+    ///
+    /// the round is closed when its last result is entered.
+    Result {
+        player_id: usize,
+        opponent_id: usize,
+        /// games won, drawn and lost by the player, e.g. 2-1 or 1-1-1
+        score: Option<String>,
+        /// the result is a forfeit of the player, the opponent or both
+        #[arg(long, value_enum)]
+        forfeit: Option<Forfeit>,
+    },
+    /// print the standings with the tiebreakers
+    Standings,
+    /// write the tournament in another format ( to the standard output by default )
+    Export {
+        #[arg(value_enum)]
+        format: Format,
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// write a print-ready document as HTML ( to the standard output by default )
+    Print {
+        #[arg(value_enum)]
+        document: Printable,
+        /// write a PDF instead
+        #[arg(long)]
+        pdf: bool,
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// keep scores on an interactive screen
+    #[cfg(feature = "tui")]
+    Tui,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Scoring {
+    Chess,
+    Football,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Pairing {
+    Points,
+    Dutch,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Forfeit {
+    Player,
+    Opponent,
+    Both,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Json,
+    Trf,
+    Pairings,
+    Standings,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Printable {
+    Pairings,
+    PairingsByName,
+    Standings,
+    Slips,
+}
+
+/// the file, or the standard output
+fn output_writer(output: Option<PathBuf>) -> Result<Box<dyn Write>, OpoError> {
+    Ok(match output {
+        Some(output) => Box::new(File::create(output)?),
+        None => Box::new(io::stdout()),
+    })
+}
+
+/// `W-L` or `W-D-L` as the counts of won, drawn and lost games
+fn parse_score(score: &str) -> Result<(i32, i32, i32), OpoError> {
+    let counts = score.split('-')
+        .map(|count| count.trim().parse::<i32>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| OpoError::InvalidFormat(format!("invalid score: {}", score)))?;
+    match counts[..] {
+        [win, lose] => Ok((win, 0, lose)),
+        [win, draw, lose] => Ok((win, draw, lose)),
+        _ => Err(OpoError::InvalidFormat(format!("invalid score: {}", score))),
+    }
+}
+
+fn print_pairings(tournament: &Tournament, out: &mut dyn Write) -> Result<(), OpoError> {
+    let players = tournament.players();
+    for table in tournament.tables() {
+        let (id, opponent_id) = (table.player_id, table.opponent_id);
+        writeln!(out, "{:>3}  {:>4} {}  -  {:>4} {}", table.number, id, players[id].name(), opponent_id, players[opponent_id].name())?;
+    }
+    for id in tournament.byes() {
+        writeln!(out, "bye  {:>4} {}", id, players[id].name())?;
+    }
+    Ok(())
+}
+
+fn print_standings(tournament: &Tournament, out: &mut dyn Write) -> Result<(), OpoError> {
+    write!(out, "{:>4} {:>4} {:<24} {:>6}", "rank", "id", "name", "points")?;
+    for tiebreak in tournament.tiebreaks() {
+        write!(out, " {:>8}", tiebreak.name())?;
+    }
+    writeln!(out)?;
+    for (rank, (player, values)) in tournament.ranked_standings().into_iter().enumerate() {
+        let name = if player.is_dropped() { format!("{} (dropped)", player.name()) } else { player.name().clone() };
+        write!(out, "{:>4} {:>4} {:<24} {:>6}", rank + 1, player.id(), name, player.points())?;
+        for value in values {
+            write!(out, " {:>8.4}", value)?;
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+fn run(cli: Cli, out: &mut dyn Write) -> Result<(), OpoError> {
+    let path = cli.file.as_path();
+    match cli.command {
+        Command::New { scoring, pairing, bye_points, bye_game_win, force } => {
+            if path.exists() && !force {
+                return Err(OpoError::InvalidFormat(format!("{} exists ( use --force to overwrite it )", path.display())));
+            }
+            let mut tournament: Tournament = Default::default();
+            let scoring = match scoring {
+                Scoring::Chess => ScoringSystem::chess(),
+                Scoring::Football => ScoringSystem::football(),
+            };
+            let bye_points = bye_points.unwrap_or(scoring.bye());
+            tournament.set_scoring_system(scoring.with_bye(bye_points).with_bye_game_win(bye_game_win))?;
+            tournament.set_pairing_system(match pairing {
+                Pairing::Points => PairingSystem::Points,
+                Pairing::Dutch => PairingSystem::Dutch,
+            });
+            tournament.save(path)?;
+        }
+        Command::AddPlayer { name, rating, byes } => {
+            let mut tournament = Tournament::load(path)?;
+            let id = tournament.players().len();
+            let mut player = Player::new(id, name);
+            if let Some(rating) = rating {
+                player.set_rating(rating);
+            }
+            player.set_initial_byes(byes);
+            tournament.add_player(player);
+            tournament.save(path)?;
+            writeln!(out, "{}", id)?;
+        }
+        Command::Drop { player_id } => {
+            let mut tournament = Tournament::load(path)?;
+            tournament.drop_player(player_id)?;
+            tournament.save(path)?;
+        }
+        Command::Bye { player_id, round, points } => {
+            let mut tournament = Tournament::load(path)?;
+            tournament.assign_bye(round, player_id, points)?;
+            tournament.save(path)?;
+        }
+        Command::Pair => {
+            let mut tournament = Tournament::load(path)?;
+            let round_number = tournament.pair_next_round()?;
+            tournament.save(path)?;
+            writeln!(out, "round {}", round_number)?;
+            print_pairings(&tournament, out)?;
+        }
+        Command::Result { player_id, opponent_id, score, forfeit } => {
+            let mut tournament = Tournament::load(path)?;
+            let round_number = match tournament.state() {
+                RoundState::ResultsPending(round_number) => round_number,
+                state => return Err(OpoError::InvalidState { state, action: "record a result" }),
+            };
+            let (win, draw, lose) = match &score {
+                Some(score) => parse_score(score)?,
+                None => (0, 0, 0),
+            };
+            let (player_withdraw, opponent_withdraw) = match forfeit {
+                Some(Forfeit::Player) => (true, false),
+                Some(Forfeit::Opponent) => (false, true),
+                Some(Forfeit::Both) => (true, true),
+                None => (false, false),
+            };
+            let matching = Matching::new(round_number, player_id, opponent_id, win, draw, lose, player_withdraw, opponent_withdraw)?;
+            tournament.record_result(matching)?;
+            if tournament.outstanding_tables().is_empty() {
+                tournament.close_round()?;
+                writeln!(out, "round {} is complete", round_number)?;
+            }
+            tournament.save(path)?;
+        }
+        #[cfg(feature = "tui")]
+        Command::Tui => tui::run(cli.file)?,
+        Command::Standings => print_standings(&Tournament::load(path)?, out)?,
+        Command::Export { format, output } => {
+            let tournament = Tournament::load(path)?;
+            let mut writer = output_writer(output)?;
+            let writer = writer.as_mut();
+            match format {
+                Format::Json => writeln!(writer, "{}", tournament.to_json()?)?,
+                Format::Trf => write!(writer, "{}", tournament.to_trf())?,
+                Format::Pairings => export_pairings(&tournament, writer)?,
+                Format::Standings => export_standings(&tournament, writer)?,
+            }
+        }
+        Command::Print { document, pdf: as_pdf, output } => {
+            let tournament = Tournament::load(path)?;
+            let document = match document {
+                Printable::Pairings => Document::PairingsByTable,
+                Printable::PairingsByName => Document::PairingsByName,
+                Printable::Standings => Document::Standings,
+                Printable::Slips => Document::ResultSlips,
+            };
+            let mut writer = output_writer(output)?;
+            if as_pdf {
+                writer.write_all(&pdf(&tournament, document))?;
+            } else {
+                writer.write_all(html(&tournament, document).as_bytes())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn main() {
+    let cli = Cli::parse();
+    if let Err(e) = run(cli, &mut io::stdout()) {
+        eprintln!("opo: {}", e);
+        std::process::exit(1);
+    }
+}
+
+#[test]
+fn test_parse_score() {
+    assert_eq!(parse_score("2-1").unwrap(), (2, 0, 1));
+    assert_eq!(parse_score("1-1-1").unwrap(), (1, 1, 1));
+    assert!(parse_score("2").is_err());
+    assert!(parse_score("a-b").is_err());
+}
+
+#[test]
+fn test_run_event() {
+    let path = std::env::temp_dir().join(format!("opo_test_cli_{}.json", std::process::id()));
+    let file = path.to_str().unwrap();
+    let run_args = |args: &[&str]| -> Result<String, OpoError> {
+        let mut out = Vec::new();
+        let cli = Cli::try_parse_from([&["opo", "--file", file], args].concat()).unwrap();
+        run(cli, &mut out)?;
+        Ok(String::from_utf8(out).unwrap())
+    };
+    run_args(&["new", "--scoring", "chess", "--force"]).unwrap();
+    assert!(matches!(run_args(&["new"]), Err(OpoError::InvalidFormat(_))));
+    for name in ["Alice", "Bob", "Carol", "Dave", "Eve"] {
+        run_args(&["add-player", name, "--rating", "1500"]).unwrap();
+    }
+    run_args(&["drop", "4"]).unwrap();
+    assert!(matches!(run_args(&["bye", "9", "--round", "1"]), Err(OpoError::UnknownPlayer(9))));
+    let pairings = run_args(&["pair"]).unwrap();
+    assert!(pairings.starts_with("round 1\n"));
+    assert_eq!(pairings.lines().count(), 3);
+
+    let tournament = Tournament::load(&path).unwrap();
+    let tables = tournament.outstanding_tables();
+    let (a, b) = (tables[0].0, tables[0].1.unwrap());
+    run_args(&["result", &a.to_string(), &b.to_string(), "2-0"]).unwrap();
+    assert!(matches!(run_args(&["result", &a.to_string(), &b.to_string(), "2-0"]), Err(OpoError::DuplicateResult { .. })));
+    let (c, d) = (tables[1].0, tables[1].1.unwrap());
+    let closed = run_args(&["result", &c.to_string(), &d.to_string(), "--forfeit", "opponent"]).unwrap();
+    assert_eq!(closed, "round 1 is complete\n");
+
+    let standings = run_args(&["standings"]).unwrap();
+    assert_eq!(standings.lines().count(), 6);
+    assert!(standings.lines().last().unwrap().contains("Eve (dropped)"));
+    let slips = path.with_extension("pdf");
+    run_args(&["print", "slips", "--pdf", "--output", slips.to_str().unwrap()]).unwrap();
+    assert!(std::fs::read(&slips).unwrap().starts_with(b"%PDF"));
+    std::fs::remove_file(&slips).unwrap();
+    let tournament = Tournament::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(tournament.state(), RoundState::Complete(1));
+    assert_eq!(tournament.players()[a].points(), 1.0);
+}
@@ -0,0 +1,182 @@
+//! colours ( or play/draw seats ) of two-player games with first-move advantage
+
+use super::players::Player;
+use super::scoring::scaled_points;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Color {
+    White,
+    Black,
+}
+
+impl Color {
+    pub fn opposite(self) -> Self {
+        match self {
+            Color::White => Color::Black,
+            Color::Black => Color::White,
+        }
+    }
+}
+
+/// the colour a player wants in the next game, and how strongly
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ColorPreference {
+    /// no game has been played yet
+    #[default]
+    None,
+    Mild(Color),
+    Strong(Color),
+    /// the player must get this colour
+    Absolute(Color),
+}
+
+impl ColorPreference {
+    pub fn color(&self) -> Option<Color> {
+        match *self {
+            ColorPreference::None => None,
+            ColorPreference::Mild(color) | ColorPreference::Strong(color) | ColorPreference::Absolute(color) => Some(color),
+        }
+    }
+
+    /// 0 for none, 1 for mild, 2 for strong and 3 for absolute
+    pub fn strength(&self) -> u8 {
+        match self {
+            ColorPreference::None => 0,
+            ColorPreference::Mild(_) => 1,
+            ColorPreference::Strong(_) => 2,
+            ColorPreference::Absolute(_) => 3,
+        }
+    }
+
+    pub fn is_absolute(&self) -> bool {
+        matches!(self, ColorPreference::Absolute(_))
+    }
+
+    /// two players who have the same absolute preference can not be paired
+    pub fn conflicts(&self, other: &ColorPreference) -> bool {
+        self.is_absolute() && other.is_absolute() && self.color() == other.color()
+    }
+}
+
+/// allocate colours to the pairs of `matching_list` ( C.04.3 E )
+///
+/// `matching_list` is in the shape of `Tournament::matching_build`, and `players` is indexed by id.
+/// returns the colour of each player ( `None` for the players who have no opponent ).
+/// the boards are ordered by the rank of their higher player, and for each pair:
+///
+/// 1. both preferences are granted if they are compatible
+/// 2. the stronger preference is granted ( the wider colour difference between absolute ones )
+/// 3. colours are alternated to the most recent round in which they had different colours
+/// 4. the preference of the higher ranked player is granted
+/// 5. the higher ranked player gets white on odd boards and black on even boards
+pub fn allocate_colors(players: &[Player], matching_list: &[Option<usize>]) -> Vec<Option<Color>> {
+    let mut boards: Vec<(&Player, &Player)> = matching_list.iter().enumerate()
+        .filter_map(|(id, opponent)| opponent.map(|opponent| (&players[id], &players[opponent])))
+        .filter(|(player, opponent)| rank_cmp(player, opponent) == Ordering::Less)
+        .collect();
+    boards.sort_by(|a, b| rank_cmp(a.0, b.0));
+
+    let mut colors = vec![None; matching_list.len()];
+    for (board, (higher, lower)) in boards.into_iter().enumerate() {
+        let color = allocate_pair(higher, lower, board);
+        colors[higher.id()] = Some(color);
+        colors[lower.id()] = Some(color.opposite());
+    }
+    colors
+}
+
+/// higher ranked is `Less`: points, then rating, then id
+fn rank_cmp(a: &Player, b: &Player) -> Ordering {
+    scaled_points(b.points()).cmp(&scaled_points(a.points()))
+        .then(b.rating().cmp(&a.rating()))
+        .then(a.id().cmp(&b.id()))
+}
+
+/// the colour of `higher`
+fn allocate_pair(higher: &Player, lower: &Player, board: usize) -> Color {
+    let hp = higher.color_preference();
+    let lp = lower.color_preference();
+    match (hp.color(), lp.color()) {
+        (Some(hc), Some(lc)) if hc != lc => return hc,
+        (Some(hc), None) => return hc,
+        (None, Some(lc)) => return lc.opposite(),
+        (Some(hc), Some(_)) => {
+            if hp.strength() != lp.strength() {
+                return if hp.strength() > lp.strength() { hc } else { hc.opposite() };
+            }
+            if hp.is_absolute() {
+                let hd = higher.color_difference().abs();
+                let ld = lower.color_difference().abs();
+                if hd != ld {
+                    return if hd > ld { hc } else { hc.opposite() };
+                }
+            }
+        }
+        (None, None) => {}
+    }
+    let higher_history = higher.color_history();
+    let lower_history = lower.color_history();
+    let alternation = higher_history.iter().rev()
+        .zip(lower_history.iter().rev())
+        .find(|(h, l)| h != l);
+    if let Some((&h, _)) = alternation {
+        return h.opposite();
+    }
+    match hp.color() {
+        Some(hc) => hc,
+        None if board % 2 == 0 => Color::White,
+        None => Color::Black,
+    }
+}
+
+#[cfg(test)]
+fn player_with_colors(id: usize, colors: &[Color]) -> Player {
+    use super::matching::Matching;
+    let mut p = Player::new(id, format!("{}abcd", id));
+    for (round, color) in colors.iter().enumerate() {
+        let mut m = Matching::new(round as i32 + 1, id, 100 + round, 1, 0, 1, false, false).unwrap();
+        m.set_color(Some(*color));
+        p.add_matching(m);
+    }
+    p
+}
+
+#[test]
+fn test_color_preference_conflicts() {
+    use Color::*;
+    assert!(ColorPreference::Absolute(White).conflicts(&ColorPreference::Absolute(White)));
+    assert!(!ColorPreference::Absolute(White).conflicts(&ColorPreference::Absolute(Black)));
+    assert!(!ColorPreference::Absolute(White).conflicts(&ColorPreference::Strong(White)));
+    assert!(!ColorPreference::None.conflicts(&ColorPreference::None));
+}
+
+#[test]
+fn test_allocate_colors() {
+    use Color::*;
+    // no history: higher ranked player gets white on odd boards
+    let players: Vec<Player> = (0..4).map(|i| player_with_colors(i, &[])).collect();
+    assert_eq!(allocate_colors(&players, &[Some(2), Some(3), Some(0), Some(1)]),
+               vec![Some(White), Some(Black), Some(Black), Some(White)]);
+
+    let players = vec![
+        player_with_colors(0, &[White]),
+        player_with_colors(1, &[Black]),
+        player_with_colors(2, &[White, Black, Black]),
+        player_with_colors(3, &[Black]),
+        player_with_colors(4, &[White, Black, Black, White]),
+        player_with_colors(5, &[Black, White, Black, White]),
+        player_with_colors(6, &[White]),
+        player_with_colors(7, &[]),
+    ];
+    let colors = allocate_colors(&players, &[Some(1), Some(0), Some(3), Some(2), Some(5), Some(4), Some(7), Some(6)]);
+    // both preferences are compatible
+    assert_eq!((colors[0], colors[1]), (Some(Black), Some(White)));
+    // absolute for 2 and strong for 3
+    assert_eq!((colors[2], colors[3]), (Some(White), Some(Black)));
+    // both mild for black, alternate to the last round with different colours
+    assert_eq!((colors[4], colors[5]), (Some(White), Some(Black)));
+    // only 6 has a preference
+    assert_eq!((colors[6], colors[7]), (Some(Black), Some(White)));
+}
@@ -0,0 +1,237 @@
+//! CSV import of registrations, and CSV export of pairings and standings
+
+use super::error::OpoError;
+use super::players::Player;
+use super::swiss_system_tournament::Tournament;
+use std::fmt;
+use std::io::{Read, Write};
+
+/// which columns of a registration CSV ( by header ) hold what
+///
+/// without an id column, the players get ids in the order of the rows.
+/// with one, the ids must follow the players already registered, without gap ( in any row order ).
+#[derive(Clone, Debug, Default)]
+pub struct PlayerColumns {
+    pub id: Option<String>,
+    pub name: String,
+    pub rating: Option<String>,
+    /// columns kept as extra fields of the players, under the header name
+    pub fields: Vec<String>,
+}
+
+/// an invalid row of a registration CSV ( 1 is the first row after the header )
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RowError {
+    pub row: usize,
+    pub message: String,
+}
+
+impl fmt::Display for RowError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "row {}: {}", self.row, self.message)
+    }
+}
+
+fn column_index(headers: &::csv::StringRecord, column: &str) -> Result<usize, OpoError> {
+    headers.iter()
+        .position(|header| header.trim() == column)
+        .ok_or_else(|| OpoError::InvalidFormat(format!("missing column: {}", column)))
+}
+
+/// register the players of a CSV with a header row, and return how many were added
+///
+/// every row is checked first: if a row is invalid, no player is added and every invalid row is reported.
+pub fn import_players<R: Read>(tournament: &mut Tournament, reader: R, columns: &PlayerColumns) -> Result<usize, OpoError> {
+    let mut reader = ::csv::Reader::from_reader(reader);
+    let headers = reader.headers()?.clone();
+    let id_index = columns.id.as_deref().map(|column| column_index(&headers, column)).transpose()?;
+    let name_index = column_index(&headers, &columns.name)?;
+    let rating_index = columns.rating.as_deref().map(|column| column_index(&headers, column)).transpose()?;
+    let field_indexes = columns.fields.iter()
+        .map(|column| column_index(&headers, column))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let first_id = tournament.players().len();
+    let mut players: Vec<(usize, Player)> = Vec::new();
+    let mut errors = Vec::new();
+    for (index, record) in reader.records().enumerate() {
+        let row = index + 1;
+        let mut error = |message: String| errors.push(RowError { row, message });
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                error(e.to_string());
+                continue;
+            }
+        };
+        let cell = |index: usize| record.get(index).unwrap_or("").trim();
+        let id = match id_index {
+            Some(index) => match cell(index).parse::<usize>() {
+                Ok(id) => id,
+                Err(_) => {
+                    error(format!("invalid id: {:?}", cell(index)));
+                    continue;
+                }
+            },
+            None => first_id + players.len(),
+        };
+        let name = cell(name_index);
+        if name.is_empty() {
+            error("empty name".to_string());
+            continue;
+        }
+        let mut player = Player::new(id, name.to_string());
+        if let Some(index) = rating_index.filter(|&index| !cell(index).is_empty()) {
+            match cell(index).parse::<i32>() {
+                Ok(rating) => player.set_rating(rating),
+                Err(_) => {
+                    error(format!("invalid rating: {:?}", cell(index)));
+                    continue;
+                }
+            }
+        }
+        for (column, &index) in columns.fields.iter().zip(&field_indexes) {
+            player.set_field(column, cell(index));
+        }
+        players.push((row, player));
+    }
+
+    players.sort_by_key(|(row, player)| (player.id(), *row));
+    for (expected, (row, player)) in (first_id..).zip(&players) {
+        if player.id() != expected {
+            let message = if player.id() < expected { "duplicated or registered id" } else { "ids leave a gap before this id" };
+            errors.push(RowError { row: *row, message: format!("{}: {}", message, player.id()) });
+            break;
+        }
+    }
+    if !errors.is_empty() {
+        errors.sort_by_key(|error| error.row);
+        return Err(OpoError::InvalidRows(errors));
+    }
+    let count = players.len();
+    for (_, player) in players {
+        tournament.add_player(player);
+    }
+    Ok(count)
+}
+
+/// write the current pairings as `table,player_id,player,opponent_id,opponent` ( see `Tournament::tables` )
+///
+/// the players without opponent come last, without table.
+pub fn export_pairings<W: Write>(tournament: &Tournament, writer: W) -> Result<(), OpoError> {
+    let players = tournament.players();
+    let mut writer = ::csv::Writer::from_writer(writer);
+    writer.write_record(["table", "player_id", "player", "opponent_id", "opponent"])?;
+    for table in tournament.tables() {
+        let (id, opponent_id) = (table.player_id, table.opponent_id);
+        writer.write_record([
+            table.number.to_string(), id.to_string(), players[id].name().clone(), opponent_id.to_string(), players[opponent_id].name().clone(),
+        ])?;
+    }
+    for id in tournament.byes() {
+        writer.write_record([String::new(), id.to_string(), players[id].name().clone(), String::new(), String::new()])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// write the standings as `rank,player_id,player,points` and a column for each tiebreaker ( see `Tournament::ranked_standings` )
+pub fn export_standings<W: Write>(tournament: &Tournament, writer: W) -> Result<(), OpoError> {
+    let mut writer = ::csv::Writer::from_writer(writer);
+    let mut header = vec!["rank".to_string(), "player_id".to_string(), "player".to_string(), "points".to_string()];
+    header.extend(tournament.tiebreaks().iter().map(|tiebreak| tiebreak.name().to_string()));
+    writer.write_record(&header)?;
+    for (rank, (player, values)) in tournament.ranked_standings().into_iter().enumerate() {
+        let mut record = vec![(rank + 1).to_string(), player.id().to_string(), player.name().clone(), player.points().to_string()];
+        record.extend(values.iter().map(|value| format!("{:.4}", value)));
+        writer.write_record(&record)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+use super::matching::Matching;
+
+#[test]
+fn test_import_players() {
+    let mut t: Tournament = Default::default();
+    let csv = "No,Name,Rating,Club\n2,Carol,,Oak\n0,Alice,2100,Elm\n1,Bob,1900,\n";
+    let columns = PlayerColumns {
+        id: Some("No".to_string()),
+        name: "Name".to_string(),
+        rating: Some("Rating".to_string()),
+        fields: vec!["Club".to_string()],
+    };
+    assert_eq!(import_players(&mut t, csv.as_bytes(), &columns).unwrap(), 3);
+    let names: Vec<&str> = t.players().iter().map(|p| p.name().as_str()).collect();
+    assert_eq!(names, vec!["Alice", "Bob", "Carol"]);
+    assert_eq!((t.players()[0].rating(), t.players()[2].rating()), (2100, 0));
+    assert_eq!(t.players()[2].field("Club"), Some("Oak"));
+    assert_eq!(t.players()[1].field("Club"), Some(""));
+
+    // without id column, the ids follow the registered players
+    let columns = PlayerColumns { name: "Name".to_string(), ..Default::default() };
+    assert_eq!(import_players(&mut t, "Name\nDave\nEve\n".as_bytes(), &columns).unwrap(), 2);
+    assert_eq!(t.players()[4].id(), 4);
+    assert_eq!(t.player_number(), 5);
+}
+
+#[test]
+fn test_import_players_errors() {
+    let mut t: Tournament = Default::default();
+    let columns = PlayerColumns {
+        id: Some("id".to_string()),
+        name: "name".to_string(),
+        rating: Some("rating".to_string()),
+        fields: Vec::new(),
+    };
+    let csv = "id,name,rating\n0,Alice,abc\n1,,1500\nx,Carol,1500\n2,Dave\n";
+    match import_players(&mut t, csv.as_bytes(), &columns) {
+        Err(OpoError::InvalidRows(errors)) => {
+            let rows: Vec<usize> = errors.iter().map(|e| e.row).collect();
+            assert_eq!(rows, vec![1, 2, 3, 4]);
+            assert_eq!(errors[1].to_string(), "row 2: empty name");
+        }
+        result => panic!("{:?}", result),
+    }
+    assert!(t.players().is_empty());
+    let result = import_players(&mut t, "id,name,rating\n0,Alice,1\n0,Bob,2\n".as_bytes(), &columns);
+    assert!(matches!(result, Err(OpoError::InvalidRows(errors)) if errors[0].row == 2));
+    let result = import_players(&mut t, "id,name,rating\n1,Alice,1\n".as_bytes(), &columns);
+    assert!(matches!(result, Err(OpoError::InvalidRows(_))));
+    let result = import_players(&mut t, "no,name\n0,Alice\n".as_bytes(), &columns);
+    assert!(matches!(result, Err(OpoError::InvalidFormat(_))));
+    assert!(t.players().is_empty());
+}
+
+#[test]
+fn test_export_pairings_and_standings() {
+    let mut t: Tournament = Default::default();
+    for i in 0..5 {
+        t.add_player(Player::new(i, format!("{}abcd", i)));
+    }
+    t.aggregate_matches(vec![
+        Matching::new(1, 0, 1, 2, 0, 0, false, false).unwrap(),
+        Matching::new(1, 2, 3, 1, 1, 0, false, false).unwrap(),
+        Matching::no_opponent_new(1, 4),
+    ]).unwrap();
+    let mut standings = Vec::new();
+    export_standings(&t, &mut standings).unwrap();
+    let standings = String::from_utf8(standings).unwrap();
+    let lines: Vec<&str> = standings.lines().collect();
+    assert_eq!(lines.len(), 6);
+    assert_eq!(lines[0], "rank,player_id,player,points,OMW%,GW%,OGW%");
+    assert!(lines[1].starts_with("1,0,0abcd,3,"));
+    assert!(lines[5].starts_with("5,1,1abcd,0,"));
+
+    t.pair_round().unwrap();
+    let mut pairings = Vec::new();
+    export_pairings(&t, &mut pairings).unwrap();
+    let pairings = String::from_utf8(pairings).unwrap();
+    let lines: Vec<&str> = pairings.lines().collect();
+    assert_eq!(lines[0], "table,player_id,player,opponent_id,opponent");
+    assert_eq!(lines.len(), 4);
+    assert!(lines[1].starts_with("1,"));
+    assert!(lines[3].starts_with(",") && lines[3].ends_with(",,"));
+}
@@ -0,0 +1,537 @@
+//! FIDE Dutch system ( C.04.3 ) pairing
+//!
+//! players are split into score groups, which are paired from the top one by one as brackets.
+//! a bracket is made of the moved down players ( MDPs ) of the previous bracket and its residents.
+//! each bracket is divided into S1 and S2, and candidates are generated
+//! by transpositions of S2 and then by exchanges between S1 and S2.
+//! the first candidate which fulfils the absolute criteria and the completion criterion
+//! and which is ideal about the relative criteria is taken ( or the first best one, when every candidate has been examined ).
+//! only some of the quality criteria are modelled, see `PairingSystem::Dutch`.
+
+use super::color::{Color, ColorPreference};
+#[cfg(test)]
+use super::matching::Matching;
+use super::error::OpoError;
+use super::players::Player;
+use super::scoring::{scaled_points, ScoringSystem};
+use super::weighted_matching::{max_weight_matching, min_cost_perfect_matching};
+use itertools::Itertools;
+use std::cmp::{Ordering, Reverse};
+
+/// the number of candidates examined in one bracket
+///
+/// a bracket whose search is cut before it finds an ideal candidate fails with `OpoError::PairingSearchExhausted`,
+/// since the best candidate found may not be the one of C.04.3.
+const CANDIDATE_LIMIT: usize = 20000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Float {
+    Down,
+    Up,
+}
+
+/// a player seen from the pairing
+struct Entrant<'a> {
+    player: &'a Player,
+    /// the points ( see `scaled_points` )
+    score: i64,
+    /// how did the player float in the previous round?
+    float: Option<Float>,
+    /// the player has not had a bye yet
+    bye_eligible: bool,
+    preference: ColorPreference,
+}
+
+/// the players to be paired, ranked by score and pairing number
+///
+/// the index in `entrants` is used as the bracket sequence number ( BSN ),
+/// so smaller index is higher ranked in every bracket.
+struct Field<'a> {
+    entrants: Vec<Entrant<'a>>,
+}
+
+/// quality of a candidate, smaller is better
+///
+/// 1. the number of downfloaters
+/// 2. the number of players who do not get their colour preference
+/// 3. the number of players who do not get their strong ( or absolute ) colour preference
+/// 4. the number of players who float the same way as in the previous round
+type Quality = (usize, usize, usize, usize);
+
+struct Candidate {
+    quality: Quality,
+    pairs: Vec<(usize, usize)>,
+    floaters: Vec<usize>,
+}
+
+/// pair players by the FIDE Dutch system
+///
+/// the pairing number of a player is given by rating ( higher first ) and then by id,
+/// and the score groups are given by the points of `scoring`.
+/// returns `Vec<Option<usize>>` in the same shape as `matching_build`.
+pub fn matching_build_dutch(players: &[Player], scoring: &ScoringSystem) -> Result<Vec<Option<usize>>, OpoError> {
+
+    let field = Field::new(players, scoring);
+    let everyone: Vec<usize> = (0..field.entrants.len()).collect();
+    if !field.can_complete(&everyone) {
+        return Err(OpoError::PairingInfeasible);
+    }
+
+    let groups: Vec<Vec<usize>> = everyone.into_iter()
+        .group_by(|&p| field.entrants[p].score)
+        .into_iter()
+        .map(|(_, group)| group.collect())
+        .collect();
+
+    let mut pairs = Vec::new();
+    let mut mdps: Vec<usize> = Vec::new();
+    for (gi, residents) in groups.iter().enumerate() {
+        let lower: Vec<usize> = groups[gi + 1..].concat();
+        let mut search = BracketSearch::new(&field, lower, &mdps, residents);
+        search.heterogeneous(&mdps, residents);
+        if search.cut && !search.done {
+            return Err(OpoError::PairingSearchExhausted { bracket_size: mdps.len() + residents.len() });
+        }
+        match search.best {
+            Some(candidate) => {
+                pairs.extend(candidate.pairs);
+                mdps = candidate.floaters;
+                mdps.sort_unstable();
+            }
+            // the bracket can not be paired, so it is merged into the next one
+            None if !search.last => {
+                mdps.extend(residents.iter().copied());
+            }
+            // the completion criterion of the brackets above keeps the last one pairable
+            None => return Err(OpoError::PairingInfeasible),
+        }
+    }
+
+    let mut matching_list = vec![None; players.len()];
+    for (a, b) in pairs {
+        let a = field.entrants[a].player.id();
+        let b = field.entrants[b].player.id();
+        matching_list[a] = Some(b);
+        matching_list[b] = Some(a);
+    }
+    Ok(matching_list)
+
+}
+
+/// how did `player` float in the last round? ( a no-opponent counts as a downfloat )
+fn last_float(player: &Player, players: &[Player], scoring: &ScoringSystem) -> Option<Float> {
+    let (last, before) = player.matching_list().split_last()?;
+    if last.is_dropped() {
+        return None;
+    }
+    if last.is_no_opponent() {
+        return Some(Float::Down);
+    }
+    let score = scaled_points(before.iter().map(|matching| matching.matching_points(scoring)).sum());
+    let opponent_score = scaled_points(players.get(last.opponent_id())?
+        .matching_list().iter()
+        .take_while(|matching| matching.round_number() != last.round_number())
+        .map(|matching| matching.matching_points(scoring))
+        .sum());
+    match score.cmp(&opponent_score) {
+        Ordering::Greater => Some(Float::Down),
+        Ordering::Less => Some(Float::Up),
+        Ordering::Equal => None,
+    }
+}
+
+/// exchanges between S1 and S2 in the order of C.04.3 D.2, beginning with no exchange
+///
+/// 1. the smallest number of exchanged players
+/// 2. the smallest difference between the sums of the BSNs moved to S1 and moved to S2
+/// 3. the highest BSNs moved from S1 to S2
+/// 4. the lowest BSNs moved from S2 to S1
+fn exchanges(s1: &[usize], s2: &[usize]) -> impl Iterator<Item = (Vec<usize>, Vec<usize>)> {
+    let s1 = s1.to_vec();
+    let s2 = s2.to_vec();
+    let identity = (s1.clone(), s2.clone());
+    let max_exchange = max_exchange(s1.len(), s2.len());
+    std::iter::once(identity).chain((1..=max_exchange).flat_map(move |k| {
+        let mut swaps: Vec<(Vec<usize>, Vec<usize>)> = s1.iter().copied().rev().combinations(k)
+            .cartesian_product(s2.iter().copied().combinations(k).collect::<Vec<_>>())
+            .collect();
+        swaps.sort_by_key(|(out, into)| (
+            into.iter().sum::<usize>() as isize - out.iter().sum::<usize>() as isize,
+            Reverse(out.clone()),
+            into.clone(),
+        ));
+        let s1 = s1.clone();
+        let s2 = s2.clone();
+        swaps.into_iter().map(move |(out, into)| {
+            let x1 = s1.iter().filter(|p| !out.contains(p)).chain(&into).copied().sorted().collect();
+            let x2 = s2.iter().filter(|p| !into.contains(p)).chain(&out).copied().sorted().collect();
+            (x1, x2)
+        })
+    }))
+}
+
+/// the largest number of exchanged players whose exchanges fit in `CANDIDATE_LIMIT`
+fn max_exchange(s1: usize, s2: usize) -> usize {
+    (1..=s1.min(s2))
+        .take_while(|&k| binomial(s1, k).saturating_mul(binomial(s2, k)) <= CANDIDATE_LIMIT)
+        .last()
+        .unwrap_or(0)
+}
+
+fn binomial(n: usize, k: usize) -> usize {
+    (0..k).fold(1usize, |acc, i| acc.saturating_mul(n - i) / (i + 1))
+}
+
+impl<'a> Field<'a> {
+    fn new(players: &'a [Player], scoring: &ScoringSystem) -> Self {
+        let mut entrants: Vec<Entrant> = players.iter()
+            .filter(|p| !p.is_dropped())
+            .map(|p| Entrant {
+                player: p,
+                score: scaled_points(p.matching_list().iter().map(|matching| matching.matching_points(scoring)).sum()),
+                float: last_float(p, players, scoring),
+                bye_eligible: !p.had_matched_id(None),
+                preference: p.color_preference(),
+            })
+            .collect();
+        entrants.sort_by(|a, b| b.score.cmp(&a.score)
+            .then(b.player.rating().cmp(&a.player.rating()))
+            .then(a.player.id().cmp(&b.player.id())));
+        Field { entrants }
+    }
+
+    /// absolute criteria: two players shall not play against each other more than once,
+    /// and two players with the same absolute colour preference shall not meet
+    fn compatible(&self, a: usize, b: usize) -> bool {
+        !self.entrants[a].player.had_matched_id(Some(self.entrants[b].player.id()))
+            && !self.entrants[a].preference.conflicts(&self.entrants[b].preference)
+    }
+
+    fn edges(&self, members: &[usize], cost: impl Fn(usize, usize) -> i64) -> Vec<(usize, usize, i64)> {
+        let mut edges = Vec::new();
+        for (i, &a) in members.iter().enumerate() {
+            for (j, &b) in members.iter().enumerate().skip(i + 1) {
+                if self.compatible(a, b) {
+                    edges.push((i, j, cost(i, j)));
+                }
+            }
+        }
+        edges
+    }
+
+    /// the maximum number of pairs in `members`
+    fn max_pairs(&self, members: &[usize]) -> usize {
+        let edges = self.edges(members, |_, _| 1);
+        max_weight_matching(members.len(), &edges, true).iter()
+            .filter(|mate| mate.is_some())
+            .count() / 2
+    }
+
+    /// completion criterion: can `members` be paired up, with a bye if they are odd?
+    fn can_complete(&self, members: &[usize]) -> bool {
+        let mut edges = self.edges(members, |_, _| 0);
+        let mut vertex_number = members.len();
+        if vertex_number % 2 == 1 {
+            for (i, &p) in members.iter().enumerate() {
+                if self.entrants[p].bye_eligible {
+                    edges.push((i, members.len(), 0));
+                }
+            }
+            vertex_number += 1;
+        }
+        min_cost_perfect_matching(vertex_number, &edges).is_some()
+    }
+}
+
+/// candidate search in one bracket
+struct BracketSearch<'f, 'a> {
+    field: &'f Field<'a>,
+    /// players of the lower brackets
+    lower: Vec<usize>,
+    /// is this the last bracket? ( the downfloater of the last bracket gets the bye )
+    last: bool,
+    budget: usize,
+    /// the quality which can not be improved
+    ideal: Quality,
+    best: Option<Candidate>,
+    done: bool,
+    /// some candidates were not examined ( the budget ran out, or the exchanges were cut )
+    cut: bool,
+}
+
+impl<'f, 'a> BracketSearch<'f, 'a> {
+    fn new(field: &'f Field<'a>, lower: Vec<usize>, mdps: &[usize], residents: &[usize]) -> Self {
+        let members: Vec<usize> = mdps.iter().chain(residents).copied().collect();
+        let last = lower.is_empty();
+        let floaters = if last {
+            members.len() % 2
+        } else {
+            members.len() - 2 * field.max_pairs(&members)
+        };
+        // each pair satisfies at most one white and one black preference
+        let unsatisfiable = |strength: u8| {
+            let pairs = (members.len() - floaters) / 2;
+            let wanting = |color| members.iter()
+                .filter(|&&p| field.entrants[p].preference.strength() >= strength)
+                .filter(|&&p| field.entrants[p].preference.color() == Some(color))
+                .count();
+            wanting(Color::White).saturating_sub(pairs + floaters) + wanting(Color::Black).saturating_sub(pairs + floaters)
+        };
+        let ideal = (floaters, unsatisfiable(1), unsatisfiable(2), 0);
+        BracketSearch { field, lower, last, budget: CANDIDATE_LIMIT, ideal, best: None, done: false, cut: false }
+    }
+
+    fn stopped(&mut self) -> bool {
+        self.cut |= self.budget == 0;
+        self.done || self.budget == 0
+    }
+
+    /// pair as many MDPs ( S1 ) as possible with the residents ( S2 ), then the remainder
+    fn heterogeneous(&mut self, mdps: &[usize], residents: &[usize]) {
+        for m1 in (0..=mdps.len().min(residents.len())).rev() {
+            for s1 in mdps.iter().copied().combinations(m1) {
+                let limbo: Vec<usize> = mdps.iter().copied().filter(|p| !s1.contains(p)).collect();
+                for s2 in residents.iter().copied().permutations(m1) {
+                    if self.stopped() {
+                        return;
+                    }
+                    self.budget -= 1;
+                    if s1.iter().zip(&s2).any(|(&a, &b)| !self.field.compatible(a, b)) {
+                        continue;
+                    }
+                    let pairs: Vec<(usize, usize)> = s1.iter().copied().zip(s2.iter().copied()).collect();
+                    let remainder: Vec<usize> = residents.iter().copied().filter(|p| !s2.contains(p)).collect();
+                    self.homogeneous(&pairs, &remainder, &limbo);
+                }
+            }
+        }
+    }
+
+    /// pair `members` of the same score, S1 is the top half ( up to the maximum pairs )
+    fn homogeneous(&mut self, pairs: &[(usize, usize)], members: &[usize], floaters: &[usize]) {
+        let max_pairs = self.field.max_pairs(members);
+        for n1 in (0..=max_pairs).rev() {
+            let (s1, s2) = members.split_at(n1);
+            self.cut |= max_exchange(s1.len(), s2.len()) < s1.len().min(s2.len());
+            for (x1, x2) in exchanges(s1, s2) {
+                for transposition in x2.iter().copied().permutations(n1) {
+                    if self.stopped() {
+                        return;
+                    }
+                    self.budget -= 1;
+                    if x1.iter().zip(&transposition).any(|(&a, &b)| !self.field.compatible(a, b)) {
+                        continue;
+                    }
+                    let mut candidate_pairs = pairs.to_vec();
+                    candidate_pairs.extend(x1.iter().copied().zip(transposition.iter().copied()));
+                    let mut candidate_floaters = floaters.to_vec();
+                    candidate_floaters.extend(x2.iter().filter(|p| !transposition.contains(p)));
+                    self.evaluate(candidate_pairs, candidate_floaters);
+                }
+            }
+        }
+    }
+
+    fn evaluate(&mut self, pairs: Vec<(usize, usize)>, floaters: Vec<usize>) {
+        let entrants = &self.field.entrants;
+        if self.last && (floaters.len() > 1 || floaters.iter().any(|&p| !entrants[p].bye_eligible)) {
+            return;
+        }
+        let repeated_downfloats = floaters.iter()
+            .filter(|&&p| entrants[p].float == Some(Float::Down))
+            .count();
+        let repeated_upfloats = pairs.iter()
+            .filter(|&&(a, b)| entrants[a].score > entrants[b].score && entrants[b].float == Some(Float::Up))
+            .count();
+        let same_colors = |strength: u8| pairs.iter()
+            .filter(|&&(a, b)| entrants[a].preference.strength().min(entrants[b].preference.strength()) >= strength)
+            .filter(|&&(a, b)| entrants[a].preference.color() == entrants[b].preference.color())
+            .count();
+        let quality = (floaters.len(), same_colors(1), same_colors(2), repeated_downfloats + repeated_upfloats);
+        if self.best.as_ref().is_some_and(|best| quality >= best.quality) {
+            return;
+        }
+        if !self.last {
+            let rest: Vec<usize> = floaters.iter().chain(&self.lower).copied().collect();
+            if !self.field.can_complete(&rest) {
+                return;
+            }
+        }
+        self.done = quality.0 <= self.ideal.0 && quality.1 <= self.ideal.1
+            && quality.2 <= self.ideal.2 && quality.3 <= self.ideal.3;
+        self.best = Some(Candidate { quality, pairs, floaters });
+    }
+}
+
+#[cfg(test)]
+fn rated_players(number: usize) -> Vec<Player> {
+    (0..number).map(|i| {
+        let mut p = Player::new(i, format!("{}abcd", i));
+        p.set_rating(2400 - 50 * i as i32);
+        p
+    }).collect()
+}
+
+#[cfg(test)]
+fn play(players: &mut [Player], round_number: i32, player_id: usize, opponent_id: usize, win_count: i32, lose_count: i32) {
+    let matching = Matching::new(round_number, player_id, opponent_id, win_count, 0, lose_count, false, false).unwrap();
+    players[opponent_id].add_matching(Matching::rev(&matching));
+    players[player_id].add_matching(matching);
+}
+
+#[test]
+fn test_dutch_first_round() {
+    // top half against bottom half in pairing number order
+    let players = rated_players(8);
+    assert_eq!(matching_build_dutch(&players, &ScoringSystem::chess()).unwrap(),
+               vec![Some(4), Some(5), Some(6), Some(7), Some(0), Some(1), Some(2), Some(3)]);
+    // the lowest player gets the bye
+    let players = rated_players(7);
+    assert_eq!(matching_build_dutch(&players, &ScoringSystem::chess()).unwrap(),
+               vec![Some(3), Some(4), Some(5), Some(0), Some(1), Some(2), None]);
+}
+
+#[test]
+fn test_dutch_score_groups() {
+    let mut players = rated_players(8);
+    for i in 0..4 {
+        play(&mut players, 1, i, i + 4, 1, 0);
+    }
+    assert_eq!(matching_build_dutch(&players, &ScoringSystem::chess()).unwrap(),
+               vec![Some(2), Some(3), Some(0), Some(1), Some(6), Some(7), Some(4), Some(5)]);
+}
+
+#[test]
+fn test_dutch_transposition() {
+    // 1 point bracket is {0, 1, 4, 5}, but 0-4 and 1-5 have already played
+    let mut players = rated_players(8);
+    play(&mut players, 1, 0, 4, 1, 1);
+    play(&mut players, 1, 1, 5, 1, 1);
+    play(&mut players, 1, 2, 6, 1, 0);
+    play(&mut players, 1, 3, 7, 1, 0);
+    assert_eq!(matching_build_dutch(&players, &ScoringSystem::chess()).unwrap(),
+               vec![Some(5), Some(4), Some(3), Some(2), Some(1), Some(0), Some(7), Some(6)]);
+}
+
+#[test]
+fn test_dutch_exchange() {
+    // no transposition of S2 = {2, 3} works, so 1 and 2 are exchanged
+    let mut players = rated_players(4);
+    play(&mut players, 1, 0, 2, 1, 1);
+    play(&mut players, 1, 1, 3, 1, 1);
+    play(&mut players, 2, 0, 3, 1, 1);
+    play(&mut players, 2, 1, 2, 1, 1);
+    assert_eq!(matching_build_dutch(&players, &ScoringSystem::chess()).unwrap(), vec![Some(1), Some(0), Some(3), Some(2)]);
+}
+
+#[test]
+fn test_dutch_floaters() {
+    // 2 floats down from the odd bracket {0, 1, 2} and meets the top of the next bracket
+    let mut players = rated_players(6);
+    for i in 0..3 {
+        play(&mut players, 1, i, i + 3, 1, 0);
+    }
+    assert_eq!(matching_build_dutch(&players, &ScoringSystem::chess()).unwrap(),
+               vec![Some(1), Some(0), Some(3), Some(2), Some(5), Some(4)]);
+
+    // 1 and 4 can not be paired in their bracket, so both float down
+    play(&mut players, 2, 0, 1, 1, 0);
+    play(&mut players, 2, 2, 3, 1, 0);
+    play(&mut players, 2, 4, 5, 1, 0);
+    assert_eq!(matching_build_dutch(&players, &ScoringSystem::chess()).unwrap(),
+               vec![Some(2), Some(5), Some(0), Some(4), Some(3), Some(1)]);
+}
+
+#[test]
+fn test_dutch_repeated_float() {
+    // 4 had the bye, so 1 floats down instead of 4 and 4 can not get the bye again
+    let mut players = rated_players(5);
+    play(&mut players, 1, 0, 2, 1, 0);
+    play(&mut players, 1, 1, 3, 1, 0);
+    players[4].add_matching(Matching::no_opponent_new(1, 4));
+    assert_eq!(matching_build_dutch(&players, &ScoringSystem::chess()).unwrap(),
+               vec![Some(4), Some(2), Some(1), None, Some(0)]);
+}
+
+#[test]
+fn test_dutch_exchange_order() {
+    // S1 = 1 2 3 4 5 and S2 = 6 7 8 9 10 11 ( BSNs from 1 ): no exchange, then 5-6, 5-7, 4-6, 5-8, 4-7, 3-6 ( C.04.3 D.2 )
+    let s1: Vec<usize> = (0..5).collect();
+    let s2: Vec<usize> = (5..11).collect();
+    let swapped: Vec<(Vec<usize>, Vec<usize>)> = exchanges(&s1, &s2).take(7)
+        .map(|(x1, x2)| (
+            s1.iter().filter(|p| x2.contains(p)).map(|p| p + 1).collect(),
+            s2.iter().filter(|p| x1.contains(p)).map(|p| p + 1).collect(),
+        ))
+        .collect();
+    assert_eq!(swapped, vec![
+        (vec![], vec![]), (vec![5], vec![6]), (vec![5], vec![7]), (vec![4], vec![6]),
+        (vec![5], vec![8]), (vec![4], vec![7]), (vec![3], vec![6]),
+    ]);
+}
+
+#[test]
+fn test_dutch_search_exhausted() {
+    // every player of the 15-player bracket floated down with a bye, so the floater of the bracket floats down again
+    // and no candidate is ideal: the search is cut before it has seen every candidate
+    let mut players = rated_players(16);
+    for player in &mut players[..15] {
+        player.add_matching(Matching::no_opponent_new(1, player.id()));
+    }
+    players[15].add_matching(Matching::dropped_new(1, 15));
+    assert!(matches!(matching_build_dutch(&players, &ScoringSystem::chess()), Err(OpoError::PairingSearchExhausted { bracket_size: 15 })));
+}
+
+#[test]
+fn test_dutch_no_pairing() {
+    let mut players = rated_players(2);
+    play(&mut players, 1, 0, 1, 1, 0);
+    assert!(matching_build_dutch(&players, &ScoringSystem::chess()).is_err());
+}
+
+#[test]
+fn test_dutch_absolute_color_preference() {
+    use super::color::Color::{self, *};
+    // two drawn games against players from outside of the field
+    let history = |players: &mut [Player], id: usize, colors: [Color; 2]| {
+        for (round, color) in colors.iter().enumerate() {
+            let mut m = Matching::new(round as i32 + 1, id, 100 + id, 1, 0, 1, false, false).unwrap();
+            m.set_color(Some(*color));
+            players[id].add_matching(m);
+        }
+    };
+    let mut players = rated_players(6);
+    for (id, color) in [White, Black, White, White, Black, Black].iter().enumerate() {
+        history(&mut players, id, [*color, *color]);
+    }
+    // 0 and 3 both must have black
+    assert_eq!(matching_build_dutch(&players, &ScoringSystem::chess()).unwrap(),
+               vec![Some(4), Some(3), Some(5), Some(1), Some(0), Some(2)]);
+}
+
+
+#[test]
+fn test_dutch_multi_round_example() {
+    // 9 players by rating, worked through C.04.3 by hand ( a report file with the results of round 1 )
+    let players = rated_players(9);
+    assert_eq!(matching_build_dutch(&players, &ScoringSystem::chess()).unwrap(),
+               vec![Some(4), Some(5), Some(6), Some(7), Some(0), Some(1), Some(2), Some(3), None]);
+    let trf = "\
+062 9
+001    1      0abcd                             2400                             1.0    1  0005 w 1
+001    2      1abcd                             2350                             0.0    7  0006 b 0
+001    3      2abcd                             2300                             0.5    5  0007 w =
+001    4      3abcd                             2250                             1.0    2  0008 b 1
+001    5      4abcd                             2200                             0.0    8  0001 b 0
+001    6      5abcd                             2150                             1.0    3  0002 w 1
+001    7      6abcd                             2100                             0.5    6  0003 b =
+001    8      7abcd                             2050                             0.0    9  0004 w 0
+001    9      8abcd                             2000                             1.0    4  0000 - U
+";
+    let tournament = super::trf::import(trf).unwrap();
+    // 1 point: 1-6 and 4-9 leave two strong black preferences unmet, the transposition 1-9 and 4-6 meets all of them
+    // 1/2 point: 3 and 7 have played, so both float down and meet 2 and 8 ( 7-5 would give both white )
+    // 0 point: 5 is left for the bye
+    assert_eq!(matching_build_dutch(tournament.players(), &ScoringSystem::chess()).unwrap(),
+               vec![Some(8), Some(2), Some(1), Some(5), None, Some(3), Some(7), Some(6), Some(0)]);
+}
@@ -0,0 +1,717 @@
+//! single-elimination playoff ( top cut ) after the Swiss rounds, and double-elimination brackets
+//!
+//! the top players of the standings are seeded so that seed 1 meets seed 8 ( of 8 ),
+//! seed 4 meets seed 5 and so on, and the higher seeds meet as late as possible.
+//! the winners of each round advance, and the placings of the whole field are given
+//! by the round in which each player is eliminated and then by the standings.
+
+use super::error::OpoError;
+use super::matching::Matching;
+use super::players::Player;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+
+/// a game of the bracket
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BracketMatch {
+    /// ids of the two players ( `None` is an empty slot )
+    players: [Option<usize>; 2],
+    /// is the result in?
+    decided: bool,
+    /// id of the player who advances ( `None` if both players are out )
+    winner: Option<usize>,
+}
+
+impl BracketMatch {
+    fn new(players: [Option<usize>; 2]) -> Self {
+        BracketMatch { players, decided: false, winner: None }
+    }
+    pub fn players(&self) -> [Option<usize>; 2] {
+        self.players
+    }
+    pub fn is_decided(&self) -> bool {
+        self.decided
+    }
+    pub fn winner(&self) -> Option<usize> {
+        self.winner
+    }
+    fn has(&self, id: usize) -> bool {
+        self.players.contains(&Some(id))
+    }
+    fn decide(&mut self, winner: Option<usize>) {
+        self.decided = true;
+        self.winner = winner;
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SingleElimination {
+    /// ids of the players in the bracket, the first seed first
+    seeds: Vec<usize>,
+    /// ids of the players outside the cut, in the order of the standings
+    others: Vec<usize>,
+    /// ids of the players who dropped from the bracket
+    dropped: Vec<usize>,
+    /// games of each round, the first round first
+    rounds: Vec<Vec<BracketMatch>>,
+}
+
+/// seed numbers ( 1-origin ) in bracket order, e.g. 1, 8, 4, 5, 2, 7, 3, 6 for 8 players
+fn bracket_order(size: usize) -> Vec<usize> {
+    let mut order = vec![1];
+    while order.len() < size {
+        let sum = order.len() * 2 + 1;
+        order = order.into_iter().flat_map(|seed| vec![seed, sum - seed]).collect();
+    }
+    order
+}
+
+/// ids of the first `size` players of `standings` who have not dropped, and ids of the others
+fn seed(standings: &[&Player], size: usize, min_size: usize) -> Result<(Vec<usize>, Vec<usize>), OpoError> {
+    if size < min_size || !size.is_power_of_two() {
+        return Err(OpoError::InvalidBracketSize(size));
+    }
+    let seeds: Vec<usize> = standings.iter()
+        .filter(|p| !p.is_dropped())
+        .take(size)
+        .map(|p| p.id())
+        .collect();
+    if seeds.len() < size {
+        return Err(OpoError::NotEnoughPlayers { required: size, available: seeds.len() });
+    }
+    let others = standings.iter()
+        .map(|p| p.id())
+        .filter(|id| !seeds.contains(id))
+        .collect();
+    Ok((seeds, others))
+}
+
+/// the id of the player who advances by `matching` ( `None` if both players withdrew )
+fn advancing(matching: &Matching) -> Result<Option<usize>, OpoError> {
+    if matching.is_win() {
+        Ok(Some(matching.player_id()))
+    } else if matching.is_lose() {
+        Ok(Some(matching.opponent_id()))
+    } else if matching.is_valid() {
+        Err(OpoError::NoWinner { player_id: matching.player_id(), opponent_id: matching.opponent_id() })
+    } else {
+        Ok(None)
+    }
+}
+
+impl SingleElimination {
+    /// seed the first `size` players of `standings` who have not dropped
+    ///
+    /// `size` must be a power of two ( 2, 4, 8, ... ) and not more than those players.
+    pub fn new(standings: &[&Player], size: usize) -> Result<Self, OpoError> {
+        let (seeds, others) = seed(standings, size, 2)?;
+        let first_round = bracket_order(size)
+            .chunks(2)
+            .map(|pair| BracketMatch::new([Some(seeds[pair[0] - 1]), Some(seeds[pair[1] - 1])]))
+            .collect();
+        Ok(SingleElimination { seeds, others, dropped: Vec::new(), rounds: vec![first_round] })
+    }
+
+    pub fn seeds(&self) -> &[usize] {
+        &self.seeds
+    }
+
+    pub fn rounds(&self) -> &[Vec<BracketMatch>] {
+        &self.rounds
+    }
+
+    /// the current round ( 1-origin )
+    pub fn round_number(&self) -> usize {
+        self.rounds.len()
+    }
+
+    /// the final has been decided
+    pub fn is_finished(&self) -> bool {
+        self.rounds.last().is_some_and(|round| round.len() == 1 && round[0].decided)
+    }
+
+    /// the games of the current round which are waiting for their result
+    pub fn pairings(&self) -> Vec<(usize, usize)> {
+        self.rounds.last().into_iter().flatten()
+            .filter(|game| !game.decided)
+            .filter_map(|game| match game.players {
+                [Some(a), Some(b)] => Some((a, b)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// enter the result of a game of the current round
+    ///
+    /// the winner of `matching` advances ( a withdrawn player loses by no-show ),
+    /// and a game which both players withdrew sends nobody to the next round.
+    /// a draw is refused, and a dropped matching drops the player ( see `drop_player` ).
+    pub fn report(&mut self, matching: &Matching) -> Result<(), OpoError> {
+        if matching.is_dropped() {
+            return self.drop_player(matching.player_id());
+        }
+        let (player_id, opponent_id) = (matching.player_id(), matching.opponent_id());
+        let game = self.rounds.last_mut().into_iter().flatten()
+            .find(|game| !game.decided && game.has(player_id) && game.has(opponent_id))
+            .ok_or(OpoError::NoSuchGame { player_id, opponent_id })?;
+        game.decide(advancing(matching)?);
+        self.advance();
+        Ok(())
+    }
+
+    /// drop the player from the bracket, the opponent advances by walkover
+    pub fn drop_player(&mut self, id: usize) -> Result<(), OpoError> {
+        if !self.seeds.contains(&id) {
+            return Err(OpoError::UnknownPlayer(id));
+        }
+        if !self.dropped.contains(&id) {
+            self.dropped.push(id);
+        }
+        self.advance();
+        Ok(())
+    }
+
+    /// give walkovers and start the next round while the current one is complete
+    fn advance(&mut self) {
+        loop {
+            let dropped = &self.dropped;
+            let current = self.rounds.last_mut().unwrap();
+            for game in current.iter_mut().filter(|game| !game.decided) {
+                let present: Vec<usize> = game.players.iter().flatten()
+                    .filter(|id| !dropped.contains(id))
+                    .copied()
+                    .collect();
+                if present.len() < 2 {
+                    game.decide(present.first().copied());
+                }
+            }
+            if current.len() < 2 || current.iter().any(|game| !game.decided) {
+                return;
+            }
+            let next = current.chunks(2)
+                .map(|pair| BracketMatch::new([pair[0].winner, pair[1].winner]))
+                .collect();
+            self.rounds.push(next);
+        }
+    }
+
+    /// `(place, id)` of every player from the first place, once the final is decided
+    ///
+    /// the players eliminated in the same round share their place ( e.g. 3rd for both semi-finals ),
+    /// and are listed in seed order. the players outside the cut follow in the order of the standings.
+    pub fn placings(&self) -> Option<Vec<(usize, usize)>> {
+        if !self.is_finished() {
+            return None;
+        }
+        let mut placings = Vec::new();
+        if let Some(champion) = self.rounds.last().unwrap()[0].winner {
+            placings.push((1, champion));
+        }
+        for round in self.rounds.iter().rev() {
+            let place = round.len() + 1;
+            let mut losers: Vec<usize> = round.iter()
+                .flat_map(|game| game.players.iter().flatten().filter(move |&&id| Some(id) != game.winner))
+                .copied()
+                .collect();
+            losers.sort_by_key(|id| self.seeds.iter().position(|seed| seed == id));
+            placings.extend(losers.into_iter().map(|id| (place, id)));
+        }
+        let cut = self.seeds.len();
+        placings.extend(self.others.iter().enumerate().map(|(i, &id)| (cut + 1 + i, id)));
+        Some(placings)
+    }
+}
+
+/// the part of a double-elimination bracket
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Side {
+    Winners,
+    Losers,
+    /// round 1 is the grand final, and round 2 is its reset
+    GrandFinal,
+}
+
+/// where the player of a slot comes from
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+enum Source {
+    /// the n-th seed ( 0-origin )
+    Seed(usize),
+    /// the winner of the n-th game
+    Winner(usize),
+    /// the loser of the n-th game
+    Loser(usize),
+}
+
+/// a game of a double-elimination bracket
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Game {
+    side: Side,
+    /// round in its side ( 1-origin )
+    round: usize,
+    sources: [Source; 2],
+    /// ids of the two players, once their sources are decided ( `None` is an empty slot )
+    players: [Option<usize>; 2],
+    decided: bool,
+    winner: Option<usize>,
+}
+
+impl Game {
+    fn new(side: Side, round: usize, sources: [Source; 2]) -> Self {
+        Game { side, round, sources, players: [None; 2], decided: false, winner: None }
+    }
+    pub fn side(&self) -> Side {
+        self.side
+    }
+    pub fn round(&self) -> usize {
+        self.round
+    }
+    pub fn players(&self) -> [Option<usize>; 2] {
+        self.players
+    }
+    pub fn is_decided(&self) -> bool {
+        self.decided
+    }
+    pub fn winner(&self) -> Option<usize> {
+        self.winner
+    }
+    /// the player who does not advance ( `None` if both players withdrew )
+    fn loser(&self) -> Option<usize> {
+        let winner = self.winner?;
+        self.players.iter().flatten().copied().find(|&id| id != winner)
+    }
+}
+
+/// double-elimination bracket: a player is out after the second loss
+///
+/// the losers of the winners' bracket drop into the losers' bracket, in the reversed order
+/// ( or with the halves swapped, alternately ) so that players do not meet again early.
+/// the winners of both brackets meet in the grand final, and if the bracket reset is on
+/// and the player from the losers' bracket wins it, the final is played again.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DoubleElimination {
+    /// ids of the players in the bracket, the first seed first
+    seeds: Vec<usize>,
+    /// ids of the players outside the bracket, in the order of the standings
+    others: Vec<usize>,
+    /// ids of the players who dropped from the bracket
+    dropped: Vec<usize>,
+    bracket_reset: bool,
+    /// every game, a game comes after the games of its sources
+    games: Vec<Game>,
+}
+
+fn push(games: &mut Vec<Game>, game: Game) -> usize {
+    games.push(game);
+    games.len() - 1
+}
+
+/// the games of the winners' bracket which lead to `game` ( including itself )
+fn games_under(games: &[Game], game: usize) -> Vec<usize> {
+    let mut under = vec![game];
+    for source in games[game].sources {
+        if let Source::Winner(from) = source {
+            under.extend(games_under(games, from));
+        }
+    }
+    under
+}
+
+/// the games of the winners' bracket whose losers can reach `game` of the losers' bracket
+fn dropped_from(games: &[Game], game: usize) -> Vec<usize> {
+    games[game].sources.iter()
+        .flat_map(|&source| match source {
+            Source::Loser(from) => vec![from],
+            Source::Winner(from) => dropped_from(games, from),
+            Source::Seed(_) => Vec::new(),
+        })
+        .collect()
+}
+
+/// the games of `round` of the winners' bracket in the order their losers meet the winners of `waiting`
+///
+/// the order is chosen from the reversed one, the one with the halves swapped and so on,
+/// as the one whose possible rematches come from the earliest rounds of the winners' bracket
+/// ( a player who lost later in the winners' bracket is more likely to be there ).
+fn drop_order(games: &[Game], waiting: &[usize], round: &[usize]) -> Vec<usize> {
+    let half = round.len() / 2;
+    let reversed: Vec<usize> = round.iter().rev().copied().collect();
+    let mut swapped = round.to_vec();
+    swapped.rotate_left(half);
+    let mut swapped_reversed = reversed.clone();
+    swapped_reversed.rotate_left(half);
+    // rounds of the possible rematches, the latest first
+    let rematches = |order: &Vec<usize>| -> Vec<usize> {
+        let mut rounds: Vec<usize> = waiting.iter().zip(order)
+            .flat_map(|(&lower, &upper)| {
+                let under = games_under(games, upper);
+                dropped_from(games, lower).into_iter().filter(move |game| under.contains(game))
+            })
+            .map(|game| games[game].round)
+            .collect();
+        rounds.sort_unstable_by(|a, b| b.cmp(a));
+        rounds
+    };
+    vec![reversed, swapped, swapped_reversed, round.to_vec()].into_iter()
+        .min_by_key(rematches)
+        .unwrap()
+}
+
+impl DoubleElimination {
+    /// seed the first `size` players of `standings` who have not dropped
+    ///
+    /// `size` must be a power of two ( 4, 8, 16, ... ) and not more than those players.
+    pub fn new(standings: &[&Player], size: usize, bracket_reset: bool) -> Result<Self, OpoError> {
+        let (seeds, others) = seed(standings, size, 4)?;
+        let mut games = Vec::new();
+
+        let mut winners: Vec<Vec<usize>> = vec![bracket_order(size)
+            .chunks(2)
+            .map(|pair| push(&mut games, Game::new(Side::Winners, 1, [Source::Seed(pair[0] - 1), Source::Seed(pair[1] - 1)])))
+            .collect()];
+        while winners.last().unwrap().len() > 1 {
+            let round = winners.len() + 1;
+            let next = winners.last().unwrap()
+                .chunks(2)
+                .map(|pair| push(&mut games, Game::new(Side::Winners, round, [Source::Winner(pair[0]), Source::Winner(pair[1])])))
+                .collect();
+            winners.push(next);
+        }
+
+        let mut round = 1;
+        let mut losers: Vec<usize> = winners[0]
+            .chunks(2)
+            .map(|pair| push(&mut games, Game::new(Side::Losers, round, [Source::Loser(pair[0]), Source::Loser(pair[1])])))
+            .collect();
+        for winners_round in winners.iter().skip(1) {
+            round += 1;
+            losers = losers.iter()
+                .zip(drop_order(&games, &losers, winners_round))
+                .map(|(&game, dropped)| push(&mut games, Game::new(Side::Losers, round, [Source::Winner(game), Source::Loser(dropped)])))
+                .collect();
+            if losers.len() > 1 {
+                round += 1;
+                losers = losers
+                    .chunks(2)
+                    .map(|pair| push(&mut games, Game::new(Side::Losers, round, [Source::Winner(pair[0]), Source::Winner(pair[1])])))
+                    .collect();
+            }
+        }
+        push(&mut games, Game::new(Side::GrandFinal, 1, [Source::Winner(winners.last().unwrap()[0]), Source::Winner(losers[0])]));
+
+        let mut bracket = DoubleElimination { seeds, others, dropped: Vec::new(), bracket_reset, games };
+        bracket.advance();
+        Ok(bracket)
+    }
+
+    pub fn seeds(&self) -> &[usize] {
+        &self.seeds
+    }
+
+    pub fn games(&self) -> &[Game] {
+        &self.games
+    }
+
+    /// the games whose players are both known and which are waiting for their result
+    pub fn ready(&self) -> Vec<&Game> {
+        self.games.iter()
+            .filter(|game| !game.decided && game.players.iter().all(Option::is_some))
+            .collect()
+    }
+
+    /// the last game ( the grand final or its reset ) has been decided
+    pub fn is_finished(&self) -> bool {
+        self.games.last().is_some_and(|game| game.decided)
+    }
+
+    /// enter the result of a ready game
+    ///
+    /// the winner of `matching` advances and the loser drops into the losers' bracket ( or is out ).
+    /// a withdrawn player loses by no-show, and both players are out if both withdrew.
+    /// a draw is refused, and a dropped matching drops the player ( see `drop_player` ).
+    pub fn report(&mut self, matching: &Matching) -> Result<(), OpoError> {
+        if matching.is_dropped() {
+            return self.drop_player(matching.player_id());
+        }
+        let (player_id, opponent_id) = (matching.player_id(), matching.opponent_id());
+        let game = self.games.iter_mut()
+            .find(|game| !game.decided && game.players.contains(&Some(player_id)) && game.players.contains(&Some(opponent_id)))
+            .ok_or(OpoError::NoSuchGame { player_id, opponent_id })?;
+        game.winner = advancing(matching)?;
+        game.decided = true;
+        self.advance();
+        Ok(())
+    }
+
+    /// drop the player from the bracket, the opponents advance by walkover
+    pub fn drop_player(&mut self, id: usize) -> Result<(), OpoError> {
+        if !self.seeds.contains(&id) {
+            return Err(OpoError::UnknownPlayer(id));
+        }
+        if !self.dropped.contains(&id) {
+            self.dropped.push(id);
+        }
+        self.advance();
+        Ok(())
+    }
+
+    /// the player of `source`, `None` while it is not decided
+    fn source(&self, source: Source) -> Option<Option<usize>> {
+        match source {
+            Source::Seed(seed) => Some(Some(self.seeds[seed])),
+            Source::Winner(game) => self.games[game].decided.then(|| self.games[game].winner),
+            Source::Loser(game) => self.games[game].decided.then(|| self.games[game].loser()),
+        }
+    }
+
+    /// the grand final was won by the player from the losers' bracket, who did not win by walkover
+    fn needs_reset(&self, final_game: &Game) -> bool {
+        self.bracket_reset
+            && final_game.side == Side::GrandFinal
+            && final_game.round == 1
+            && final_game.decided
+            && final_game.winner.is_some()
+            && final_game.winner == final_game.players[1]
+            && final_game.players[0].is_some_and(|id| !self.dropped.contains(&id))
+    }
+
+    /// fill the slots whose sources are decided, give walkovers, and add the reset of the grand final
+    fn advance(&mut self) {
+        loop {
+            for gi in 0..self.games.len() {
+                if self.games[gi].decided {
+                    continue;
+                }
+                let slots = self.games[gi].sources.map(|source| self.source(source));
+                let dropped = &self.dropped;
+                let game = &mut self.games[gi];
+                game.players = slots.map(Option::flatten);
+                if slots.iter().all(Option::is_some) {
+                    let present: Vec<usize> = game.players.iter().flatten()
+                        .filter(|id| !dropped.contains(id))
+                        .copied()
+                        .collect();
+                    if present.len() < 2 {
+                        game.decided = true;
+                        game.winner = present.first().copied();
+                    }
+                }
+            }
+            let last = self.games.len() - 1;
+            if !self.needs_reset(&self.games[last]) {
+                return;
+            }
+            self.games.push(Game::new(Side::GrandFinal, 2, [Source::Loser(last), Source::Winner(last)]));
+        }
+    }
+
+    /// players who are out after `game`
+    fn eliminated(&self, gi: usize) -> Vec<usize> {
+        let game = &self.games[gi];
+        if !game.decided {
+            return Vec::new();
+        }
+        let moves_on = match game.side {
+            Side::Winners => game.loser(),
+            Side::GrandFinal if gi + 1 < self.games.len() => game.loser(),
+            _ => None,
+        };
+        game.players.iter().flatten()
+            .copied()
+            .filter(|&id| Some(id) != game.winner && Some(id) != moves_on)
+            .collect()
+    }
+
+    /// `(place, id)` of every player from the first place, once the bracket is finished
+    ///
+    /// the players who are out in the same round share their place ( e.g. 5th for both losers
+    /// of the third round of the losers' bracket of 8 ), and are listed in seed order.
+    /// the players outside the bracket follow in the order of the standings.
+    pub fn placings(&self) -> Option<Vec<(usize, usize)>> {
+        if !self.is_finished() {
+            return None;
+        }
+        let mut placings = Vec::new();
+        if let Some(champion) = self.games.last().unwrap().winner {
+            placings.push((1, champion));
+        }
+        let mut rounds: Vec<(Side, usize, Vec<usize>)> = Vec::new();
+        for (gi, game) in self.games.iter().enumerate() {
+            let eliminated = self.eliminated(gi);
+            match rounds.iter_mut().find(|(side, round, _)| *side == game.side && *round == game.round) {
+                Some((_, _, out)) => out.extend(eliminated),
+                None => rounds.push((game.side, game.round, eliminated)),
+            }
+        }
+        rounds.sort_by_key(|(side, round, _)| Reverse((*side, *round)));
+        for (_, _, mut out) in rounds {
+            out.sort_by_key(|id| self.seeds.iter().position(|seed| seed == id));
+            let place = placings.len() + 1;
+            placings.extend(out.into_iter().map(|id| (place, id)));
+        }
+        let size = self.seeds.len();
+        placings.extend(self.others.iter().enumerate().map(|(i, &id)| (size + 1 + i, id)));
+        Some(placings)
+    }
+}
+
+#[cfg(test)]
+fn seeded_players(number: usize) -> Vec<Player> {
+    (0..number).map(|i| Player::new(i, format!("{}abcd", i))).collect()
+}
+
+#[cfg(test)]
+fn won(player_id: usize, opponent_id: usize) -> Matching {
+    Matching::new(1, player_id, opponent_id, 2, 0, 1, false, false).unwrap()
+}
+
+#[test]
+fn test_bracket_order() {
+    assert_eq!(bracket_order(2), vec![1, 2]);
+    assert_eq!(bracket_order(4), vec![1, 4, 2, 3]);
+    assert_eq!(bracket_order(8), vec![1, 8, 4, 5, 2, 7, 3, 6]);
+    assert_eq!(bracket_order(16)[..4], [1, 16, 8, 9]);
+}
+
+#[test]
+fn test_single_elimination() {
+    let players = seeded_players(10);
+    let standings: Vec<&Player> = players.iter().collect();
+    assert!(matches!(SingleElimination::new(&standings, 6), Err(OpoError::InvalidBracketSize(6))));
+    assert!(matches!(SingleElimination::new(&standings, 16), Err(OpoError::NotEnoughPlayers { required: 16, available: 10 })));
+    let mut bracket = SingleElimination::new(&standings, 8).unwrap();
+    assert_eq!(bracket.pairings(), vec![(0, 7), (3, 4), (1, 6), (2, 5)]);
+
+    assert!(matches!(bracket.report(&won(0, 1)), Err(OpoError::NoSuchGame { player_id: 0, opponent_id: 1 })));
+    assert!(matches!(bracket.report(&Matching::new(1, 0, 7, 1, 0, 1, false, false).unwrap()), Err(OpoError::NoWinner { .. })));
+    bracket.report(&won(0, 7)).unwrap();
+    bracket.report(&won(4, 3)).unwrap();
+    bracket.report(&won(6, 1)).unwrap();
+    assert_eq!(bracket.round_number(), 1);
+    assert!(bracket.placings().is_none());
+    bracket.report(&won(2, 5)).unwrap();
+    assert_eq!(bracket.round_number(), 2);
+    assert_eq!(bracket.pairings(), vec![(0, 4), (6, 2)]);
+    bracket.report(&won(4, 0)).unwrap();
+    bracket.report(&won(2, 6)).unwrap();
+    assert_eq!(bracket.pairings(), vec![(4, 2)]);
+    bracket.report(&won(2, 4)).unwrap();
+    assert!(bracket.is_finished());
+    assert_eq!(bracket.placings().unwrap(), vec![
+        (1, 2), (2, 4), (3, 0), (3, 6), (5, 1), (5, 3), (5, 5), (5, 7), (9, 8), (10, 9),
+    ]);
+}
+
+#[test]
+fn test_single_elimination_drops_and_no_shows() {
+    let mut players = seeded_players(5);
+    players[1] = Player::dummy(1);
+    let standings: Vec<&Player> = players.iter().collect();
+    // 1 dropped in the Swiss rounds, so 4 takes the last seed
+    let mut bracket = SingleElimination::new(&standings, 4).unwrap();
+    assert_eq!(bracket.seeds(), &[0, 2, 3, 4]);
+    assert_eq!(bracket.pairings(), vec![(0, 4), (2, 3)]);
+    // 4 did not show up
+    bracket.report(&Matching::new(1, 0, 4, 0, 0, 0, false, true).unwrap()).unwrap();
+    // 2 drops before the game
+    bracket.report(&Matching::dropped_new(1, 2)).unwrap();
+    assert_eq!(bracket.pairings(), vec![(0, 3)]);
+    // both players of the final did not show up
+    bracket.report(&Matching::new(2, 0, 3, 0, 0, 0, true, true).unwrap()).unwrap();
+    assert!(bracket.is_finished());
+    assert_eq!(bracket.placings().unwrap(), vec![(2, 0), (2, 3), (3, 2), (3, 4), (5, 1)]);
+    assert!(matches!(bracket.drop_player(1), Err(OpoError::UnknownPlayer(1))));
+}
+
+#[cfg(test)]
+fn play_out(bracket: &mut DoubleElimination) -> Vec<(usize, usize)> {
+    // the higher seed ( the smaller id ) always wins, and every game is returned
+    let mut played = Vec::new();
+    while let Some(game) = bracket.ready().first() {
+        let [a, b] = game.players();
+        let (a, b) = (a.unwrap().min(b.unwrap()), a.unwrap().max(b.unwrap()));
+        played.push((a, b));
+        bracket.report(&won(a, b)).unwrap();
+    }
+    played
+}
+
+#[test]
+fn test_double_elimination_structure() {
+    let players = seeded_players(8);
+    let standings: Vec<&Player> = players.iter().collect();
+    assert!(DoubleElimination::new(&standings, 2, true).is_err());
+    let bracket = DoubleElimination::new(&standings, 8, true).unwrap();
+    let count = |side: Side| bracket.games().iter().filter(|g| g.side() == side).count();
+    assert_eq!((count(Side::Winners), count(Side::Losers), count(Side::GrandFinal)), (7, 6, 1));
+    assert_eq!(bracket.games().iter().map(|g| g.round()).max(), Some(4));
+    let ready: Vec<[Option<usize>; 2]> = bracket.ready().iter().map(|g| g.players()).collect();
+    assert_eq!(ready, vec![[Some(0), Some(7)], [Some(3), Some(4)], [Some(1), Some(6)], [Some(2), Some(5)]]);
+}
+
+#[test]
+fn test_double_elimination_no_early_rematch() {
+    for size in [4, 8, 16, 32, 64] {
+        let players = seeded_players(size);
+        let standings: Vec<&Player> = players.iter().collect();
+        let mut bracket = DoubleElimination::new(&standings, size, true).unwrap();
+        let played = play_out(&mut bracket);
+        assert!(bracket.is_finished());
+        assert_eq!(played.len(), 2 * size - 2);
+        // seed 2 and seed 3 meet again in the final of the losers' bracket, and seeds 1 and 2 in the grand final
+        let (early, finals) = played.split_at(played.len() - 2);
+        assert!(early.iter().all(|pair| early.iter().filter(|p| *p == pair).count() == 1));
+        assert_eq!(finals, &[(1, 2), (0, 1)]);
+    }
+}
+
+#[test]
+fn test_double_elimination_reset_and_placings() {
+    let players = seeded_players(4);
+    let standings: Vec<&Player> = players.iter().collect();
+    let mut bracket = DoubleElimination::new(&standings, 4, true).unwrap();
+    bracket.report(&won(0, 3)).unwrap();
+    bracket.report(&won(1, 2)).unwrap();
+    bracket.report(&won(3, 2)).unwrap();
+    bracket.report(&won(0, 1)).unwrap();
+    bracket.report(&won(1, 3)).unwrap();
+    // the player from the losers' bracket wins the grand final, so it is played again
+    bracket.report(&won(1, 0)).unwrap();
+    assert!(!bracket.is_finished());
+    assert_eq!(bracket.ready()[0].side(), Side::GrandFinal);
+    assert_eq!(bracket.ready()[0].round(), 2);
+    bracket.report(&won(1, 0)).unwrap();
+    assert!(bracket.is_finished());
+    assert_eq!(bracket.placings().unwrap(), vec![(1, 1), (2, 0), (3, 3), (4, 2)]);
+
+    // without the reset, the grand final decides
+    let mut bracket = DoubleElimination::new(&standings, 4, false).unwrap();
+    for (a, b) in [(0, 3), (1, 2), (3, 2), (0, 1), (1, 3), (1, 0)] {
+        bracket.report(&won(a, b)).unwrap();
+    }
+    assert!(bracket.is_finished());
+    assert_eq!(bracket.games().len(), 6);
+    assert_eq!(bracket.placings().unwrap()[..2], [(1, 1), (2, 0)]);
+}
+
+#[test]
+fn test_double_elimination_drops() {
+    let players = seeded_players(9);
+    let standings: Vec<&Player> = players.iter().collect();
+    let mut bracket = DoubleElimination::new(&standings, 8, true).unwrap();
+    assert!(bracket.report(&Matching::new(1, 0, 7, 1, 1, 1, false, false).unwrap()).is_err());
+    // 7 does not show up, and then drops, so 4 advances without playing in the losers' bracket
+    bracket.report(&Matching::new(1, 0, 7, 0, 0, 0, false, true).unwrap()).unwrap();
+    bracket.report(&Matching::dropped_new(1, 7)).unwrap();
+    bracket.report(&won(3, 4)).unwrap();
+    assert!(bracket.ready().iter().all(|g| g.side() == Side::Winners));
+    // 1 and 6 both do not show up, and both are out
+    bracket.report(&Matching::new(1, 1, 6, 0, 0, 0, true, true).unwrap()).unwrap();
+    bracket.report(&won(2, 5)).unwrap();
+    let played = play_out(&mut bracket);
+    assert!(played.iter().all(|&(a, b)| ![1, 6, 7].contains(&a) && ![1, 6, 7].contains(&b)));
+    let placings = bracket.placings().unwrap();
+    assert_eq!(placings.len(), 9);
+    assert_eq!(placings[0], (1, 0));
+    assert_eq!(placings[8], (9, 8));
+    assert_eq!(placings[5..8], [(6, 7), (7, 1), (7, 6)]);
+}
@@ -0,0 +1,150 @@
+//! errors of the tournament model
+
+use super::csv::RowError;
+use super::swiss_system_tournament::RoundState;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum OpoError {
+    /// no player has the id
+    UnknownPlayer(usize),
+    /// a matching whose player and opponent are the same player
+    SelfPairing(usize),
+    /// the player has more than one result in the round
+    DuplicateResult { round_number: i32, player_id: usize },
+    /// no pairing satisfies the absolute criteria ( no rematch and so on )
+    PairingInfeasible,
+    /// the Dutch pairing examined as many candidates in a bracket of the players as it may,
+    /// without knowing that the best one found is the one of C.04.3
+    PairingSearchExhausted { bracket_size: usize },
+    /// negative counts, or no game in a matching which nobody withdrew
+    InvalidGameCounts { win_count: i32, draw_count: i32, lose_count: i32 },
+    /// an elimination game was reported as a draw
+    NoWinner { player_id: usize, opponent_id: usize },
+    /// no game between the players is waiting for its result
+    NoSuchGame { player_id: usize, opponent_id: usize },
+    /// the action is not allowed in the state of the round lifecycle
+    InvalidState { state: RoundState, action: &'static str },
+    /// a result which does not match the published pairings ( `None` is no opponent )
+    NotPaired { player_id: usize, opponent_id: Option<usize> },
+    /// a table which is not a feature table
+    NotFeatureTable(usize),
+    /// a result of another round than the current one
+    WrongRound { expected: i32, found: i32 },
+    /// the player has no result in the submitted round
+    MissingResult(usize),
+    /// no result of the player is recorded in the round
+    NoSuchResult { round_number: i32, player_id: usize },
+    /// a bracket size which is not a power of two ( or too small )
+    InvalidBracketSize(usize),
+    /// a number of groups which is zero
+    InvalidGroupNumber(usize),
+    /// points of a scoring system which are not finite, or a win without points
+    InvalidScoring(&'static str),
+    NotEnoughPlayers { required: usize, available: usize },
+    /// a saved tournament without the version or the tournament
+    InvalidFormat(String),
+    /// a saved tournament of a newer format
+    UnsupportedVersion(u64),
+    /// no event of the name is saved
+    NoSuchEvent(String),
+    /// an event of the name exists already
+    EventExists(String),
+    /// rows of an imported CSV which are invalid ( nothing was imported )
+    InvalidRows(Vec<RowError>),
+    Json(serde_json::Error),
+    Io(std::io::Error),
+    Csv(::csv::Error),
+    #[cfg(feature = "sqlite")]
+    Sqlite(rusqlite::Error),
+}
+
+impl fmt::Display for OpoError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OpoError::UnknownPlayer(id) => write!(f, "unknown player: {}", id),
+            OpoError::SelfPairing(id) => write!(f, "player {} is paired with themselves", id),
+            OpoError::DuplicateResult { round_number, player_id } =>
+                write!(f, "player {} has more than one result in round {}", player_id, round_number),
+            OpoError::PairingInfeasible => write!(f, "no pairing satisfies the criteria"),
+            OpoError::PairingSearchExhausted { bracket_size } =>
+                write!(f, "the Dutch pairing of a bracket of {} players needs too many candidates", bracket_size),
+            OpoError::InvalidGameCounts { win_count, draw_count, lose_count } =>
+                write!(f, "invalid game counts: {}-{}-{}", win_count, draw_count, lose_count),
+            OpoError::NoWinner { player_id, opponent_id } =>
+                write!(f, "the game between {} and {} needs a winner", player_id, opponent_id),
+            OpoError::NoSuchGame { player_id, opponent_id } =>
+                write!(f, "no game between {} and {} is waiting for its result", player_id, opponent_id),
+            OpoError::InvalidState { state, action } => write!(f, "can not {} in {:?}", action, state),
+            OpoError::NotPaired { player_id, opponent_id: Some(opponent_id) } =>
+                write!(f, "player {} is not paired with {}", player_id, opponent_id),
+            OpoError::NotPaired { player_id, opponent_id: None } =>
+                write!(f, "player {} is not without opponent", player_id),
+            OpoError::NotFeatureTable(number) => write!(f, "table {} is not a feature table", number),
+            OpoError::WrongRound { expected, found } => write!(f, "result of round {} in round {}", found, expected),
+            OpoError::MissingResult(id) => write!(f, "no result for player {}", id),
+            OpoError::NoSuchResult { round_number, player_id } =>
+                write!(f, "player {} has no result in round {}", player_id, round_number),
+            OpoError::InvalidBracketSize(size) => write!(f, "invalid bracket size: {}", size),
+            OpoError::InvalidGroupNumber(number) => write!(f, "invalid number of groups: {}", number),
+            OpoError::InvalidScoring(reason) => write!(f, "invalid scoring: {}", reason),
+            OpoError::NotEnoughPlayers { required, available } =>
+                write!(f, "not enough players: {} required, {} available", required, available),
+            OpoError::InvalidFormat(message) => write!(f, "invalid format: {}", message),
+            OpoError::UnsupportedVersion(version) => write!(f, "unsupported format version: {}", version),
+            OpoError::NoSuchEvent(name) => write!(f, "no such event: {}", name),
+            OpoError::EventExists(name) => write!(f, "the event exists already: {}", name),
+            OpoError::InvalidRows(errors) => {
+                write!(f, "invalid rows:")?;
+                for error in errors {
+                    write!(f, " {};", error)?;
+                }
+                Ok(())
+            }
+            OpoError::Json(e) => write!(f, "JSON error: {}", e),
+            OpoError::Io(e) => write!(f, "I/O error: {}", e),
+            OpoError::Csv(e) => write!(f, "CSV error: {}", e),
+            #[cfg(feature = "sqlite")]
+            OpoError::Sqlite(e) => write!(f, "SQLite error: {}", e),
+        }
+    }
+}
+
+impl Error for OpoError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            OpoError::Json(e) => Some(e),
+            OpoError::Io(e) => Some(e),
+            OpoError::Csv(e) => Some(e),
+            #[cfg(feature = "sqlite")]
+            OpoError::Sqlite(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for OpoError {
+    fn from(e: serde_json::Error) -> Self {
+        OpoError::Json(e)
+    }
+}
+
+impl From<std::io::Error> for OpoError {
+    fn from(e: std::io::Error) -> Self {
+        OpoError::Io(e)
+    }
+}
+
+impl From<::csv::Error> for OpoError {
+    fn from(e: ::csv::Error) -> Self {
+        OpoError::Csv(e)
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl From<rusqlite::Error> for OpoError {
+    fn from(e: rusqlite::Error) -> Self {
+        OpoError::Sqlite(e)
+    }
+}
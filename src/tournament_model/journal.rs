@@ -0,0 +1,39 @@
+//! the append-only journal of the operations on a tournament
+//!
+//! every operation which changes a `Tournament` appends an event to its journal,
+//! and `Tournament::replay` rebuilds the tournament from the events ( all of them, or the first ones ).
+
+use super::color::Color;
+use super::matching::Matching;
+use super::players::Player;
+use super::scoring::ScoringSystem;
+use super::swiss_system_tournament::PairingSystem;
+use super::tables::TableSettings;
+use serde::{Deserialize, Serialize};
+
+/// an operation on a tournament, in the order it was done
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    PlayerAdded { player: Player },
+    PlayerDropped { player_id: usize },
+    PairingSystemChanged { pairing_system: PairingSystem },
+    /// the tiebreakers by name ( only the builtin ones can be replayed )
+    TiebreaksChanged { tiebreaks: Vec<String> },
+    ScoringChanged { scoring: ScoringSystem },
+    TableSettingsChanged { settings: TableSettings },
+    ByeAssigned { round_number: i32, player_id: usize, points: Option<f64> },
+    ByeUnassigned { round_number: i32, player_id: usize },
+    /// results added by `Tournament::aggregate_matches`, without pairings
+    ResultsImported { matches: Vec<Matching> },
+    /// the pairings as they were published, so that a replay does not pair again
+    PairingPublished { round_number: i32, pairings: Vec<Option<usize>>, colors: Vec<Option<Color>> },
+    PairingWithdrawn { round_number: i32 },
+    RoundStarted { round_number: i32 },
+    FeatureMatchSet { round_number: i32, table_number: usize, player_id: usize },
+    ResultEntered { matching: Matching },
+    RoundClosed { round_number: i32 },
+    ResultCorrected { matching: Matching },
+    ResultDeleted { round_number: i32, player_id: usize },
+    Finished,
+}
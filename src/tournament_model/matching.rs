@@ -1,5 +1,10 @@
+use super::color::Color;
+use super::error::OpoError;
+use super::scoring::ScoringSystem;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone,Debug)]
+
+#[derive(Clone,Debug,Serialize,Deserialize)]
 /// model of matching result
 pub struct Matching {
     ///// uid of match
@@ -24,25 +29,63 @@ pub struct Matching {
     no_opponent: bool,
     /// player has dropped? ( dropped player matching is not count as round )
     dropped: bool,
+    /// the colour ( or play/draw seat ) of the player, if the game has first-move advantage
+    color: Option<Color>,
+    /// the points of a bye, when they are not the bye points of the scoring ( e.g. a penalty )
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    bye_points: Option<f64>,
 }
 
 impl Matching {
-    pub fn new(round_number: i32, player_id: usize, opponent_id: usize, win_count: i32, draw_count: i32, lose_count: i32, player_withdraw: bool, opponent_withdraw: bool ) -> Self {
+    /// the counts must not be negative, and there must be a game unless someone withdrew
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(round_number: i32, player_id: usize, opponent_id: usize, win_count: i32, draw_count: i32, lose_count: i32, player_withdraw: bool, opponent_withdraw: bool ) -> Result<Self, OpoError> {
         if player_id == opponent_id {
-            panic!("player and opponent have same id!")
+            return Err(OpoError::SelfPairing(player_id));
+        }
+        let negative = win_count < 0 || draw_count < 0 || lose_count < 0;
+        let no_game = win_count + draw_count + lose_count == 0 && !player_withdraw && !opponent_withdraw;
+        if negative || no_game {
+            return Err(OpoError::InvalidGameCounts { win_count, draw_count, lose_count });
         }
-        Matching { round_number, player_id, opponent_id, win_count, draw_count, lose_count, player_withdraw, opponent_withdraw, no_opponent: false, dropped: false }
+        Ok(Matching { round_number, player_id, opponent_id, win_count, draw_count, lose_count, player_withdraw, opponent_withdraw, no_opponent: false, dropped: false, color: None, bye_points: None })
     }
     /// give to no opponent player
     pub fn no_opponent_new(round_number: i32, player_id: usize ) -> Self {
-        Matching { round_number, player_id, opponent_id: 0, win_count: 0, draw_count: 0, lose_count: 0, player_withdraw: false, opponent_withdraw: false, no_opponent: true, dropped: false }
+        Matching { round_number, player_id, opponent_id: 0, win_count: 0, draw_count: 0, lose_count: 0, player_withdraw: false, opponent_withdraw: false, no_opponent: true, dropped: false, color: None, bye_points: None }
     }
     /// give to dropped player
     pub fn dropped_new(round_number: i32, player_id: usize ) -> Self {
-        Matching { round_number, player_id, opponent_id: 0, win_count: 0, draw_count: 0, lose_count: 0, player_withdraw: true, opponent_withdraw: false, no_opponent: false, dropped: true }
+        Matching { round_number, player_id, opponent_id: 0, win_count: 0, draw_count: 0, lose_count: 0, player_withdraw: true, opponent_withdraw: false, no_opponent: false, dropped: true, color: None, bye_points: None }
     }
     pub fn rev(m: &Matching) -> Self {
-        Self::new(m.round_number, m.opponent_id, m.player_id, m.lose_count, m.draw_count, m.win_count, m.opponent_withdraw, m.player_withdraw)
+        Matching {
+            player_id: m.opponent_id,
+            opponent_id: m.player_id,
+            win_count: m.lose_count,
+            lose_count: m.win_count,
+            player_withdraw: m.opponent_withdraw,
+            opponent_withdraw: m.player_withdraw,
+            color: m.color.map(Color::opposite),
+            ..m.clone()
+        }
+    }
+    /// set the colour of the player ( the opponent has the opposite one )
+    pub fn set_color(&mut self, color: Option<Color>) {
+        self.color = color;
+    }
+    pub fn color(&self) -> Option<Color> {
+        self.color
+    }
+    /// set the points of a no-opponent matching ( `None` for the bye points of the scoring )
+    pub fn set_bye_points(&mut self, points: Option<f64>) {
+        self.bye_points = points;
+    }
+    pub fn bye_points(&self) -> Option<f64> {
+        self.bye_points
+    }
+    pub fn round_number(&self) -> i32 {
+        self.round_number
     }
     pub fn player_id(&self) -> usize {
         self.player_id
@@ -65,7 +108,7 @@ impl Matching {
     }
     /// did only opponent withdraw or is win greater than lose
     pub fn is_win(&self) -> bool {
-        self.no_opponent || ( !self.is_draw() && !self.player_withdraw && ( self.opponent_withdraw || !self.player_withdraw && self.win_count > self.lose_count ))
+        self.no_opponent || ( !self.is_draw() && !self.player_withdraw && ( self.opponent_withdraw || self.win_count > self.lose_count ))
     }
     /// did only player withdraw or is win less than lose
     pub fn is_lose(&self) -> bool {
@@ -86,72 +129,125 @@ impl Matching {
     pub fn is_reversible(&self) -> bool {
         !self.dropped && !self.no_opponent
     }
-    pub fn matching_points(&self) -> i32 {
-        if self.is_win() {
-            3
+    /// points of the player by `scoring`
+    ///
+    /// a dropped matching has no point, and a matching which both players withdrew is a draw.
+    pub fn matching_points(&self, scoring: &ScoringSystem) -> f64 {
+        if self.dropped {
+            0.0
+        } else if self.no_opponent {
+            self.bye_points.unwrap_or(scoring.bye())
+        } else if self.player_withdraw && !self.opponent_withdraw {
+            scoring.forfeit_loss()
+        } else if self.opponent_withdraw && !self.player_withdraw {
+            scoring.forfeit_win()
+        } else if self.is_win() {
+            scoring.win()
         } else if self.is_draw() {
-            1
+            scoring.draw()
         } else {
-            0
+            scoring.loss()
         }
     }
-    pub fn game_win_percentage(&self) -> f64 {
-        ( self.win_count * 3 + self.draw_count ) as f64 / ( self.win_count + self.draw_count + self.lose_count ) as f64 / 3.0
+    /// game points over the maximum game points, each game is scored by `scoring` ( a no-opponent is a 2-0 win )
+    pub fn game_win_percentage(&self, scoring: &ScoringSystem) -> f64 {
+        let (win_count, draw_count, lose_count) = if self.no_opponent {
+            (2, 0, 0)
+        } else {
+            (self.win_count, self.draw_count, self.lose_count)
+        };
+        let game_points = win_count as f64 * scoring.win() + draw_count as f64 * scoring.draw() + lose_count as f64 * scoring.loss();
+        game_points / ( win_count + draw_count + lose_count ) as f64 / scoring.max_points()
     }
 }
 
 #[test]
 fn test_is_avail() {
-    let m = Matching::new(0, 0, 1, 10, 0, 10, false, false);
+    let m = Matching::new(0, 0, 1, 10, 0, 10, false, false).unwrap();
     assert!( m.is_valid() );
-    let m = Matching::new(0, 0, 1, 10, 0, 10, true, false);
+    let m = Matching::new(0, 0, 1, 10, 0, 10, true, false).unwrap();
     assert!( !m.is_valid() );
-    let m = Matching::new(0, 0, 1, 10, 0, 10, true, true);
+    let m = Matching::new(0, 0, 1, 10, 0, 10, true, true).unwrap();
     assert!( !m.is_valid() );
-    let m = Matching::new(0, 0, 1, 10, 0, 10, false, true);
+    let m = Matching::new(0, 0, 1, 10, 0, 10, false, true).unwrap();
     assert!( !m.is_valid() );
 }
 
 #[test]
 fn test_result() {
-    let m = Matching::new(0, 0, 1, 10, 0, 10, false, false);
+    let s = ScoringSystem::default();
+    let m = Matching::new(0, 0, 1, 10, 0, 10, false, false).unwrap();
     assert!(m.is_draw());
     assert!(!m.is_win());
     assert!(!m.is_lose());
-    assert_eq!(m.matching_points(), 1);
-    let m = Matching::new(0, 0, 1, 10, 0, 5, false, false);
+    assert_eq!(m.matching_points(&s), 1.0);
+    let m = Matching::new(0, 0, 1, 10, 0, 5, false, false).unwrap();
     assert!(!m.is_draw());
     assert!(m.is_win());
     assert!(!m.is_lose());
-    assert_eq!(m.matching_points(), 3);
-    let m = Matching::new(0, 0, 1, 5, 0, 10, false, false);
+    assert_eq!(m.matching_points(&s), 3.0);
+    let m = Matching::new(0, 0, 1, 5, 0, 10, false, false).unwrap();
     assert!(!m.is_draw());
     assert!(!m.is_win());
     assert!(m.is_lose());
-    assert_eq!(m.matching_points(), 0);
-    let m = Matching::new(0, 0, 1, 10, 0, 10, true, false);
+    assert_eq!(m.matching_points(&s), 0.0);
+    let m = Matching::new(0, 0, 1, 10, 0, 10, true, false).unwrap();
     assert!(!m.is_draw());
     assert!(!m.is_win());
     assert!(m.is_lose());
-    assert_eq!(m.matching_points(), 0);
-    let m = Matching::new(0, 0, 1, 10, 0, 10, false, true);
+    assert_eq!(m.matching_points(&s), 0.0);
+    let m = Matching::new(0, 0, 1, 10, 0, 10, false, true).unwrap();
     assert!(!m.is_draw());
     assert!(m.is_win());
     assert!(!m.is_lose());
-    assert_eq!(m.matching_points(), 3);
-    let m = Matching::new(0, 0, 1, 10, 0, 10, true, true);
+    assert_eq!(m.matching_points(&s), 3.0);
+    let m = Matching::new(0, 0, 1, 10, 0, 10, true, true).unwrap();
     assert!(m.is_draw());
     assert!(!m.is_win());
     assert!(!m.is_lose());
-    assert_eq!(m.matching_points(), 1);
+    assert_eq!(m.matching_points(&s), 1.0);
     let m = Matching::no_opponent_new(0,0);
     assert!(!m.is_draw());
     assert!(m.is_win());
     assert!(!m.is_lose());
-    assert_eq!(m.matching_points(), 3);
+    assert_eq!(m.matching_points(&s), 3.0);
     let m = Matching::dropped_new(0,0);
     assert!(!m.is_draw());
     assert!(!m.is_win());
     assert!(m.is_lose());
-    assert_eq!(m.matching_points(), 0);
+    assert_eq!(m.matching_points(&s), 0.0);
+}
+
+#[test]
+fn test_scoring() {
+    let chess = ScoringSystem::chess();
+    assert_eq!(Matching::new(0, 0, 1, 1, 0, 0, false, false).unwrap().matching_points(&chess), 1.0);
+    assert_eq!(Matching::new(0, 0, 1, 0, 1, 0, false, false).unwrap().matching_points(&chess), 0.5);
+    assert_eq!(Matching::new(0, 0, 1, 0, 0, 1, false, false).unwrap().matching_points(&chess), 0.0);
+    assert_eq!(Matching::new(0, 0, 1, 2, 1, 0, false, false).unwrap().game_win_percentage(&chess), 2.5 / 3.0);
+    let custom = ScoringSystem::new(3.0, 1.0, 0.0, 2.0, 3.0, -1.0).unwrap();
+    assert_eq!(Matching::no_opponent_new(0, 0).matching_points(&custom), 2.0);
+    assert_eq!(Matching::new(0, 0, 1, 0, 0, 0, true, false).unwrap().matching_points(&custom), -1.0);
+    assert_eq!(Matching::new(0, 0, 1, 0, 0, 0, false, true).unwrap().matching_points(&custom), 3.0);
+    assert_eq!(Matching::new(0, 0, 1, 0, 0, 0, true, true).unwrap().matching_points(&custom), 1.0);
+    assert_eq!(Matching::dropped_new(0, 0).matching_points(&custom), 0.0);
+}
+
+#[test]
+fn test_color() {
+    let mut m = Matching::new(0, 0, 1, 1, 0, 0, false, false).unwrap();
+    assert_eq!(m.color(), None);
+    assert_eq!(Matching::rev(&m).color(), None);
+    m.set_color(Some(Color::White));
+    assert_eq!(m.color(), Some(Color::White));
+    assert_eq!(Matching::rev(&m).color(), Some(Color::Black));
+}
+
+#[test]
+fn test_invalid_matching() {
+    assert!(matches!(Matching::new(1, 3, 3, 2, 0, 0, false, false), Err(OpoError::SelfPairing(3))));
+    assert!(matches!(Matching::new(1, 0, 1, -1, 0, 2, false, false), Err(OpoError::InvalidGameCounts { win_count: -1, .. })));
+    assert!(matches!(Matching::new(1, 0, 1, 0, 0, 0, false, false), Err(OpoError::InvalidGameCounts { .. })));
+    // no game is played when someone withdrew
+    assert!(Matching::new(1, 0, 1, 0, 0, 0, true, false).is_ok());
 }
@@ -1,155 +1,229 @@
+use super::color::ColorPreference;
+use super::error::OpoError;
 use super::players::Player;
-use itertools::Itertools;
-
-pub fn matching_build(players: &Vec<Player>) -> Result<Vec<Option<usize>>, String> {
+use super::scoring::{scaled_points, ScoringSystem};
+use super::tiebreak::{standings, Tiebreak};
+use super::weighted_matching::{optimal_perfect_matching, PerfectMatching};
+use std::collections::BTreeMap;
+
+/// build a matching which minimises the sum of point differences between paired players
+///
+/// the players are paired as a minimum-cost perfect matching ( by the blossom algorithm ),
+/// where the cost of a pairing is the point difference of the two players.
+/// players who have already matched can not be paired again,
+/// and players who have the same absolute colour preference can not be paired.
+/// the bye of an odd field follows `pair_with_bye`.
+///
+/// first, only the players within a small window of each other's rank are connected,
+/// and the window widens until its matching is proved to be the minimum of the whole field ( see `min_cost_pairs` ).
+pub fn matching_build(players: &[Player], tiebreaks: &[Box<dyn Tiebreak>], scoring: &ScoringSystem) -> Result<Vec<Option<usize>>, OpoError> {
+    let matchable_players = filter_sorted_matchable_players(players, tiebreaks, scoring);
+    pair_with_bye(players.len(), &matchable_players, min_cost_pairs)
+}
 
-    let mut matchable_players = filter_sorted_matchable_players(players);
-    let dummy_player = Player::dummy(usize::max_value());
-    if matchable_players.len() % 2 == 1 {
-        matchable_players.push(&dummy_player);
-    }
-    let matchable_number: usize = matchable_players.len();
-
-    for ext in 6..26 {
-        let mut dp = vec![vec![i32::max_value(); 1 << ext]; matchable_number+1];
-        let mut rb = vec![vec![(-1,false); 1 << ext]; matchable_number+1];
-        dp[0][0] = 0;
-        for ni in 0..matchable_number+1 {
-            for bi in 0..(1<<ext) {
-                if dp[ni][bi] == i32::max_value() {
-                    continue;
-                }
-                let nni = ni + 1;
-                if bi & 1 == 1 {
-                    if dp[ni][bi] < dp[nni][bi >> 1] {
-                        dp[nni][bi >> 1] = dp[ni][bi];
-                        rb[nni][bi >> 1] = (bi as i32, false);
-                    }
-                    continue;
-                }
-                if ni == matchable_number {
-                    break;
-                }
-                for pi in 0..ext {
-                    let ppi = ni + pi + 1;
-                    if ppi >= matchable_number {
-                        break;
-                    }
-                    if ( (bi >> 1) & (1 << pi) ) != 0 {
-                        continue;
-                    }
-                    let player = matchable_players[ni];
-                    let opponent = matchable_players[ppi];
-                    let bbi = (bi >> 1) | (1 << pi);
-                    let mut cost = player.points();
-                    let opponent_id = if opponent.id() != usize::max_value() {
-                        cost -= opponent.points();
-                        Some(opponent.id())
-                    } else {
-                        None
-                    };
-                    if !player.had_matched_id(opponent_id) {
-                        if dp[ni][bi] + cost < dp[nni][bbi] {
-                            dp[nni][bbi] = dp[ni][bi] + cost;
-                            rb[nni][bbi] = (bi as i32, true);
-                        }
-                    }
+/// the pairs of an even number of players as indexes, by the minimum-cost perfect matching of `matching_build`
+///
+/// the matching of a window is the global minimum when it costs no more than pairing the neighbours by points ( the minimum without the bans ),
+/// or when no pair outside the window could make it cheaper ( a negative reduced cost against the duals of the window's matching,
+/// see `PerfectMatching::could_improve` ). otherwise the window doubles, up to the whole field.
+fn min_cost_pairs(players: &[&Player]) -> Option<Vec<(usize, usize)>> {
+    let matchable_number = players.len();
+    let preferences: Vec<ColorPreference> = players.iter().map(|p| p.color_preference()).collect();
+    let points: Vec<i64> = players.iter().map(|p| scaled_points(p.points())).collect();
+    let allowed = |ni: usize, pi: usize| {
+        !players[ni].had_matched_id(Some(players[pi].id())) && !preferences[ni].conflicts(&preferences[pi])
+    };
+
+    let cost = |mate: &[usize]| mate.iter().enumerate().map(|(ni, &mi)| (points[ni] - points[mi]).abs()).sum::<i64>() / 2;
+    // without the bans, the neighbours by points are paired
+    let lower_bound = {
+        let mut sorted = points.clone();
+        sorted.sort_unstable();
+        sorted.chunks(2).map(|pair| pair[1] - pair[0]).sum::<i64>()
+    };
+
+    let mut window = 8;
+    loop {
+        let mut edges = Vec::new();
+        for ni in 0..matchable_number {
+            for pi in (ni + 1..matchable_number).take(window) {
+                if allowed(ni, pi) {
+                    edges.push((ni, pi, (points[ni] - points[pi]).abs()));
                 }
             }
         }
 
-        // rollback
-        let mut matching_list = vec![None; players.len()];
-        let mut rbn = matchable_number;
-        let mut rbb: usize = 0;
-        let matching_success = loop {
-            let tmp_rbb = rb[rbn][rbb].0;
-            if tmp_rbb < 0 {
-                break false;
-            }
-            if rb[rbn][rbb].1 {
-                let transition = (rbb<<1) - tmp_rbb as usize;
-                let lid = matchable_players[rbn - 1].id();
-                let rid = matchable_players[rbn - 1 + transition.trailing_zeros() as usize].id();
-                if lid < matchable_number && rid < matchable_number {
-                    matching_list[lid] = Some(rid);
-                    matching_list[rid] = Some(lid);
-                }
+        let complete = window + 1 >= matchable_number;
+        match optimal_perfect_matching(matchable_number, &edges) {
+            Some(matching) if complete || cost(&matching.mate) == lower_bound || !improvable_outside(&matching, &points, window, allowed) => {
+                return Some(matching.mate.into_iter().enumerate().filter(|&(ni, mi)| ni < mi).collect());
             }
-            rbn -= 1;
-            rbb = tmp_rbb as usize;
-            if rbn == 0 && rbb == 0 {
-                break true;
-            }
-        };
-
-        if matching_success {
-            return Ok(matching_list);
+            None if complete => return None,
+            _ => window *= 2,
         }
-
     }
-
-    Err("No satisfying matching!".to_string())
-
 }
 
-pub fn matching_build_greed(players: &Vec<Player>) -> Result<Vec<Option<usize>>, String> {
-
-    let matchable_players = filter_sorted_matchable_players(players);
-    let mut matching_list: Vec<Option<usize>> = vec![None; players.len()];
-    let res = matching_dfs(&matchable_players, 0, &mut matching_list);
+/// whether an allowed pair `(ni, pi)` with `pi > ni + window` could make the matching cheaper
+///
+/// the players are grouped by points and by outermost blossom,
+/// and the players of a group are looked at by decreasing price as long as the bound of `PerfectMatching::price` lets them improve the matching.
+fn improvable_outside<F>(matching: &PerfectMatching, points: &[i64], window: usize, allowed: F) -> bool
+where F: Fn(usize, usize) -> bool {
+    let blossoms: Vec<Option<(usize, i64)>> = (0..points.len()).map(|index| matching.outermost_blossom(index)).collect();
+    let mut groups: BTreeMap<_, Vec<(i64, usize)>> = BTreeMap::new();
+    for (index, &group) in points.iter().enumerate() {
+        groups.entry((group, blossoms[index].map(|(b, _)| b))).or_default().push((matching.price(index), index));
+    }
+    for members in groups.values_mut() {
+        members.sort_by_key(|&(price, _)| std::cmp::Reverse(price));
+    }
+    (0..points.len()).any(|ni| {
+        groups.iter().any(|(&(group, blossom), members)| {
+            let cost = (points[ni] - group).abs();
+            let shared = match blossoms[ni] {
+                Some((b, z)) if Some(b) == blossom => 2 * z,
+                _ => 0,
+            };
+            members.iter()
+                .take_while(|&&(price, _)| 4 * cost + shared < matching.price(ni) + price)
+                .any(|&(_, pi)| pi > ni + window && matching.could_improve(ni, pi, cost) && allowed(ni, pi))
+        })
+    })
+}
 
-    if res.is_some() {
-        Ok(res.unwrap())
+/// pair the players ( in the order of the standings ) with `pair`, which gives the pairs as indexes of the players it is given
+///
+/// in an odd field, the bye goes to the lowest-ranked player who has not had a bye yet and without whom the others can be paired.
+fn pair_with_bye<F>(player_number: usize, players: &[&Player], mut pair: F) -> Result<Vec<Option<usize>>, OpoError>
+where F: FnMut(&[&Player]) -> Option<Vec<(usize, usize)>> {
+    let mut attempt = |bye: Option<usize>| {
+        let paired: Vec<&Player> = players.iter().enumerate()
+            .filter(|&(index, _)| Some(index) != bye)
+            .map(|(_, &player)| player)
+            .collect();
+        let pairs = pair(&paired)?;
+        let mut matching_list = vec![None; player_number];
+        for (a, b) in pairs {
+            matching_list[paired[a].id()] = Some(paired[b].id());
+            matching_list[paired[b].id()] = Some(paired[a].id());
+        }
+        Some(matching_list)
+    };
+    let matching_list = if players.len() % 2 == 1 {
+        (0..players.len()).rev()
+            .filter(|&index| !players[index].had_matched_id(None))
+            .find_map(|index| attempt(Some(index)))
     } else {
-        Err("No satisfying matching!".to_string())
-    }
+        attempt(None)
+    };
+    matching_list.ok_or(OpoError::PairingInfeasible)
+}
 
+/// pair each player in the order of the standings with the first possible opponent, backtracking when the rest can not be paired
+///
+/// the bye of an odd field follows `pair_with_bye`.
+pub fn matching_build_greed(players: &[Player], tiebreaks: &[Box<dyn Tiebreak>], scoring: &ScoringSystem) -> Result<Vec<Option<usize>>, OpoError> {
+    let matchable_players = filter_sorted_matchable_players(players, tiebreaks, scoring);
+    pair_with_bye(players.len(), &matchable_players, |paired| {
+        let mut mates = vec![None; paired.len()];
+        if !matching_dfs(paired, 0, &mut mates) {
+            return None;
+        }
+        Some(mates.into_iter().enumerate().filter_map(|(a, b)| b.filter(|&b| a < b).map(|b| (a, b))).collect())
+    })
 }
 
-fn matching_dfs(players: &Vec<&Player>, player: usize, matched_list: &mut Vec<Option<usize>> ) -> Option<Vec<Option<usize>>> {
+fn matching_dfs(players: &[&Player], player: usize, mates: &mut Vec<Option<usize>>) -> bool {
 
     if player == players.len() {
-        return if matched_list.iter().filter(|x| x.is_some()).count() >= players.len() - 1 {
-            Some(matched_list.clone())
-        } else {
-            None
-        }
+        return true;
     }
 
-    let player_id = players.get(player).unwrap().id();
-    if matched_list.get(player_id).unwrap().is_some() {
-        return matching_dfs(players, player+1, matched_list);
+    if mates[player].is_some() {
+        return matching_dfs(players, player + 1, mates);
     }
 
-    for opponent in player+1..players.len() {
-        let opponent_id = players.get(opponent).unwrap().id();
-        if !players.get(player).unwrap().had_matched_id(Some(opponent_id)) && matched_list.get(opponent_id).unwrap().is_none() {
-            *matched_list.get_mut(player_id).unwrap() = Some(opponent_id);
-            *matched_list.get_mut(opponent_id).unwrap() = Some(player_id);
-            let res = matching_dfs(players, player+1, matched_list);
-            if res.is_some() {
-                return res;
+    for opponent in player + 1..players.len() {
+        if !players[player].had_matched_id(Some(players[opponent].id())) && mates[opponent].is_none() {
+            mates[player] = Some(opponent);
+            mates[opponent] = Some(player);
+            if matching_dfs(players, player + 1, mates) {
+                return true;
             }
-            *matched_list.get_mut(player_id).unwrap() = None;
-            *matched_list.get_mut(opponent_id).unwrap() = None;
-        }
-    }
-
-    if !players.get(player).unwrap().had_matched_id(None) {
-        let res = matching_dfs(players, player+1, matched_list);
-        if res.is_some() {
-            return res;
+            mates[player] = None;
+            mates[opponent] = None;
         }
     }
 
-    None
+    false
 
 }
 
-pub fn filter_sorted_matchable_players(players: &Vec<Player>) -> Vec<&Player> {
-   players.into_iter()
+/// players who are not dropped, in the order of the standings
+pub fn filter_sorted_matchable_players<'a>(players: &'a [Player], tiebreaks: &[Box<dyn Tiebreak>], scoring: &ScoringSystem) -> Vec<&'a Player> {
+    standings(players, tiebreaks, scoring).into_iter()
         .filter(|p| !p.is_dropped())
-        .sorted().rev()
-        .collect::<Vec<&Player>>()
-}
\ No newline at end of file
+        .collect()
+}
+
+#[test]
+fn test_min_cost_pairs_against_brute_force() {
+    use super::matching::Matching;
+    fn brute(players: &[&Player], paired: &mut Vec<bool>) -> Option<i64> {
+        let first = match paired.iter().position(|&p| !p) {
+            Some(first) => first,
+            None => return Some(0),
+        };
+        paired[first] = true;
+        let mut best = None;
+        for other in first + 1..players.len() {
+            if paired[other] || players[first].had_matched_id(Some(players[other].id())) {
+                continue;
+            }
+            paired[other] = true;
+            let cost = (scaled_points(players[first].points()) - scaled_points(players[other].points())).abs();
+            if let Some(rest) = brute(players, paired) {
+                best = Some(best.map_or(cost + rest, |best: i64| best.min(cost + rest)));
+            }
+            paired[other] = false;
+        }
+        paired[first] = false;
+        best
+    }
+    let mut seed: u64 = 0x2545F4914F6CDD1D;
+    let mut next = || {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        seed
+    };
+    for _ in 0..30 {
+        // three rounds of random games, then the field in a random order ( so the window does not follow the points )
+        let mut players: Vec<Player> = (0..12).map(|i| Player::new(i, format!("{}abcd", i))).collect();
+        for round_number in 1..4 {
+            let mut order: Vec<usize> = (0..12).collect();
+            for i in (1..12).rev() {
+                order.swap(i, next() as usize % (i + 1));
+            }
+            for pair in order.chunks(2) {
+                let win = (next() % 2) as i32;
+                players[pair[0]].add_matching(Matching::new(round_number, pair[0], pair[1], win, 0, 1 - win, false, false).unwrap());
+                players[pair[1]].add_matching(Matching::new(round_number, pair[1], pair[0], 1 - win, 0, win, false, false).unwrap());
+            }
+        }
+        for player in &mut players {
+            player.calculate_points(&ScoringSystem::default());
+        }
+        let mut field: Vec<&Player> = players.iter().collect();
+        for i in (1..12).rev() {
+            field.swap(i, next() as usize % (i + 1));
+        }
+        let expected = brute(&field, &mut vec![false; 12]);
+        let pairs = min_cost_pairs(&field);
+        let cost = pairs.as_ref().map(|pairs| pairs.iter()
+            .map(|&(a, b)| (scaled_points(field[a].points()) - scaled_points(field[b].points())).abs())
+            .sum::<i64>());
+        assert_eq!(cost, expected);
+    }
+}
@@ -1,8 +1,25 @@
 
+pub mod color;
+pub mod csv;
+pub mod elimination;
+pub mod error;
+pub mod journal;
 pub mod matching;
 pub mod players;
+pub mod print;
+pub mod round_robin;
+pub mod scoring;
+#[cfg(feature = "server")]
+pub mod server;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
 pub mod swiss_system_tournament;
+pub mod tables;
+pub mod tiebreak;
+pub mod trf;
 mod matching_algorithm;
+mod dutch_system;
+pub mod weighted_matching;
 
 /// assert approximately equal
 #[macro_export]
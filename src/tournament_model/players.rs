@@ -1,39 +1,40 @@
-use std::cmp::Ordering;
+use super::color::{Color, ColorPreference};
 use super::matching::Matching;
+use super::scoring::ScoringSystem;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
-#[derive(Default,Debug)]
+#[derive(Clone,Default,Debug,Serialize,Deserialize)]
 /// # Player is player
 ///
-/// ## Ordering
-///
-/// 1. dropping relation ( them who dropping is `true` is Less )
-/// 2. Point Greater
-/// 3. OpMatchWin Greater
-/// 4. GameWin Greater
-/// 5. OpGameWin Greater
-/// 6. ID Less
+/// players are ordered by `tiebreak::standings` with the tiebreakers of the tournament
 pub struct Player{
     /// the player's id
     id: usize,
     /// the player's name
     name: String,
+    /// the player's rating ( used for the initial ranking of rating based pairing systems )
+    rating: i32,
     /// did Player dropped on the tournament?
     dropped: bool,
     /// points the player gained in matches
-    points: i32,
+    points: f64,
     match_win_percentage: f64,
     opponent_match_win_percentage: f64,
     game_win_percentage: f64,
     opponent_game_win_percentage: f64,
     matching_list: Vec<Matching>,
+    /// other registration data by name ( club, federation and so on )
+    #[serde(default)]
+    fields: BTreeMap<String, String>,
+    /// the number of rounds from the first one the player is given a bye in instead of being paired ( e.g. for an invitation )
+    #[serde(default)]
+    initial_byes: usize,
 }
 
 impl Player {
     pub fn new(id: usize, name: String) -> Self {
-        let mut player: Player = Default::default();
-        player.name = name;
-        player.id = id;
-        player
+        Player { id, name, ..Default::default() }
     }
     pub fn dummy(id: usize) -> Self {
         let mut player = Player::new(id, "!!DUMMY!!".to_string());
@@ -63,20 +64,65 @@ impl Player {
     pub fn id(&self) -> usize {
         self.id
     }
-    pub fn points(&self) -> i32 {
+    pub fn rating(&self) -> i32 {
+        self.rating
+    }
+    pub fn set_rating(&mut self, rating: i32) {
+        self.rating = rating;
+    }
+    pub fn initial_byes(&self) -> usize {
+        self.initial_byes
+    }
+    pub fn set_initial_byes(&mut self, initial_byes: usize) {
+        self.initial_byes = initial_byes;
+    }
+    /// the player has a bye for registration in the round
+    pub fn has_initial_bye(&self, round_number: i32) -> bool {
+        round_number >= 1 && round_number as usize <= self.initial_byes
+    }
+    pub fn points(&self) -> f64 {
         self.points
     }
+    pub fn field(&self, name: &str) -> Option<&str> {
+        self.fields.get(name).map(String::as_str)
+    }
+    pub fn fields(&self) -> &BTreeMap<String, String> {
+        &self.fields
+    }
+    pub fn set_field(&mut self, name: &str, value: &str) {
+        self.fields.insert(name.to_string(), value.to_string());
+    }
 
     pub fn add_matching(&mut self, matching: Matching) {
         self.matching_list.push(matching);
     }
 
+    /// replace the matching of the same round, keeping its place in the list
+    pub fn replace_matching(&mut self, matching: Matching) -> Option<Matching> {
+        let recorded = self.matching_list.iter_mut().find(|m| m.round_number() == matching.round_number())?;
+        Some(std::mem::replace(recorded, matching))
+    }
+
+    /// remove the matching of the round
+    pub fn remove_matching(&mut self, round_number: i32) -> Option<Matching> {
+        let index = self.matching_list.iter().position(|m| m.round_number() == round_number)?;
+        Some(self.matching_list.remove(index))
+    }
+
+    /// forget the matchings from the round on ( to look at the player as it was before the round )
+    pub(crate) fn truncate_rounds(&mut self, round_number: i32) {
+        self.matching_list.retain(|m| m.round_number() < round_number);
+    }
+
     pub fn is_dropped(&self) -> bool {
         self.dropped
     }
+    pub fn set_dropped(&mut self, dropped: bool) {
+        self.dropped = dropped;
+    }
 
     pub fn initialize_points(&mut self) {
-        self.points = 0;
+        self.points = 0.0;
         self.game_win_percentage = 0.0;
         self.match_win_percentage = 0.0;
         self.opponent_game_win_percentage = 0.0;
@@ -84,123 +130,129 @@ impl Player {
     }
 
     pub fn matched_round_number(&self) -> usize {
-        self.matching_list().into_iter()
+        self.matching_list().iter()
             .filter(|matching| !matching.is_dropped())
             .count()
     }
 
-    pub fn calculate_points(&mut self) {
+    pub fn calculate_points(&mut self, scoring: &ScoringSystem) {
+        // not `sum`, whose empty sum is -0 ( printed as "-0" )
         self.points =
-            self.matching_list().into_iter()
-            .map(|matching| matching.matching_points())
-            .sum()
+            self.matching_list().iter()
+            .map(|matching| matching.matching_points(scoring))
+            .fold(0.0, |points, matching_points| points + matching_points)
     }
 
     fn any_percentage(opponents_wp: Vec<f64>) -> f64 {
         let count = opponents_wp.len();
-        let sum = opponents_wp.into_iter().sum::<f64>();
-        return if count > 0 {
+        let sum = opponents_wp.iter().sum::<f64>();
+        if count > 0 {
             sum / count as f64
         } else {
             0.0
         }
     }
 
-    fn matching_list_to_filtered_mapped_percentage_list<PercentageFn>(matching_list: &Vec<Matching>, mut percentage_fn: PercentageFn ) -> Vec<f64>
+    fn matching_list_to_filtered_mapped_percentage_list<PercentageFn>(matching_list: &[Matching], percentage_fn: PercentageFn ) -> Vec<f64>
     where PercentageFn: FnMut(&Matching) -> f64 {
-        matching_list.into_iter()
+        matching_list.iter()
             .filter(|matching| matching.is_valid())
-            .map(|matching| percentage_fn(matching))
+            .map(percentage_fn)
             .collect()
     }
 
-    pub fn calculate_match_win_percentages(&mut self) {
-        self.match_win_percentage =
-            self.points as f64 / self.matched_round_number() as f64 / 3.0;
+    /// points over the maximum points of the rounds the player has matched ( 0 before the first round )
+    pub fn calculate_match_win_percentages(&mut self, scoring: &ScoringSystem) {
+        let matched_round_number = self.matched_round_number();
+        self.match_win_percentage = if matched_round_number > 0 {
+            self.points / matched_round_number as f64 / scoring.max_points()
+        } else {
+            0.0
+        };
     }
 
-    pub fn calculate_opponent_match_win_percentages(&mut self, players_mwp: &Vec<f64>) {
+    pub fn calculate_opponent_match_win_percentages(&mut self, players_mwp: &[f64]) {
         let omwp_list: Vec<f64> = Self::matching_list_to_filtered_mapped_percentage_list(self.matching_list(), |matching|{
             f64::max(1.0/3.0, *players_mwp.get(matching.opponent_id()).unwrap())
         });
         self.opponent_match_win_percentage = Self::any_percentage(omwp_list);
     }
 
-    pub fn calculate_game_win_percentages(&mut self) {
-        let gwp_list: Vec<f64> = Self::matching_list_to_filtered_mapped_percentage_list(self.matching_list(), |matching|{
-            matching.game_win_percentage()
-        });
+    /// the byes count only if `scoring` counts them as game wins
+    pub fn calculate_game_win_percentages(&mut self, scoring: &ScoringSystem) {
+        let gwp_list: Vec<f64> = self.matching_list().iter()
+            .filter(|matching| matching.is_valid() || matching.is_no_opponent() && scoring.bye_game_win())
+            .map(|matching| matching.game_win_percentage(scoring))
+            .collect();
         self.game_win_percentage = Self::any_percentage(gwp_list);
     }
 
-    pub fn calculate_opponent_game_win_percentages(&mut self, players_gwp: &Vec<f64>) {
+    pub fn calculate_opponent_game_win_percentages(&mut self, players_gwp: &[f64]) {
         let ogwp_list: Vec<f64> = Self::matching_list_to_filtered_mapped_percentage_list(self.matching_list(), |matching|{
             *players_gwp.get(matching.opponent_id()).unwrap()
         });
         self.opponent_game_win_percentage = Self::any_percentage(ogwp_list)
     }
 
-    pub fn had_matched_id(&self, search_id: Option<usize>) -> bool {
-        match search_id {
-            Some(id) =>
-                self.matching_list().into_iter()
-                    .find(|matching| matching.opponent_id() == id).is_some(),
-            None =>
-                self.matching_list().into_iter()
-                    .find(|matching| matching.is_no_opponent()).is_some(),
-        }
+    /// colours of the played games, oldest first ( unplayed games have no colour )
+    pub fn color_history(&self) -> Vec<Color> {
+        self.matching_list().iter()
+            .filter(|matching| matching.is_valid())
+            .filter_map(Matching::color)
+            .collect()
     }
 
-}
+    /// the number of games with white minus the number of games with black
+    pub fn color_difference(&self) -> i32 {
+        self.color_history().into_iter()
+            .map(|color| match color {
+                Color::White => 1,
+                Color::Black => -1,
+            })
+            .sum()
+    }
 
-impl Ord for Player {
-    fn cmp(&self, other: &Self) -> Ordering {
-        if self.dropped ^ other.dropped {
-            if self.dropped {
-                Ordering::Less
-            } else {
-                Ordering::Greater
-            }
+    /// colour preference ( C.04.3 A.6 )
+    ///
+    /// * absolute: the colour difference is over 1, or the last two games had the same colour
+    /// * strong: the colour difference is 1
+    /// * mild: the colour difference is 0, and the player wants to alternate
+    pub fn color_preference(&self) -> ColorPreference {
+        let history = self.color_history();
+        let difference = self.color_difference();
+        let last = match history.last() {
+            Some(&last) => last,
+            None => return ColorPreference::None,
+        };
+        if difference > 1 {
+            ColorPreference::Absolute(Color::Black)
+        } else if difference < -1 {
+            ColorPreference::Absolute(Color::White)
+        } else if history.len() >= 2 && history[history.len() - 2] == last {
+            ColorPreference::Absolute(last.opposite())
+        } else if difference == 1 {
+            ColorPreference::Strong(Color::Black)
+        } else if difference == -1 {
+            ColorPreference::Strong(Color::White)
         } else {
-            if !self.points.eq(&other.points) {
-                self.points.cmp(&other.points)
-            } else {
-                if !self.opponent_match_win_percentage.eq(&other.opponent_match_win_percentage) {
-                    self.opponent_match_win_percentage.partial_cmp(&other.opponent_match_win_percentage).unwrap()
-                } else {
-                    if !self.game_win_percentage.eq(&other.game_win_percentage) {
-                        self.game_win_percentage.partial_cmp(&other.game_win_percentage).unwrap()
-                    } else {
-                        if !self.opponent_game_win_percentage.eq(&other.opponent_game_win_percentage) {
-                            self.opponent_game_win_percentage.partial_cmp(&other.opponent_game_win_percentage).unwrap()
-                        } else {
-                            other.id.cmp(&self.id)
-                        }
-                    }
-                }
-            }
+            ColorPreference::Mild(last.opposite())
         }
     }
-}
 
-impl PartialOrd for Player {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(&other))
+    pub fn had_matched_id(&self, search_id: Option<usize>) -> bool {
+        match search_id {
+            // a bye or an absence stores the opponent id 0, which is not a game against player 0
+            Some(id) =>
+                self.matching_list().iter()
+                    .any(|matching| matching.is_reversible() && matching.opponent_id() == id),
+            None =>
+                self.matching_list().iter()
+                    .any(|matching| matching.is_no_opponent()),
+        }
     }
-}
 
-impl PartialEq for Player {
-    fn eq(&self, other: &Self) -> bool {
-        !(self.dropped ^ other.dropped) &&
-        self.points == other.points &&
-        self.opponent_match_win_percentage == other.opponent_match_win_percentage &&
-        self.game_win_percentage == other.game_win_percentage &&
-        self.opponent_game_win_percentage == self.opponent_game_win_percentage &&
-        self.id == other.id
-    }
 }
 
-impl Eq for Player {}
 
 #[test]
 fn test_player_construct() {
@@ -214,30 +266,49 @@ fn test_player_construct() {
 fn test_add_matching() {
     // referenced: https://kirisamemagic.diarynote.jp/201401060210226433/
     let mut p = Player::new(0, "あ😁し😁は😁ら".to_string());
-    p.add_matching(Matching::new(0, 0, 1, 2, 0, 0, false, false));
-    p.add_matching(Matching::new(0, 0, 2, 1, 1, 0, false, false));
-    p.add_matching(Matching::new(0, 0, 3, 2, 0, 1, false, false));
-    p.add_matching(Matching::new(0, 0, 4, 0, 0, 0, false, true));
-    p.add_matching(Matching::new(0, 0, 5, 0, 0, 2, false, false));
+    p.add_matching(Matching::new(0, 0, 1, 2, 0, 0, false, false).unwrap());
+    p.add_matching(Matching::new(0, 0, 2, 1, 1, 0, false, false).unwrap());
+    p.add_matching(Matching::new(0, 0, 3, 2, 0, 1, false, false).unwrap());
+    p.add_matching(Matching::new(0, 0, 4, 0, 0, 0, false, true).unwrap());
+    p.add_matching(Matching::new(0, 0, 5, 0, 0, 2, false, false).unwrap());
     assert_eq!(p.matching_list[0].draw_count(), 0);
     assert_eq!(p.matching_list[1].win_count(), 1);
     assert!(!p.matching_list[3].is_valid());
 }
 
+#[test]
+fn test_replace_and_remove_matching() {
+    let mut p = Player::new(0, "あ😁し😁は😁ら".to_string());
+    p.add_matching(Matching::new(1, 0, 1, 2, 0, 0, false, false).unwrap());
+    p.add_matching(Matching::new(2, 0, 2, 2, 0, 0, false, false).unwrap());
+    p.add_matching(Matching::new(3, 0, 3, 2, 0, 0, false, false).unwrap());
+    let old = p.replace_matching(Matching::new(2, 0, 2, 0, 0, 2, false, false).unwrap()).unwrap();
+    assert!(old.is_win());
+    assert!(p.matching_list[1].is_lose());
+    assert!(p.replace_matching(Matching::no_opponent_new(4, 0)).is_none());
+    assert_eq!(p.remove_matching(1).unwrap().opponent_id(), 1);
+    assert!(p.remove_matching(1).is_none());
+    p.truncate_rounds(3);
+    assert_eq!(p.matching_list.len(), 1);
+    assert_eq!(p.matching_list[0].round_number(), 2);
+}
+
 #[test]
 fn test_points_calculation() {
     // referenced: https://kirisamemagic.diarynote.jp/201401060210226433/
     use crate::assert_ap;
     let mut p = Player::new(0, "あ😁し😁は😁ら".to_string());
-    p.add_matching(Matching::new(0, 0, 1, 2, 0, 0, false, false));
-    p.add_matching(Matching::new(0, 0, 2, 1, 1, 0, false, false));
-    p.add_matching(Matching::new(0, 0, 3, 2, 0, 1, false, false));
-    p.add_matching(Matching::new(0, 0, 4, 0, 0, 0, false, true));
-    p.add_matching(Matching::new(0, 0, 5, 0, 0, 2, false, false));
-    p.calculate_points();
-    p.calculate_opponent_match_win_percentages(&vec![0.800,0.067,0.500,0.667,0.000,0.867]);
-    p.calculate_game_win_percentages();
-    assert_eq!(p.points, 12);
+    p.calculate_points(&ScoringSystem::default());
+    assert_eq!(p.points.to_string(), "0");
+    p.add_matching(Matching::new(0, 0, 1, 2, 0, 0, false, false).unwrap());
+    p.add_matching(Matching::new(0, 0, 2, 1, 1, 0, false, false).unwrap());
+    p.add_matching(Matching::new(0, 0, 3, 2, 0, 1, false, false).unwrap());
+    p.add_matching(Matching::new(0, 0, 4, 0, 0, 0, false, true).unwrap());
+    p.add_matching(Matching::new(0, 0, 5, 0, 0, 2, false, false).unwrap());
+    p.calculate_points(&ScoringSystem::default());
+    p.calculate_opponent_match_win_percentages(&[0.800,0.067,0.500,0.667,0.000,0.867]);
+    p.calculate_game_win_percentages(&ScoringSystem::default());
+    assert_eq!(p.points, 12.0);
     assert_ap!(p.opponent_match_win_percentage, 0.591, 0.001);
     assert_ap!(p.game_win_percentage, 0.5833, 0.0001);
 }
@@ -246,40 +317,66 @@ fn test_points_calculation() {
 fn test_special_points() {
     use crate::assert_ap;
     let mut p = Player::new(0, "あ😁し😁は😁ら".to_string());
-    p.add_matching(Matching::new(0, 0, 1, 2, 1, 0, false, false));
-    p.add_matching(Matching::new(0, 0, 2, 1, 1, 0, true, false));
-    p.add_matching(Matching::new(0, 0, 3, 2, 0, 1, false, true));
+    p.add_matching(Matching::new(0, 0, 1, 2, 1, 0, false, false).unwrap());
+    p.add_matching(Matching::new(0, 0, 2, 1, 1, 0, true, false).unwrap());
+    p.add_matching(Matching::new(0, 0, 3, 2, 0, 1, false, true).unwrap());
     p.add_matching(Matching::no_opponent_new(0, 0));
     p.add_matching(Matching::dropped_new(0, 0));
-    p.calculate_points();
-    p.calculate_opponent_match_win_percentages(&vec![0.800,0.067,0.500,0.667,0.000,0.867]);
-    p.calculate_game_win_percentages();
-    p.calculate_opponent_game_win_percentages(&vec![0.800,0.067,0.500,0.667,0.000,0.867]);
-    assert_eq!(p.points, 9);
+    p.calculate_points(&ScoringSystem::default());
+    p.calculate_opponent_match_win_percentages(&[0.800,0.067,0.500,0.667,0.000,0.867]);
+    p.calculate_game_win_percentages(&ScoringSystem::default());
+    p.calculate_opponent_game_win_percentages(&[0.800,0.067,0.500,0.667,0.000,0.867]);
+    assert_eq!(p.points, 9.0);
     assert_ap!(p.opponent_match_win_percentage, 0.333, 0.001);
     assert_ap!(p.game_win_percentage, 0.777, 0.001);
     assert_ap!(p.opponent_game_win_percentage, 0.067, 0.001);
 }
 
 #[test]
-fn test_players_ord() {
+fn test_players_standings() {
+    use super::tiebreak::{default_tiebreaks, standings};
     let mut ps = Vec::new();
     let mut p1 = Player::new(0, "あ😁し😁は😁ら".to_string());
-    p1.points = 1;
+    p1.points = 1.0;
     let mut p2 = Player::new(1, "あ😁し😁は😁ら".to_string());
-    p2.points = 2;
+    p2.points = 2.0;
     let mut p3 = Player::new(2, "あ😁し😁は😁ら".to_string());
-    p3.points = 0;
+    p3.points = 0.0;
     let mut p4 = Player::new(3, "あ😁し😁は😁ら".to_string());
-    p4.points = 1;
-    assert_eq!(p1.cmp(&p2), Ordering::Less);
-    assert_eq!(p1.cmp(&p3), Ordering::Greater);
-    assert_eq!(p1.cmp(&p4), Ordering::Greater); // ID is less than p4, so Ordering is Greater
+    p4.points = 1.0;
     ps.push(p1);
     ps.push(p2);
     ps.push(p3);
     ps.push(p4);
-    ps.sort();
-    assert_eq!(ps[0].id, 2);
-    assert_eq!(ps[3].id, 1);
+    let standings = standings(&ps, &default_tiebreaks(), &ScoringSystem::default());
+    assert_eq!(standings[0].id, 1);
+    assert_eq!(standings[1].id, 0); // ID is less than p4, so p1 is higher
+    assert_eq!(standings[2].id, 3);
+    assert_eq!(standings[3].id, 2);
+}
+
+#[test]
+fn test_color_preference() {
+    let colored = |round_number: i32, color: Color| {
+        let mut m = Matching::new(round_number, 0, round_number as usize, 1, 0, 0, false, false).unwrap();
+        m.set_color(Some(color));
+        m
+    };
+    let mut p = Player::new(0, "あ😁し😁は😁ら".to_string());
+    assert_eq!(p.color_preference(), ColorPreference::None);
+    p.add_matching(colored(1, Color::White));
+    assert_eq!(p.color_preference(), ColorPreference::Strong(Color::Black));
+    p.add_matching(colored(2, Color::Black));
+    assert_eq!(p.color_preference(), ColorPreference::Mild(Color::White));
+    p.add_matching(Matching::no_opponent_new(3, 0));
+    assert_eq!(p.color_preference(), ColorPreference::Mild(Color::White));
+    p.add_matching(colored(4, Color::Black));
+    assert_eq!(p.color_preference(), ColorPreference::Absolute(Color::White));
+    assert_eq!(p.color_history(), vec![Color::White, Color::Black, Color::Black]);
+    assert_eq!(p.color_difference(), -1);
+    p.add_matching(colored(5, Color::White));
+    p.add_matching(colored(6, Color::Black));
+    p.add_matching(colored(7, Color::Black));
+    assert_eq!(p.color_difference(), -2);
+    assert_eq!(p.color_preference(), ColorPreference::Absolute(Color::White));
 }
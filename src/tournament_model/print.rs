@@ -0,0 +1,364 @@
+//! print-ready pairings, standings and result slips, as self-contained HTML or as PDF
+//!
+//! the HTML has its style inline and no script, to be printed from a browser.
+//! the PDF is plain Courier text on A4 pages: the header of a list is repeated on every page, and no slip is cut by a page.
+
+use super::swiss_system_tournament::Tournament;
+use std::mem;
+
+/// a printable document of the current round
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Document {
+    /// the tables in order, with the points of the players
+    PairingsByTable,
+    /// every player in alphabetical order, with the table and the opponent
+    PairingsByName,
+    /// the standings with a column for each tiebreaker
+    Standings,
+    /// a slip for each table, with boxes for the game counts and lines for the signatures
+    ResultSlips,
+}
+
+/// the table of a result slip and its two players
+struct Slip {
+    round_number: i32,
+    table: usize,
+    players: [String; 2],
+}
+
+enum Body {
+    /// rows under a header, `numeric` telling the columns aligned to the right
+    List { header: Vec<String>, numeric: Vec<bool>, rows: Vec<Vec<String>> },
+    Slips(Vec<Slip>),
+}
+
+fn strings(cells: &[&str]) -> Vec<String> {
+    cells.iter().map(|cell| cell.to_string()).collect()
+}
+
+/// the title and the body of the document
+fn content(tournament: &Tournament, document: Document) -> (String, Body) {
+    let players = tournament.players();
+    let round_number = tournament.pairing_round();
+    let label = |id: usize| format!("{} ({})", players[id].name(), id);
+    let points = |id: usize| players[id].points().to_string();
+    let tables = tournament.tables();
+    let byes = tournament.byes();
+    match document {
+        Document::PairingsByTable => {
+            let mut rows: Vec<Vec<String>> = tables.iter().map(|table| vec![
+                table.number.to_string(), label(table.player_id), points(table.player_id), label(table.opponent_id), points(table.opponent_id),
+            ]).collect();
+            rows.extend(byes.iter().map(|&id| vec![String::new(), label(id), points(id), "bye".to_string(), String::new()]));
+            let header = strings(&["table", "player", "points", "opponent", "points"]);
+            (format!("Round {} pairings", round_number), Body::List { header, numeric: vec![true, false, true, false, true], rows })
+        }
+        Document::PairingsByName => {
+            let mut entries = Vec::new();
+            for table in &tables {
+                entries.push((table.player_id, table.number.to_string(), label(table.opponent_id)));
+                entries.push((table.opponent_id, table.number.to_string(), label(table.player_id)));
+            }
+            entries.extend(byes.iter().map(|&id| (id, "bye".to_string(), String::new())));
+            entries.sort_by_key(|(id, ..)| (players[*id].name().to_lowercase(), *id));
+            let rows = entries.into_iter().map(|(id, table, opponent)| vec![label(id), table, opponent]).collect();
+            let header = strings(&["player", "table", "opponent"]);
+            (format!("Round {} pairings by name", round_number), Body::List { header, numeric: vec![false, true, false], rows })
+        }
+        Document::Standings => {
+            let mut header = strings(&["rank", "player", "points"]);
+            header.extend(tournament.tiebreaks().iter().map(|tiebreak| tiebreak.name().to_string()));
+            let numeric = (0..header.len()).map(|column| column != 1).collect();
+            let rows = tournament.ranked_standings().into_iter().enumerate().map(|(rank, (player, values))| {
+                let name = if player.is_dropped() { format!("{} dropped", label(player.id())) } else { label(player.id()) };
+                let mut row = vec![(rank + 1).to_string(), name, points(player.id())];
+                row.extend(values.iter().map(|value| format!("{:.4}", value)));
+                row
+            }).collect();
+            (format!("Standings after round {}", tournament.round_number()), Body::List { header, numeric, rows })
+        }
+        Document::ResultSlips => {
+            let slips = tables.iter().map(|table| Slip {
+                round_number,
+                table: table.number,
+                players: [label(table.player_id), label(table.opponent_id)],
+            }).collect();
+            (format!("Round {} result slips", round_number), Body::Slips(slips))
+        }
+    }
+}
+
+const STYLE: &str = "
+body { font-family: sans-serif; font-size: 11pt; }
+h1 { font-size: 16pt; }
+h2 { font-size: 12pt; margin: 0 0 6pt; }
+table { border-collapse: collapse; width: 100%; }
+th, td { border: 1px solid #444; padding: 2pt 6pt; text-align: left; }
+.num { text-align: right; }
+thead { display: table-header-group; }
+tr, .slip { break-inside: avoid; page-break-inside: avoid; }
+.slip { border: 1px dashed #444; padding: 8pt; margin-bottom: 12pt; }
+.slip td { height: 24pt; }
+.slip .count { width: 10%; }
+.slip .signature { width: 30%; }
+@page { size: A4; margin: 15mm; }
+";
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;").replace('\'', "&#39;")
+}
+
+fn cell_html(tag: &str, text: &str, numeric: bool) -> String {
+    let class = if numeric { " class=\"num\"" } else { "" };
+    format!("<{0}{1}>{2}</{0}>", tag, class, escape(text))
+}
+
+/// the document as an HTML page with its style inline
+pub fn html(tournament: &Tournament, document: Document) -> String {
+    let (title, body) = content(tournament, document);
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{0}</title>\n<style>{1}</style>\n</head>\n<body>\n<h1>{0}</h1>\n",
+        escape(&title), STYLE,
+    );
+    match body {
+        Body::List { header, numeric, rows } => {
+            html.push_str("<table>\n<thead><tr>");
+            for (cell, &numeric) in header.iter().zip(&numeric) {
+                html.push_str(&cell_html("th", cell, numeric));
+            }
+            html.push_str("</tr></thead>\n<tbody>\n");
+            for row in rows {
+                html.push_str("<tr>");
+                for (cell, &numeric) in row.iter().zip(&numeric) {
+                    html.push_str(&cell_html("td", cell, numeric));
+                }
+                html.push_str("</tr>\n");
+            }
+            html.push_str("</tbody>\n</table>\n");
+        }
+        Body::Slips(slips) => {
+            for slip in slips {
+                html.push_str(&format!(
+                    "<div class=\"slip\">\n<h2>Round {} &middot; table {}</h2>\n<table>\n\
+                     <thead><tr><th>player</th><th>won</th><th>drawn</th><th>lost</th><th>signature</th></tr></thead>\n<tbody>\n",
+                    slip.round_number, slip.table,
+                ));
+                for player in &slip.players {
+                    html.push_str(&format!(
+                        "<tr><td>{}</td><td class=\"count\"></td><td class=\"count\"></td><td class=\"count\"></td><td class=\"signature\"></td></tr>\n",
+                        escape(player),
+                    ));
+                }
+                html.push_str("</tbody>\n</table>\n</div>\n");
+            }
+        }
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+/// A4 in points, the margins, and Courier 10 on a leading of 12 ( 6 points a character )
+const PAGE_WIDTH: usize = 595;
+const PAGE_HEIGHT: usize = 842;
+const MARGIN: usize = 50;
+const FONT_SIZE: usize = 10;
+const LEADING: usize = 12;
+const PAGE_LINES: usize = (PAGE_HEIGHT - 2 * MARGIN) / LEADING;
+const LINE_WIDTH: usize = (PAGE_WIDTH - 2 * MARGIN) * 10 / (6 * FONT_SIZE);
+
+/// the text cut or padded to the width
+fn pad(text: &str, width: usize, right: bool) -> String {
+    let text: String = text.chars().take(width).collect();
+    if right {
+        format!("{:>1$}", text, width)
+    } else {
+        format!("{:<1$}", text, width)
+    }
+}
+
+/// the columns joined by two spaces, the widest columns cut until the line fits
+fn list_lines(header: &[String], numeric: &[bool], rows: &[Vec<String>]) -> (Vec<String>, Vec<String>) {
+    let mut widths: Vec<usize> = header.iter().map(|cell| cell.chars().count()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let separators = 2 * (widths.len().saturating_sub(1));
+    while widths.iter().sum::<usize>() + separators > LINE_WIDTH {
+        match widths.iter_mut().max() {
+            Some(width) if *width > 4 => *width -= 1,
+            _ => break,
+        }
+    }
+    let line = |cells: &[String]| {
+        cells.iter().zip(&widths).zip(numeric)
+            .map(|((cell, &width), &right)| pad(cell, width, right))
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+    let head = vec![line(header), "-".repeat(widths.iter().sum::<usize>() + separators)];
+    (head, rows.iter().map(|row| line(row)).collect())
+}
+
+fn slip_lines(slip: &Slip) -> Vec<String> {
+    let count = "[    ]";
+    let mut lines = vec![
+        format!("Round {} - table {}", slip.round_number, slip.table),
+        String::new(),
+        format!("{:<36} {:<6} {:<6} {:<6} {}", "player", "won", "drawn", "lost", "signature"),
+    ];
+    for player in &slip.players {
+        lines.push(format!("{} {} {} {} {}", pad(player, 36, false), count, count, count, "_".repeat(20)));
+        lines.push(String::new());
+    }
+    lines.push("- ".repeat(LINE_WIDTH / 2).trim_end().to_string());
+    lines.push(String::new());
+    lines
+}
+
+/// the blocks of lines cut into pages with the head on top of every page, no block cut ( unless longer than a page )
+fn paginate(head: Vec<String>, blocks: Vec<Vec<String>>) -> Vec<Vec<String>> {
+    let mut pages = Vec::new();
+    let mut page = head.clone();
+    for block in blocks {
+        if page.len() > head.len() && page.len() + block.len() > PAGE_LINES {
+            pages.push(mem::replace(&mut page, head.clone()));
+        }
+        page.extend(block);
+    }
+    pages.push(page);
+    pages
+}
+
+/// a PDF string of the line, in WinAnsiEncoding ( `?` for the characters out of Latin-1 )
+fn pdf_string(line: &str) -> Vec<u8> {
+    let mut bytes = vec![b'('];
+    for c in line.chars() {
+        match c {
+            '(' | ')' | '\\' => bytes.extend([b'\\', c as u8]),
+            c if (c as u32) < 256 && !c.is_control() => bytes.push(c as u32 as u8),
+            _ => bytes.push(b'?'),
+        }
+    }
+    bytes.push(b')');
+    bytes
+}
+
+/// the document as a PDF of text pages
+pub fn pdf(tournament: &Tournament, document: Document) -> Vec<u8> {
+    let (title, body) = content(tournament, document);
+    let mut head = vec![title, String::new()];
+    let pages = match body {
+        Body::List { header, numeric, rows } => {
+            let (header_lines, lines) = list_lines(&header, &numeric, &rows);
+            head.extend(header_lines);
+            paginate(head, lines.into_iter().map(|line| vec![line]).collect())
+        }
+        Body::Slips(slips) => paginate(head, slips.iter().map(slip_lines).collect()),
+    };
+
+    // 1 the catalog, 2 the page tree, 3 the font, then each page and its content
+    let kids: Vec<String> = (0..pages.len()).map(|index| format!("{} 0 R", 4 + 2 * index)).collect();
+    let mut objects: Vec<Vec<u8>> = vec![
+        b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+        format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), pages.len()).into_bytes(),
+        b"<< /Type /Font /Subtype /Type1 /BaseFont /Courier /Encoding /WinAnsiEncoding >>".to_vec(),
+    ];
+    for (index, page) in pages.iter().enumerate() {
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources << /Font << /F1 3 0 R >> >> /Contents {} 0 R >>",
+            PAGE_WIDTH, PAGE_HEIGHT, 5 + 2 * index,
+        ).into_bytes());
+        let mut content = format!("BT /F1 {} Tf {} TL {} {} Td\n", FONT_SIZE, LEADING, MARGIN, PAGE_HEIGHT - MARGIN).into_bytes();
+        for line in page {
+            content.extend(pdf_string(line));
+            content.extend(b" Tj T*\n");
+        }
+        content.extend(b"ET");
+        let mut object = format!("<< /Length {} >>\nstream\n", content.len()).into_bytes();
+        object.extend(content);
+        object.extend(b"\nendstream");
+        objects.push(object);
+    }
+
+    let mut pdf = b"%PDF-1.4\n".to_vec();
+    let mut offsets = Vec::new();
+    for (index, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend(format!("{} 0 obj\n", index + 1).into_bytes());
+        pdf.extend(object);
+        pdf.extend(b"\nendobj\n");
+    }
+    let xref = pdf.len();
+    pdf.extend(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).into_bytes());
+    for offset in offsets {
+        pdf.extend(format!("{:010} 00000 n \n", offset).into_bytes());
+    }
+    pdf.extend(format!("trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n", objects.len() + 1, xref).into_bytes());
+    pdf
+}
+
+#[cfg(test)]
+use super::players::Player;
+
+#[cfg(test)]
+fn paired_tournament(player_number: usize) -> Tournament {
+    let mut t: Tournament = Default::default();
+    for i in 0..player_number {
+        t.add_player(Player::new(i, format!("{}abcd", (b'z' - (i % 26) as u8) as char)));
+    }
+    t.pair_next_round().unwrap();
+    t
+}
+
+#[test]
+fn test_html() {
+    let mut t = paired_tournament(5);
+    t.add_player(Player::new(5, "<script>".to_string()));
+    let pairings = html(&t, Document::PairingsByTable);
+    assert!(pairings.starts_with("<!DOCTYPE html>") && pairings.contains("<h1>Round 1 pairings</h1>"));
+    assert_eq!(pairings.matches("<tr>").count(), 1 + 3);
+    assert!(pairings.contains("<td>bye</td>"));
+
+    let by_name = html(&t, Document::PairingsByName);
+    let names: Vec<usize> = ["vabcd", "wabcd", "xabcd", "yabcd", "zabcd"].iter().map(|name| by_name.find(&format!("<tr><td>{}", name)).unwrap()).collect();
+    assert!(names.windows(2).all(|pair| pair[0] < pair[1]));
+
+    let standings = html(&t, Document::Standings);
+    assert!(standings.contains("<th class=\"num\">OMW%</th>"));
+    assert!(standings.contains("&lt;script&gt; (5)") && !standings.contains("<script>"));
+
+    let slips = html(&t, Document::ResultSlips);
+    assert_eq!(slips.matches("<div class=\"slip\">").count(), 2);
+    assert!(slips.contains("<h2>Round 1 &middot; table 2</h2>"));
+}
+
+#[test]
+fn test_pdf() {
+    let t = paired_tournament(200);
+    for document in [Document::PairingsByTable, Document::PairingsByName, Document::Standings, Document::ResultSlips] {
+        let pdf = pdf(&t, document);
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(text.starts_with("%PDF-1.4\n") && text.ends_with("%%EOF\n"));
+        // every object is where the cross-reference table says
+        let xref: usize = text.lines().rev().nth(1).unwrap().parse().unwrap();
+        let offsets: Vec<usize> = text[xref..].lines().skip(3).take_while(|line| line.ends_with(" n ")).map(|line| line[..10].parse().unwrap()).collect();
+        for (index, &offset) in offsets.iter().enumerate() {
+            assert!(text[offset..].starts_with(&format!("{} 0 obj\n", index + 1)));
+        }
+        let pages = text.matches("/Type /Page ").count();
+        let lines = text.matches(" Tj T*").count();
+        let expected_pages = match document {
+            Document::PairingsByTable => (100 + PAGE_LINES - 4 - 1) / (PAGE_LINES - 4),
+            Document::PairingsByName | Document::Standings => (200 + PAGE_LINES - 4 - 1) / (PAGE_LINES - 4),
+            Document::ResultSlips => 100 / ((PAGE_LINES - 2) / 9) + 1,
+        };
+        assert_eq!(pages, expected_pages, "{:?}", document);
+        assert!(lines <= pages * PAGE_LINES);
+        assert!(text.contains("(Round 1 ") || text.contains("(Standings after round 0)"));
+    }
+    assert_eq!(pdf_string("a(b)\\é€"), b"(a\\(b\\)\\\\\xe9?)".to_vec());
+}
@@ -0,0 +1,170 @@
+//! round-robin schedules ( circle / Berger method ) and group stages
+
+use super::error::OpoError;
+use super::players::Player;
+
+/// how the players are ranked before they are split into groups
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GroupSeeding {
+    /// by rating ( higher first ) and then by id
+    Rating,
+    /// by the standings of the previous stage
+    Standings,
+}
+
+/// every round of a round robin of `participants` in the shape of `Tournament::matching_build`
+///
+/// `participants` are ids in the order of the pairing numbers, and each round has `player_number` elements.
+/// the rounds are given by the circle method in the order of the Berger tables:
+/// the last pairing number stays and the others rotate by half of the circle each round.
+/// with an odd number of participants, the player who would meet the missing one has no opponent.
+/// with `double`, the whole cycle is played twice.
+pub fn round_robin(participants: &[usize], player_number: usize, double: bool) -> Vec<Vec<Option<usize>>> {
+    if participants.len() < 2 {
+        return Vec::new();
+    }
+    let mut circle: Vec<Option<usize>> = participants.iter().copied().map(Some).collect();
+    if circle.len() % 2 == 1 {
+        circle.push(None);
+    }
+    let n = circle.len();
+
+    let mut rounds = Vec::new();
+    for round in 0..n - 1 {
+        let offset = round * (n / 2) % (n - 1);
+        let mut matching_list = vec![None; player_number];
+        let mut pair = |a: Option<usize>, b: Option<usize>| {
+            if let (Some(a), Some(b)) = (a, b) {
+                matching_list[a] = Some(b);
+                matching_list[b] = Some(a);
+            }
+        };
+        pair(circle[offset], circle[n - 1]);
+        for i in 1..n / 2 {
+            pair(circle[(offset + i) % (n - 1)], circle[(offset + n - 1 - i) % (n - 1)]);
+        }
+        rounds.push(matching_list);
+    }
+    if double {
+        rounds.extend(rounds.clone());
+    }
+    rounds
+}
+
+/// ids of the players who are not dropped, by rating ( higher first ) and then by id
+pub fn seed_by_rating(players: &[Player]) -> Vec<usize> {
+    let mut seeded: Vec<&Player> = players.iter().filter(|p| !p.is_dropped()).collect();
+    seeded.sort_by(|a, b| b.rating().cmp(&a.rating()).then(a.id().cmp(&b.id())));
+    seeded.into_iter().map(|p| p.id()).collect()
+}
+
+/// split `seeded` ( the first seed first ) into `group_number` groups of balanced strength
+///
+/// the seeds are dealt in a snake: 1, 2, 3, 4 to the groups A, B, C, D and then 5, 6, 7, 8 to D, C, B, A.
+pub fn split_groups(seeded: &[usize], group_number: usize) -> Result<Vec<Vec<usize>>, OpoError> {
+    if group_number == 0 {
+        return Err(OpoError::InvalidGroupNumber(group_number));
+    }
+    if group_number > seeded.len() {
+        return Err(OpoError::NotEnoughPlayers { required: group_number, available: seeded.len() });
+    }
+    let mut groups = vec![Vec::new(); group_number];
+    for (i, &id) in seeded.iter().enumerate() {
+        let (lap, position) = (i / group_number, i % group_number);
+        let group = if lap % 2 == 0 { position } else { group_number - 1 - position };
+        groups[group].push(id);
+    }
+    Ok(groups)
+}
+
+/// the rounds of the round robins of every group played at the same time
+///
+/// a group with fewer rounds leaves its players without opponent in the last rounds.
+pub fn group_stage(groups: &[Vec<usize>], player_number: usize, double: bool) -> Vec<Vec<Option<usize>>> {
+    let mut rounds: Vec<Vec<Option<usize>>> = Vec::new();
+    for group in groups {
+        for (ri, group_round) in round_robin(group, player_number, double).into_iter().enumerate() {
+            if rounds.len() <= ri {
+                rounds.push(vec![None; player_number]);
+            }
+            for (id, opponent) in group_round.into_iter().enumerate() {
+                if opponent.is_some() {
+                    rounds[ri][id] = opponent;
+                }
+            }
+        }
+    }
+    rounds
+}
+
+#[cfg(test)]
+fn assert_round_robin(participants: &[usize], rounds: &[Vec<Option<usize>>], cycles: usize) {
+    let n = participants.len();
+    assert_eq!(rounds.len(), cycles * if n % 2 == 0 { n - 1 } else { n });
+    let mut meetings = std::collections::HashMap::new();
+    for round in rounds {
+        let byes = participants.iter().filter(|&&id| round[id].is_none()).count();
+        assert_eq!(byes, n % 2);
+        for (id, opponent) in round.iter().enumerate() {
+            if let Some(opponent) = opponent {
+                assert_eq!(round[*opponent], Some(id));
+                *meetings.entry((id, *opponent)).or_insert(0) += 1;
+            }
+        }
+    }
+    for &a in participants {
+        for &b in participants {
+            if a != b {
+                assert_eq!(meetings.get(&(a, b)), Some(&cycles));
+            }
+        }
+    }
+}
+
+#[test]
+fn test_round_robin() {
+    let rounds = round_robin(&[0, 1, 2, 3], 4, false);
+    // Berger table for 4: 1-4 2-3, 4-3 1-2, 2-4 3-1
+    assert_eq!(rounds[0], vec![Some(3), Some(2), Some(1), Some(0)]);
+    assert_eq!(rounds[1], vec![Some(1), Some(0), Some(3), Some(2)]);
+    assert_eq!(rounds[2], vec![Some(2), Some(3), Some(0), Some(1)]);
+    for n in 2..12 {
+        let participants: Vec<usize> = (0..n).collect();
+        assert_round_robin(&participants, &round_robin(&participants, n, false), 1);
+        assert_round_robin(&participants, &round_robin(&participants, n, true), 2);
+    }
+    // participants are ids, and the others have no opponent
+    let rounds = round_robin(&[4, 1, 6], 7, false);
+    assert_round_robin(&[4, 1, 6], &rounds, 1);
+    assert!(rounds.iter().all(|round| round[0].is_none() && round[5].is_none()));
+    assert!(round_robin(&[0], 1, false).is_empty());
+}
+
+#[test]
+fn test_groups() {
+    let mut players: Vec<Player> = (0..10).map(|i| Player::new(i, format!("{}abcd", i))).collect();
+    for (i, p) in players.iter_mut().enumerate() {
+        p.set_rating(1000 + 10 * i as i32);
+    }
+    players[3] = Player::dummy(3);
+    let seeded = seed_by_rating(&players);
+    assert_eq!(seeded, vec![9, 8, 7, 6, 5, 4, 2, 1, 0]);
+    let groups = split_groups(&seeded, 3).unwrap();
+    assert_eq!(groups, vec![vec![9, 4, 2], vec![8, 5, 1], vec![7, 6, 0]]);
+    assert!(matches!(split_groups(&seeded, 0), Err(OpoError::InvalidGroupNumber(0))));
+    assert!(matches!(split_groups(&seeded, 10), Err(OpoError::NotEnoughPlayers { required: 10, available: 9 })));
+
+    let groups = split_groups(&seeded, 2).unwrap();
+    assert_eq!(groups, vec![vec![9, 6, 5, 1, 0], vec![8, 7, 4, 2]]);
+    let rounds = group_stage(&groups, players.len(), false);
+    assert_eq!(rounds.len(), 5);
+    for round in &rounds {
+        for (id, opponent) in round.iter().enumerate() {
+            if let Some(opponent) = opponent {
+                assert_eq!(groups[0].contains(&id), groups[0].contains(opponent));
+            }
+        }
+    }
+    // the group of 4 has finished after 3 rounds
+    assert!(groups[1].iter().all(|&id| rounds[3][id].is_none() && rounds[4][id].is_none()));
+}
@@ -0,0 +1,112 @@
+//! points given for the results of matchings
+
+use super::error::OpoError;
+use serde::{Deserialize, Serialize};
+
+/// points for each kind of result
+///
+/// the default is football-style 3/1/0, with 3 points for a bye and for a forfeit win.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ScoringSystem {
+    win: f64,
+    draw: f64,
+    loss: f64,
+    /// points for no opponent
+    bye: f64,
+    /// points when only the opponent withdrew
+    forfeit_win: f64,
+    /// points when only the player withdrew
+    forfeit_loss: f64,
+    /// a bye counts as a 2-0 game win in the game-win percentage ( otherwise it does not count )
+    #[serde(default)]
+    bye_game_win: bool,
+}
+
+impl Default for ScoringSystem {
+    fn default() -> Self {
+        ScoringSystem::football()
+    }
+}
+
+impl ScoringSystem {
+    /// the points must be finite, and a win must have more than 0 points ( the percentages are divided by them )
+    pub fn new(win: f64, draw: f64, loss: f64, bye: f64, forfeit_win: f64, forfeit_loss: f64) -> Result<Self, OpoError> {
+        let scoring = ScoringSystem { win, draw, loss, bye, forfeit_win, forfeit_loss, bye_game_win: false };
+        scoring.validate()?;
+        Ok(scoring)
+    }
+    /// check the points as `new` does ( e.g. after `with_bye`, or of a scoring from a file )
+    pub fn validate(&self) -> Result<(), OpoError> {
+        let points = [self.win, self.draw, self.loss, self.bye, self.forfeit_win, self.forfeit_loss];
+        if !points.iter().all(|points| points.is_finite()) {
+            return Err(OpoError::InvalidScoring("points must be finite"));
+        }
+        if self.win <= 0.0 {
+            return Err(OpoError::InvalidScoring("a win must have more than 0 points"));
+        }
+        Ok(())
+    }
+    /// the same scoring with other points for a bye
+    pub fn with_bye(self, bye: f64) -> Self {
+        ScoringSystem { bye, ..self }
+    }
+    /// the same scoring, where a bye counts as a 2-0 game win in the game-win percentage or not
+    pub fn with_bye_game_win(self, bye_game_win: bool) -> Self {
+        ScoringSystem { bye_game_win, ..self }
+    }
+    /// 1 for a win, 1/2 for a draw and 0 for a loss
+    pub fn chess() -> Self {
+        ScoringSystem { win: 1.0, draw: 0.5, loss: 0.0, bye: 1.0, forfeit_win: 1.0, forfeit_loss: 0.0, bye_game_win: false }
+    }
+    /// 3 for a win, 1 for a draw and 0 for a loss
+    pub fn football() -> Self {
+        ScoringSystem { win: 3.0, draw: 1.0, loss: 0.0, bye: 3.0, forfeit_win: 3.0, forfeit_loss: 0.0, bye_game_win: false }
+    }
+
+    pub fn win(&self) -> f64 {
+        self.win
+    }
+    pub fn draw(&self) -> f64 {
+        self.draw
+    }
+    pub fn loss(&self) -> f64 {
+        self.loss
+    }
+    pub fn bye(&self) -> f64 {
+        self.bye
+    }
+    pub fn forfeit_win(&self) -> f64 {
+        self.forfeit_win
+    }
+    pub fn forfeit_loss(&self) -> f64 {
+        self.forfeit_loss
+    }
+    pub fn bye_game_win(&self) -> bool {
+        self.bye_game_win
+    }
+    /// the points of a win, which percentages are divided by
+    pub fn max_points(&self) -> f64 {
+        self.win
+    }
+}
+
+/// points as an integer ( in thousandths ), to compare and to weight them exactly
+pub(crate) fn scaled_points(points: f64) -> i64 {
+    (points * 1000.0).round() as i64
+}
+
+#[test]
+fn test_scoring_systems() {
+    let s: ScoringSystem = Default::default();
+    assert_eq!(s, ScoringSystem::football());
+    assert_eq!((s.win(), s.draw(), s.loss(), s.bye()), (3.0, 1.0, 0.0, 3.0));
+    let s = ScoringSystem::chess();
+    assert_eq!((s.win(), s.draw(), s.loss(), s.max_points()), (1.0, 0.5, 0.0, 1.0));
+    let s = ScoringSystem::new(3.0, 1.0, 0.0, 2.0, 3.0, 0.0).unwrap();
+    assert_eq!(s.bye(), 2.0);
+    assert!(matches!(ScoringSystem::new(0.0, 0.0, 0.0, 0.0, 0.0, 0.0), Err(OpoError::InvalidScoring(_))));
+    assert!(matches!(ScoringSystem::new(1.0, f64::NAN, 0.0, 1.0, 1.0, 0.0), Err(OpoError::InvalidScoring(_))));
+    assert!(matches!(s.with_bye(f64::INFINITY).validate(), Err(OpoError::InvalidScoring(_))));
+    assert_eq!(scaled_points(0.5), 500);
+    assert_eq!(scaled_points(0.1 + 0.2), scaled_points(0.3));
+}
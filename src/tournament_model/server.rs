@@ -0,0 +1,584 @@
+//! the HTTP API of tournaments ( feature `server` )
+//!
+//! every route takes and answers JSON:
+//!
+//! | route | |
+//! | --- | --- |
+//! | `GET /tournaments` | the names |
+//! | `POST /tournaments` | create `{ "name", "scoring": "chess" \| "football", "pairing_system": "Points" \| "Dutch" }` |
+//! | `GET /tournaments/{name}` | the state, the round and the number of players |
+//! | `GET /tournaments/{name}/players` | the players |
+//! | `POST /tournaments/{name}/players` | register `{ "name", "rating", "initial_byes" }` |
+//! | `POST /tournaments/{name}/players/{id}/drop` | drop the player |
+//! | `GET /tournaments/{name}/pairings` | the tables of the current round with their results |
+//! | `POST /tournaments/{name}/pairings` | pair and start the next round ( see `Tournament::pair_next_round` ) |
+//! | `POST /tournaments/{name}/results` | enter `{ "player_id", "opponent_id", "wins", "draws", "losses", "player_withdraw", "opponent_withdraw" }` |
+//! | `PUT /tournaments/{name}/results` | correct a result, with its `"round_number"` |
+//! | `GET /tournaments/{name}/standings` | the standings with the tiebreakers |
+//! | `GET /tournaments/{name}/events` | the changes as server-sent events ( see `Api::subscribe` ) |
+//!
+//! the tournaments are kept by name, each behind its own lock, and an error answers `{ "error": message }` with `status`.
+//! with a directory, the tournaments are loaded from `<directory>/<name>.json` and saved there after every change.
+
+use super::error::OpoError;
+use super::journal::Event;
+use super::matching::Matching;
+use super::players::Player;
+use super::scoring::ScoringSystem;
+use super::swiss_system_tournament::{PairingSystem, RoundState, Tournament};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::thread;
+use std::time::Duration;
+
+/// how long an event stream stays silent before a comment checks that the client is still there
+const KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+/// the HTTP status of an error: 404 for what does not exist, 409 for what the state forbids,
+/// 500 for the storage and 400 for other bad input
+pub fn status(error: &OpoError) -> u16 {
+    match error {
+        OpoError::UnknownPlayer(_) | OpoError::NoSuchEvent(_) | OpoError::NoSuchResult { .. } | OpoError::NoSuchGame { .. } => 404,
+        OpoError::InvalidState { .. } | OpoError::DuplicateResult { .. } | OpoError::MissingResult(_)
+            | OpoError::PairingInfeasible | OpoError::PairingSearchExhausted { .. } | OpoError::EventExists(_) => 409,
+        OpoError::Io(_) | OpoError::Csv(_) => 500,
+        #[cfg(feature = "sqlite")]
+        OpoError::Sqlite(_) => 500,
+        _ => 400,
+    }
+}
+
+/// an answer of the API
+#[derive(Debug)]
+pub struct Response {
+    pub status: u16,
+    pub body: Value,
+}
+
+#[derive(Deserialize)]
+struct NewTournament {
+    name: String,
+    scoring: Option<String>,
+    pairing_system: Option<PairingSystem>,
+}
+
+#[derive(Deserialize)]
+struct NewPlayer {
+    name: String,
+    #[serde(default)]
+    rating: i32,
+    #[serde(default)]
+    initial_byes: usize,
+}
+
+#[derive(Deserialize)]
+struct ResultBody {
+    round_number: Option<i32>,
+    player_id: usize,
+    opponent_id: usize,
+    #[serde(default)]
+    wins: i32,
+    #[serde(default)]
+    draws: i32,
+    #[serde(default)]
+    losses: i32,
+    #[serde(default)]
+    player_withdraw: bool,
+    #[serde(default)]
+    opponent_withdraw: bool,
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    // a panic in another request leaves the data as consistent as the operations of `Tournament` do
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// the names are used as file names
+fn check_name(name: &str) -> Result<(), OpoError> {
+    let valid = !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(OpoError::InvalidFormat(format!("invalid tournament name: {:?}", name)))
+    }
+}
+
+fn current_round(tournament: &Tournament) -> i32 {
+    match tournament.state() {
+        RoundState::Paired(round_number) | RoundState::ResultsPending(round_number) | RoundState::Complete(round_number) => round_number,
+        _ => tournament.round_number(),
+    }
+}
+
+fn player_json(player: &Player) -> Value {
+    json!({
+        "id": player.id(),
+        "name": player.name(),
+        "rating": player.rating(),
+        "initial_byes": player.initial_byes(),
+        "dropped": player.is_dropped(),
+        "points": player.points(),
+        "fields": player.fields(),
+    })
+}
+
+fn pairings_json(tournament: &Tournament) -> Value {
+    let players = tournament.players();
+    let round_number = current_round(tournament);
+    let seats = tournament.tables().into_iter()
+        .map(|table| (Some(table.number), table.player_id, Some(table.opponent_id)))
+        .chain(tournament.byes().into_iter().map(|id| (None, id, None)));
+    let tables: Vec<Value> = seats.map(|(number, id, opponent_id): (Option<usize>, usize, Option<usize>)| {
+        let result = players[id].matching_list().iter().find(|m| m.round_number() == round_number).map(|m| json!({
+            "wins": m.win_count(),
+            "draws": m.draw_count(),
+            "losses": m.lose_count(),
+            "forfeit": !m.is_valid() && !m.is_no_opponent(),
+        }));
+        json!({
+            "table": number,
+            "player_id": id,
+            "player": players[id].name(),
+            "opponent_id": opponent_id,
+            "opponent": opponent_id.map(|opponent_id| players[opponent_id].name()),
+            "result": result,
+        })
+    }).collect();
+    json!({ "round_number": round_number, "state": tournament.state(), "tables": tables })
+}
+
+fn standings_json(tournament: &Tournament) -> Value {
+    let standings: Vec<Value> = tournament.ranked_standings().into_iter().enumerate().map(|(rank, (player, values))| {
+        let tiebreaks: BTreeMap<&str, f64> = tournament.tiebreaks().iter()
+            .map(|tiebreak| tiebreak.name())
+            .zip(values)
+            .collect();
+        json!({
+            "rank": rank + 1,
+            "player_id": player.id(),
+            "player": player.name(),
+            "points": player.points(),
+            "dropped": player.is_dropped(),
+            "tiebreaks": tiebreaks,
+        })
+    }).collect();
+    Value::Array(standings)
+}
+
+/// a server-sent event: `event: <kind>` and the JSON in `data: `
+fn message(kind: &str, data: &Value) -> String {
+    format!("event: {}\ndata: {}\n\n", kind, data)
+}
+
+/// the messages which tell what the events of the journal changed, each kind once:
+/// `pairings` and `results` carry the tables of the current round, `standings` the standings
+fn messages(tournament: &Tournament, events: &[Event]) -> Vec<String> {
+    let (mut pairings, mut results, mut standings) = (false, false, false);
+    for event in events {
+        match event {
+            Event::PairingPublished { .. } | Event::PairingWithdrawn { .. } | Event::RoundStarted { .. }
+                | Event::FeatureMatchSet { .. } => pairings = true,
+            Event::ResultEntered { .. } | Event::ResultCorrected { .. } | Event::ResultDeleted { .. }
+                | Event::RoundClosed { .. } | Event::Finished => {
+                results = true;
+                standings = true;
+            }
+            Event::PlayerAdded { .. } | Event::PlayerDropped { .. } | Event::TiebreaksChanged { .. }
+                | Event::ScoringChanged { .. } | Event::ResultsImported { .. } => standings = true,
+            Event::PairingSystemChanged { .. } | Event::TableSettingsChanged { .. }
+                | Event::ByeAssigned { .. } | Event::ByeUnassigned { .. } => {}
+        }
+    }
+    let mut messages = Vec::new();
+    if pairings {
+        messages.push(message("pairings", &pairings_json(tournament)));
+    }
+    if results {
+        messages.push(message("results", &pairings_json(tournament)));
+    }
+    if standings {
+        messages.push(message("standings", &standings_json(tournament)));
+    }
+    messages
+}
+
+/// the tournaments served by the API
+pub struct Api {
+    tournaments: Mutex<BTreeMap<String, Arc<Mutex<Tournament>>>>,
+    /// the event streams of each tournament ( always locked after the tournament )
+    subscribers: Mutex<BTreeMap<String, Vec<Sender<String>>>>,
+    directory: Option<PathBuf>,
+}
+
+impl Api {
+    /// an API which keeps the tournaments in memory only
+    pub fn in_memory() -> Self {
+        Api { tournaments: Mutex::new(BTreeMap::new()), subscribers: Mutex::new(BTreeMap::new()), directory: None }
+    }
+
+    /// an API which loads the tournaments of the directory ( created if missing ), and saves them there
+    pub fn with_directory<P: Into<PathBuf>>(directory: P) -> Result<Self, OpoError> {
+        let directory = directory.into();
+        fs::create_dir_all(&directory)?;
+        let mut tournaments = BTreeMap::new();
+        for entry in fs::read_dir(&directory)? {
+            let path = entry?.path();
+            let name = match (path.extension(), path.file_stem().and_then(|stem| stem.to_str())) {
+                (Some(extension), Some(name)) if extension == "json" && check_name(name).is_ok() => name.to_string(),
+                _ => continue,
+            };
+            tournaments.insert(name, Arc::new(Mutex::new(Tournament::load(&path)?)));
+        }
+        Ok(Api { tournaments: Mutex::new(tournaments), subscribers: Mutex::new(BTreeMap::new()), directory: Some(directory) })
+    }
+
+    fn tournament(&self, name: &str) -> Result<Arc<Mutex<Tournament>>, OpoError> {
+        lock(&self.tournaments).get(name).cloned().ok_or_else(|| OpoError::NoSuchEvent(name.to_string()))
+    }
+
+    fn save(&self, name: &str, tournament: &Tournament) -> Result<(), OpoError> {
+        match &self.directory {
+            Some(directory) => tournament.save(directory.join(format!("{}.json", name))),
+            None => Ok(()),
+        }
+    }
+
+    /// change the tournament under its lock, save it when the change succeeds and notify the subscribers
+    ///
+    /// a change which can not be saved is undone, so that the tournament in memory stays the one on the disk.
+    fn change<F: FnOnce(&mut Tournament) -> Result<Value, OpoError>>(&self, name: &str, change: F) -> Result<Value, OpoError> {
+        let tournament = self.tournament(name)?;
+        let mut tournament = lock(&tournament);
+        let journal_length = tournament.journal().len();
+        let saved = self.directory.as_ref().map(|_| tournament.to_json()).transpose()?;
+        let value = change(&mut tournament)?;
+        if let Err(error) = self.save(name, &tournament) {
+            if let Some(saved) = saved {
+                *tournament = Tournament::from_json(&saved)?;
+            }
+            return Err(error);
+        }
+        let messages = messages(&tournament, &tournament.journal()[journal_length..]);
+        if let Some(subscribers) = lock(&self.subscribers).get_mut(name) {
+            // a stream whose client went away has dropped its receiver
+            subscribers.retain(|subscriber| messages.iter().all(|message| subscriber.send(message.clone()).is_ok()));
+        }
+        Ok(value)
+    }
+
+    /// the server-sent events of the tournament: first its pairings and standings,
+    /// then the messages of every change ( see `messages` ), in the order of the changes
+    pub fn subscribe(&self, name: &str) -> Result<Receiver<String>, OpoError> {
+        let tournament = self.tournament(name)?;
+        let tournament = lock(&tournament);
+        let (sender, receiver) = mpsc::channel();
+        for message in [message("pairings", &pairings_json(&tournament)), message("standings", &standings_json(&tournament))] {
+            // the receiver is still here
+            let _ = sender.send(message);
+        }
+        lock(&self.subscribers).entry(name.to_string()).or_default().push(sender);
+        Ok(receiver)
+    }
+
+    fn read<F: FnOnce(&Tournament) -> Value>(&self, name: &str, read: F) -> Result<Value, OpoError> {
+        let tournament = self.tournament(name)?;
+        let tournament = lock(&tournament);
+        Ok(read(&tournament))
+    }
+
+    fn create(&self, body: &str) -> Result<Value, OpoError> {
+        let request: NewTournament = serde_json::from_str(body)?;
+        check_name(&request.name)?;
+        let mut tournament: Tournament = Default::default();
+        match request.scoring.as_deref() {
+            Some("chess") => tournament.set_scoring_system(ScoringSystem::chess())?,
+            Some("football") | None => {}
+            Some(scoring) => return Err(OpoError::InvalidFormat(format!("unknown scoring: {}", scoring))),
+        }
+        if let Some(pairing_system) = request.pairing_system {
+            tournament.set_pairing_system(pairing_system);
+        }
+        let mut tournaments = lock(&self.tournaments);
+        if tournaments.contains_key(&request.name) {
+            return Err(OpoError::EventExists(request.name));
+        }
+        self.save(&request.name, &tournament)?;
+        tournaments.insert(request.name.clone(), Arc::new(Mutex::new(tournament)));
+        Ok(json!({ "name": request.name }))
+    }
+
+    fn enter_result(tournament: &mut Tournament, body: &str, correction: bool) -> Result<Value, OpoError> {
+        let request: ResultBody = serde_json::from_str(body)?;
+        let round_number = match (correction, request.round_number, tournament.state()) {
+            (true, Some(round_number), _) => round_number,
+            (true, None, _) => return Err(OpoError::InvalidFormat("a correction needs the round_number".to_string())),
+            (false, _, RoundState::ResultsPending(round_number)) => round_number,
+            (false, _, state) => return Err(OpoError::InvalidState { state, action: "record a result" }),
+        };
+        let matching = Matching::new(
+            round_number, request.player_id, request.opponent_id, request.wins, request.draws, request.losses,
+            request.player_withdraw, request.opponent_withdraw,
+        )?;
+        if correction {
+            let changed_rounds = tournament.amend_result(matching)?;
+            return Ok(json!({ "changed_rounds": changed_rounds }));
+        }
+        tournament.record_result(matching)?;
+        let outstanding = tournament.outstanding_tables().len();
+        if outstanding == 0 {
+            tournament.close_round()?;
+        }
+        Ok(json!({ "outstanding": outstanding, "round_complete": outstanding == 0 }))
+    }
+
+    fn route(&self, method: &str, segments: &[&str], body: &str) -> Result<Option<(u16, Value)>, OpoError> {
+        let parse_id = |id: &str| id.parse::<usize>().map_err(|_| OpoError::InvalidFormat(format!("invalid player id: {}", id)));
+        let answer = match (method, segments) {
+            ("GET", ["tournaments"]) => (200, json!(lock(&self.tournaments).keys().collect::<Vec<_>>())),
+            ("POST", ["tournaments"]) => (201, self.create(body)?),
+            ("GET", ["tournaments", name]) => (200, self.read(name, |t| json!({
+                "name": name,
+                "state": t.state(),
+                "round_number": current_round(t),
+                "player_number": t.players().len(),
+            }))?),
+            ("GET", ["tournaments", name, "players"]) => {
+                (200, self.read(name, |t| Value::Array(t.players().iter().map(player_json).collect()))?)
+            }
+            ("POST", ["tournaments", name, "players"]) => {
+                let request: NewPlayer = serde_json::from_str(body)?;
+                (201, self.change(name, |t| {
+                    let mut player = Player::new(t.players().len(), request.name);
+                    player.set_rating(request.rating);
+                    player.set_initial_byes(request.initial_byes);
+                    t.add_player(player);
+                    Ok(player_json(&t.players()[t.players().len() - 1]))
+                })?)
+            }
+            ("POST", ["tournaments", name, "players", id, "drop"]) => {
+                let id = parse_id(id)?;
+                (200, self.change(name, |t| {
+                    t.drop_player(id)?;
+                    Ok(player_json(&t.players()[id]))
+                })?)
+            }
+            ("GET", ["tournaments", name, "pairings"]) => (200, self.read(name, pairings_json)?),
+            ("POST", ["tournaments", name, "pairings"]) => (201, self.change(name, |t| {
+                t.pair_next_round()?;
+                Ok(pairings_json(t))
+            })?),
+            ("POST", ["tournaments", name, "results"]) => (200, self.change(name, |t| Self::enter_result(t, body, false))?),
+            ("PUT", ["tournaments", name, "results"]) => (200, self.change(name, |t| Self::enter_result(t, body, true))?),
+            ("GET", ["tournaments", name, "standings"]) => (200, self.read(name, standings_json)?),
+            _ => return Ok(None),
+        };
+        Ok(Some(answer))
+    }
+
+    /// answer a request: `url` is the path ( a query is ignored ) and `body` the JSON of the request
+    pub fn handle(&self, method: &str, url: &str, body: &str) -> Response {
+        let path = url.split('?').next().unwrap_or("");
+        let segments: Vec<&str> = path.split('/').filter(|segment| !segment.is_empty()).collect();
+        match self.route(method, &segments, body) {
+            Ok(Some((status, body))) => Response { status, body },
+            Ok(None) => Response { status: 404, body: json!({ "error": format!("no route: {} {}", method, path) }) },
+            Err(e) => Response { status: status(&e), body: json!({ "error": e.to_string() }) },
+        }
+    }
+}
+
+/// the tournament name of `/tournaments/{name}/events`
+fn events_route(url: &str) -> Option<&str> {
+    let path = url.split('?').next().unwrap_or("");
+    match path.split('/').filter(|segment| !segment.is_empty()).collect::<Vec<_>>()[..] {
+        ["tournaments", name, "events"] => Some(name),
+        _ => None,
+    }
+}
+
+/// write the events to the connection until the API or the client goes away
+///
+/// the stream is written by hand, since the responses of tiny_http are buffered.
+fn stream_events(mut writer: Box<dyn Write + Send>, events: Receiver<String>) -> io::Result<()> {
+    writer.write_all(b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n")?;
+    writer.flush()?;
+    loop {
+        let message = match events.recv_timeout(KEEP_ALIVE) {
+            Ok(message) => message,
+            Err(RecvTimeoutError::Timeout) => ": keep-alive\n\n".to_string(),
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        };
+        writer.write_all(message.as_bytes())?;
+        writer.flush()?;
+    }
+}
+
+/// serve the API at the address ( e.g. `127.0.0.1:8080` ) with `threads` workers, until the process ends
+pub fn serve(api: Arc<Api>, address: &str, threads: usize) -> Result<(), OpoError> {
+    let server = tiny_http::Server::http(address).map_err(|e| OpoError::Io(io::Error::new(io::ErrorKind::Other, e.to_string())))?;
+    let server = Arc::new(server);
+    let workers: Vec<_> = (0..threads.max(1)).map(|_| {
+        let (server, api) = (Arc::clone(&server), Arc::clone(&api));
+        thread::spawn(move || {
+            while let Ok(mut request) = server.recv() {
+                let subscription = match (request.method(), events_route(request.url())) {
+                    (tiny_http::Method::Get, Some(name)) => Some(api.subscribe(name)),
+                    _ => None,
+                };
+                let mut body = String::new();
+                let response = match subscription {
+                    Some(Ok(events)) => {
+                        let writer = request.into_writer();
+                        // the stream lasts as long as its client, so it does not hold a worker
+                        thread::spawn(move || stream_events(writer, events));
+                        continue;
+                    }
+                    Some(Err(e)) => Response { status: status(&e), body: json!({ "error": e.to_string() }) },
+                    None => match request.as_reader().read_to_string(&mut body) {
+                        Ok(_) => api.handle(request.method().as_str(), request.url(), &body),
+                        Err(e) => Response { status: 400, body: json!({ "error": e.to_string() }) },
+                    },
+                };
+                let header = tiny_http::Header::from_bytes("Content-Type", "application/json").expect("a valid header");
+                let http_response = tiny_http::Response::from_string(response.body.to_string())
+                    .with_status_code(response.status)
+                    .with_header(header);
+                // the client may have gone away, which does not concern the other requests
+                let _ = request.respond(http_response);
+            }
+        })
+    }).collect();
+    for worker in workers {
+        let _ = worker.join();
+    }
+    Ok(())
+}
+
+#[test]
+fn test_api() {
+    let api = Api::in_memory();
+    let call = |method: &str, url: &str, body: &str| {
+        let response = api.handle(method, url, body);
+        (response.status, response.body)
+    };
+    assert_eq!(call("POST", "/tournaments", r#"{"name": "open", "scoring": "chess"}"#).0, 201);
+    assert_eq!(call("POST", "/tournaments", r#"{"name": "open"}"#).0, 409);
+    assert_eq!(call("POST", "/tournaments", r#"{"name": "../etc"}"#).0, 400);
+    assert_eq!(call("GET", "/tournaments", "").1, json!(["open"]));
+    for name in ["Alice", "Bob", "Carol", "Dave"] {
+        let (status, player) = call("POST", "/tournaments/open/players", &format!(r#"{{"name": "{}", "rating": 1500}}"#, name));
+        assert_eq!((status, &player["name"]), (201, &json!(name)));
+    }
+    assert_eq!(call("POST", "/tournaments/open/players", "{").0, 400);
+    assert_eq!(call("GET", "/tournaments/open/players", "").1.as_array().unwrap().len(), 4);
+    assert_eq!(call("GET", "/tournaments/nothing/players", "").0, 404);
+    assert_eq!(call("GET", "/nothing", "").0, 404);
+
+    let (status, pairings) = call("POST", "/tournaments/open/pairings", "");
+    assert_eq!(status, 201);
+    assert_eq!(pairings["state"], json!({ "ResultsPending": 1 }));
+    assert_eq!(call("POST", "/tournaments/open/pairings", "").0, 409);
+    let tables = pairings["tables"].as_array().unwrap().clone();
+    assert_eq!(tables.len(), 2);
+    let table = |i: usize| (tables[i]["player_id"].as_u64().unwrap(), tables[i]["opponent_id"].as_u64().unwrap());
+
+    let (a, b) = table(0);
+    let result = format!(r#"{{"player_id": {}, "opponent_id": {}, "wins": 1}}"#, a, b);
+    assert_eq!(call("POST", "/tournaments/open/results", &result).1, json!({ "outstanding": 1, "round_complete": false }));
+    assert_eq!(call("POST", "/tournaments/open/results", &result).0, 409);
+    let wrong = format!(r#"{{"player_id": {}, "opponent_id": {}, "wins": 1}}"#, a, table(1).0);
+    assert_eq!(call("POST", "/tournaments/open/results", &wrong).0, 400);
+    let (c, d) = table(1);
+    let result = format!(r#"{{"player_id": {}, "opponent_id": {}, "draws": 1}}"#, c, d);
+    assert_eq!(call("POST", "/tournaments/open/results", &result).1["round_complete"], json!(true));
+    let correction = format!(r#"{{"round_number": 1, "player_id": {}, "opponent_id": {}, "losses": 1}}"#, a, b);
+    assert_eq!(call("PUT", "/tournaments/open/results", &correction).1, json!({ "changed_rounds": [] }));
+
+    assert_eq!(call("POST", &format!("/tournaments/open/players/{}/drop", a), "").1["dropped"], json!(true));
+    assert_eq!(call("POST", "/tournaments/open/players/9/drop", "").0, 404);
+    let (_, standings) = call("GET", "/tournaments/open/standings", "");
+    assert_eq!(standings[0]["player_id"], json!(b));
+    assert_eq!(standings[0]["points"], json!(1.0));
+    assert!(standings[0]["tiebreaks"]["OMW%"].is_number());
+    assert_eq!(standings[3]["dropped"], json!(true));
+    assert_eq!(call("GET", "/tournaments/open", "").1["round_number"], json!(1));
+}
+
+#[test]
+fn test_api_concurrency_and_directory() {
+    let directory = std::env::temp_dir().join(format!("opo_test_server_{}", std::process::id()));
+    let api = Arc::new(Api::with_directory(&directory).unwrap());
+    for name in ["a", "b"] {
+        assert_eq!(api.handle("POST", "/tournaments", &format!(r#"{{"name": "{}"}}"#, name)).status, 201);
+    }
+    let workers: Vec<_> = (0..8).map(|i| {
+        let api = Arc::clone(&api);
+        thread::spawn(move || {
+            let name = if i % 2 == 0 { "a" } else { "b" };
+            for j in 0..10 {
+                let body = format!(r#"{{"name": "{}-{}"}}"#, i, j);
+                assert_eq!(api.handle("POST", &format!("/tournaments/{}/players", name), &body).status, 201);
+            }
+        })
+    }).collect();
+    for worker in workers {
+        worker.join().unwrap();
+    }
+    let reloaded = Api::with_directory(&directory).unwrap();
+    fs::remove_dir_all(&directory).unwrap();
+    for name in ["a", "b"] {
+        let players = reloaded.handle("GET", &format!("/tournaments/{}/players", name), "").body;
+        let ids: Vec<u64> = players.as_array().unwrap().iter().map(|p| p["id"].as_u64().unwrap()).collect();
+        assert_eq!(ids, (0..40).collect::<Vec<u64>>());
+    }
+}
+
+#[test]
+fn test_subscribe() {
+    let api = Api::in_memory();
+    assert!(matches!(api.subscribe("open"), Err(OpoError::NoSuchEvent(_))));
+    api.handle("POST", "/tournaments", r#"{"name": "open"}"#);
+    let events = api.subscribe("open").unwrap();
+    let kinds = |events: &Receiver<String>| -> Vec<String> {
+        events.try_iter().map(|message| message.lines().next().unwrap().trim_start_matches("event: ").to_string()).collect()
+    };
+    assert_eq!(kinds(&events), vec!["pairings", "standings"]);
+    api.handle("POST", "/tournaments/open/players", r#"{"name": "Alice"}"#);
+    api.handle("POST", "/tournaments/open/players", r#"{"name": "Bob"}"#);
+    assert_eq!(kinds(&events), vec!["standings", "standings"]);
+    assert_eq!(api.handle("POST", "/tournaments/open/results", r#"{"player_id": 0, "opponent_id": 1, "wins": 1}"#).status, 409);
+    assert!(kinds(&events).is_empty());
+
+    api.handle("POST", "/tournaments/open/pairings", "");
+    let message = events.try_recv().unwrap();
+    let data: Value = serde_json::from_str(message.lines().nth(1).unwrap().trim_start_matches("data: ")).unwrap();
+    assert_eq!(data["tables"].as_array().unwrap().len(), 1);
+    assert!(kinds(&events).is_empty());
+    api.handle("POST", "/tournaments/open/results", r#"{"player_id": 0, "opponent_id": 1, "wins": 1}"#);
+    assert_eq!(kinds(&events), vec!["results", "standings"]);
+
+    drop(events);
+    api.handle("POST", "/tournaments/open/players/1/drop", "");
+    assert!(lock(&api.subscribers)["open"].is_empty());
+}
+
+#[test]
+fn test_change_which_can_not_be_saved() {
+    let directory = std::env::temp_dir().join(format!("opo_test_server_unsaved_{}", std::process::id()));
+    let api = Api::with_directory(&directory).unwrap();
+    assert_eq!(api.handle("POST", "/tournaments", r#"{"name": "open"}"#).status, 201);
+    let events = api.subscribe("open").unwrap();
+    assert_eq!(events.try_iter().count(), 2);
+    // the directory is gone, so the tournament can not be written
+    fs::remove_dir_all(&directory).unwrap();
+    assert_eq!(api.handle("POST", "/tournaments/open/players", r#"{"name": "Alice"}"#).status, 500);
+    assert!(api.handle("GET", "/tournaments/open/players", "").body.as_array().unwrap().is_empty());
+    assert!(lock(&api.tournament("open").unwrap()).journal().is_empty());
+    assert!(events.try_recv().is_err());
+}
@@ -0,0 +1,219 @@
+//! storage of tournaments in an SQLite file ( feature `sqlite` )
+//!
+//! one file holds several events by name. each event keeps the saved tournament ( as `Tournament::to_json` ),
+//! and its players, matchings, published rounds and journal in their own tables, so that they can be queried.
+
+use super::error::OpoError;
+use super::swiss_system_tournament::Tournament;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::path::Path;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS events (
+    name TEXT PRIMARY KEY,
+    tournament TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS players (
+    event TEXT NOT NULL REFERENCES events(name) ON DELETE CASCADE,
+    id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    rating INTEGER NOT NULL,
+    dropped INTEGER NOT NULL,
+    points REAL NOT NULL,
+    PRIMARY KEY (event, id)
+);
+CREATE TABLE IF NOT EXISTS matchings (
+    event TEXT NOT NULL REFERENCES events(name) ON DELETE CASCADE,
+    round_number INTEGER NOT NULL,
+    player_id INTEGER NOT NULL,
+    opponent_id INTEGER,
+    win_count INTEGER NOT NULL,
+    draw_count INTEGER NOT NULL,
+    lose_count INTEGER NOT NULL,
+    color TEXT,
+    matching TEXT NOT NULL,
+    PRIMARY KEY (event, round_number, player_id)
+);
+CREATE TABLE IF NOT EXISTS rounds (
+    event TEXT NOT NULL REFERENCES events(name) ON DELETE CASCADE,
+    round_number INTEGER NOT NULL,
+    pairings TEXT NOT NULL,
+    PRIMARY KEY (event, round_number)
+);
+CREATE TABLE IF NOT EXISTS journal (
+    event TEXT NOT NULL REFERENCES events(name) ON DELETE CASCADE,
+    seq INTEGER NOT NULL,
+    entry TEXT NOT NULL,
+    PRIMARY KEY (event, seq)
+);
+";
+
+/// an SQLite file of tournaments
+pub struct SqliteStore {
+    connection: Connection,
+}
+
+impl SqliteStore {
+    /// open the file, and create the tables if they do not exist
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, OpoError> {
+        Self::with_connection(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> Result<Self, OpoError> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(connection: Connection) -> Result<Self, OpoError> {
+        connection.execute_batch("PRAGMA foreign_keys = ON;")?;
+        connection.execute_batch(SCHEMA)?;
+        Ok(SqliteStore { connection })
+    }
+
+    /// names of the events in the file, in alphabetical order
+    pub fn events(&self) -> Result<Vec<String>, OpoError> {
+        let mut statement = self.connection.prepare("SELECT name FROM events ORDER BY name")?;
+        let names = statement.query_map([], |row| row.get(0))?.collect::<Result<_, _>>()?;
+        Ok(names)
+    }
+
+    /// save the tournament as the event `name` ( replacing the saved one ) in one transaction
+    ///
+    /// the journal is append-only: only the events after the saved ones are written.
+    pub fn save(&mut self, name: &str, tournament: &Tournament) -> Result<(), OpoError> {
+        let json = tournament.to_json()?;
+        let transaction = self.connection.transaction()?;
+        transaction.execute(
+            "INSERT INTO events (name, tournament) VALUES (?1, ?2)
+             ON CONFLICT (name) DO UPDATE SET tournament = excluded.tournament",
+            params![name, json],
+        )?;
+        for table in ["players", "matchings", "rounds"] {
+            transaction.execute(&format!("DELETE FROM {} WHERE event = ?1", table), params![name])?;
+        }
+        for player in tournament.players() {
+            transaction.execute(
+                "INSERT INTO players (event, id, name, rating, dropped, points) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![name, player.id() as i64, player.name(), player.rating(), player.is_dropped(), player.points()],
+            )?;
+            for matching in player.matching_list() {
+                let opponent_id = if matching.is_reversible() { Some(matching.opponent_id() as i64) } else { None };
+                let color = matching.color().map(|color| format!("{:?}", color));
+                transaction.execute(
+                    "INSERT INTO matchings (event, round_number, player_id, opponent_id, win_count, draw_count, lose_count, color, matching)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                    params![
+                        name, matching.round_number(), player.id() as i64, opponent_id,
+                        matching.win_count(), matching.draw_count(), matching.lose_count(), color,
+                        serde_json::to_string(matching)?,
+                    ],
+                )?;
+            }
+        }
+        for round_number in 1..=tournament.round_number() + 1 {
+            let pairings = tournament.round_pairings(round_number);
+            if !pairings.is_empty() {
+                transaction.execute(
+                    "INSERT INTO rounds (event, round_number, pairings) VALUES (?1, ?2, ?3)",
+                    params![name, round_number, serde_json::to_string(pairings)?],
+                )?;
+            }
+        }
+        let saved: i64 = transaction.query_row("SELECT COUNT(*) FROM journal WHERE event = ?1", params![name], |row| row.get(0))?;
+        // a shorter journal is another tournament saved under the same name
+        transaction.execute("DELETE FROM journal WHERE event = ?1 AND seq >= ?2", params![name, tournament.journal().len() as i64])?;
+        for (seq, entry) in tournament.journal().iter().enumerate().skip(saved as usize) {
+            transaction.execute(
+                "INSERT INTO journal (event, seq, entry) VALUES (?1, ?2, ?3)",
+                params![name, seq as i64, serde_json::to_string(entry)?],
+            )?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// load the event `name`
+    pub fn load(&self, name: &str) -> Result<Tournament, OpoError> {
+        let json: Option<String> = self.connection
+            .query_row("SELECT tournament FROM events WHERE name = ?1", params![name], |row| row.get(0))
+            .optional()?;
+        let json = json.ok_or_else(|| OpoError::NoSuchEvent(name.to_string()))?;
+        Tournament::from_json(&json)
+    }
+
+    /// delete the event `name` and everything saved with it
+    pub fn delete(&mut self, name: &str) -> Result<(), OpoError> {
+        match self.connection.execute("DELETE FROM events WHERE name = ?1", params![name])? {
+            0 => Err(OpoError::NoSuchEvent(name.to_string())),
+            _ => Ok(()),
+        }
+    }
+
+    /// change the tournament, and save it as the event `name` when the change succeeds
+    ///
+    /// a failed change leaves the tournament as it was ( the operations of `Tournament` change nothing on error ),
+    /// so nothing is saved.
+    pub fn update<T, F>(&mut self, name: &str, tournament: &mut Tournament, change: F) -> Result<T, OpoError>
+    where F: FnOnce(&mut Tournament) -> Result<T, OpoError> {
+        let result = change(tournament)?;
+        self.save(name, tournament)?;
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+use super::{matching::Matching, players::Player};
+
+#[cfg(test)]
+fn row_count(store: &SqliteStore, table: &str) -> i64 {
+    store.connection
+        .query_row(&format!("SELECT COUNT(*) FROM {} WHERE event = 'spring'", table), [], |row| row.get(0))
+        .unwrap()
+}
+
+#[test]
+fn test_sqlite_store() {
+    let mut store = SqliteStore::open_in_memory().unwrap();
+    let mut t: Tournament = Default::default();
+    for i in 0..4 {
+        store.update("spring", &mut t, |t| {
+            t.add_player(Player::new(i, format!("{}abcd", i)));
+            Ok(())
+        }).unwrap();
+    }
+    let pairings = store.update("spring", &mut t, |t| Ok(t.pair_round()?.to_vec())).unwrap();
+    store.update("spring", &mut t, |t| t.start_round()).unwrap();
+    let opponent = pairings[0].unwrap();
+    store.update("spring", &mut t, |t| t.record_result(Matching::new(1, 0, opponent, 2, 0, 0, false, false)?)).unwrap();
+    // a refused change is not saved
+    let result = store.update("spring", &mut t, |t| t.close_round());
+    assert!(matches!(result, Err(OpoError::MissingResult(_))));
+
+    let mut other: Tournament = Default::default();
+    other.add_player(Player::new(0, "0abcd".to_string()));
+    store.save("autumn", &other).unwrap();
+    assert_eq!(store.events().unwrap(), vec!["autumn".to_string(), "spring".to_string()]);
+
+    let loaded = store.load("spring").unwrap();
+    assert_eq!(loaded.to_json().unwrap(), t.to_json().unwrap());
+    assert_eq!(store.load("autumn").unwrap().players().len(), 1);
+    assert_eq!(row_count(&store, "players"), 4);
+    assert_eq!(row_count(&store, "matchings"), 2);
+    assert_eq!(row_count(&store, "rounds"), 1);
+    assert_eq!(row_count(&store, "journal") as usize, t.journal().len());
+
+    store.delete("spring").unwrap();
+    assert_eq!(row_count(&store, "matchings"), 0);
+    assert!(matches!(store.load("spring"), Err(OpoError::NoSuchEvent(_))));
+    assert!(matches!(store.delete("spring"), Err(OpoError::NoSuchEvent(_))));
+}
+
+#[test]
+fn test_sqlite_file() {
+    let path = std::env::temp_dir().join(format!("opo_test_sqlite_{}.db", std::process::id()));
+    let mut t: Tournament = Default::default();
+    t.add_player(Player::new(0, "0abcd".to_string()));
+    SqliteStore::open(&path).unwrap().save("spring", &t).unwrap();
+    let loaded = SqliteStore::open(&path).unwrap().load("spring").unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded.to_json().unwrap(), t.to_json().unwrap());
+}
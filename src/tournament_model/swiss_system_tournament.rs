@@ -1,35 +1,253 @@
+use super::color::Color;
+use super::elimination::{DoubleElimination, SingleElimination};
+use super::error::OpoError;
+use super::journal::Event;
 use super::matching::Matching;
 use super::players::Player;
+use super::round_robin::{group_stage, round_robin, seed_by_rating, split_groups, GroupSeeding};
+use super::scoring::{scaled_points, ScoringSystem};
+use super::tables::{number_tables, Table, TableSettings};
+use super::tiebreak::{builtin, default_tiebreaks, ranked, standings, Ranked, Tiebreak};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
 
-#[derive(Default, Debug)]
+/// the version of the JSON format written by `Tournament::to_json`
+///
+/// files of an older version can be loaded, and fields missing in them get the default value.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// how `Tournament::matching_build` pairs the players
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum PairingSystem {
+    /// pair down by points ( minimum point difference )
+    #[default]
+    Points,
+    /// FIDE Dutch system ( C.04.3 ) for chess events
+    ///
+    /// this is not a complete implementation of C.04.3:
+    /// - the quality criteria are the number of downfloaters ( C.5 ), the colour preferences ( C.10 ), the strong colour preferences ( C.11 )
+    ///   and the floats of the previous round ( C.12, C.13 ). the scores of the downfloaters ( C.6 ), the pairing of the next bracket ( C.7 ),
+    ///   the colours of the topscorers ( C.8, C.9 ), the floats of two rounds before ( C.14, C.15 )
+    ///   and the score differences of the floaters ( C.16 to C.19 ) are not looked at.
+    /// - at most 20000 candidates are examined in a bracket. a bracket which needs more is not paired,
+    ///   and the pairing fails with `OpoError::PairingSearchExhausted` ( instead of taking the best candidate found ).
+    Dutch,
+}
+
+/// where the tournament is in its lifecycle
+///
+/// registration → round 1 paired → results of round 1 pending → round 1 complete → round 2 paired → ... → finished
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum RoundState {
+    /// no round has been paired yet
+    #[default]
+    Registration,
+    /// the pairings of the round are published, and the round has not started
+    Paired(i32),
+    /// the round has started, and its results are awaited
+    ResultsPending(i32),
+    /// every result of the round is in
+    Complete(i32),
+    Finished,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Tournament {
     player_number: i32,
     // available_player_number: i32,
     // matching_number: i32,
     players: Vec<Player>,
+    pairing_system: PairingSystem,
+    /// tiebreakers used by the standings and the pairing, in order ( saved by name )
+    #[serde(with = "super::tiebreak::by_name")]
+    tiebreaks: Vec<Box<dyn Tiebreak>>,
+    /// points for each kind of result
+    scoring: ScoringSystem,
+    state: RoundState,
+    /// the published pairings of each round, in the shape of `matching_build`
+    pairings: BTreeMap<i32, Vec<Option<usize>>>,
+    /// the colours of the published pairings of the current round
+    pairing_colors: Vec<Option<Color>>,
+    /// how the tables are numbered
+    table_settings: TableSettings,
+    /// the numbered tables of the published pairings of each round
+    tables: BTreeMap<i32, Vec<Table>>,
+    /// the players given a bye before the pairing, by round, with the points of the bye ( `None` for the bye points of the scoring )
+    assigned_byes: BTreeMap<i32, BTreeMap<usize, Option<f64>>>,
+    /// every operation on the tournament, oldest first
+    journal: Vec<Event>,
     // matching_list: Vec<Matching>,
 }
 
+impl Default for Tournament {
+    fn default() -> Self {
+        Tournament {
+            player_number: 0,
+            players: Vec::new(),
+            pairing_system: PairingSystem::default(),
+            tiebreaks: default_tiebreaks(),
+            scoring: ScoringSystem::default(),
+            state: RoundState::default(),
+            pairings: BTreeMap::new(),
+            pairing_colors: Vec::new(),
+            table_settings: TableSettings::default(),
+            tables: BTreeMap::new(),
+            assigned_byes: BTreeMap::new(),
+            journal: Vec::new(),
+        }
+    }
+}
+
+/// a saved tournament: `{ "version": FORMAT_VERSION, "tournament": { ... } }`
+#[derive(Serialize)]
+struct SavedTournament<'a> {
+    version: u32,
+    tournament: &'a Tournament,
+}
+
 impl Tournament {
-    /// make up json file tournament data. name is `tournament_name`.json.
-    // pub fn make_json() {
-    //     unimplemented!();
-    // }
+    /// the players and their results as a FIDE tournament report ( see `trf::export` )
+    pub fn to_trf(&self) -> String {
+        super::trf::export(self)
+    }
+
+    /// a tournament from a FIDE tournament report ( see `trf::import` )
+    pub fn from_trf(trf: &str) -> Result<Self, OpoError> {
+        super::trf::import(trf)
+    }
+
+    /// the whole tournament ( players, their matchings and the configuration ) as versioned JSON
+    pub fn to_json(&self) -> Result<String, OpoError> {
+        let saved = SavedTournament { version: FORMAT_VERSION, tournament: self };
+        Ok(serde_json::to_string_pretty(&saved)?)
+    }
+
+    /// load a tournament written by `to_json`
+    pub fn from_json(json: &str) -> Result<Self, OpoError> {
+        let mut saved: serde_json::Value = serde_json::from_str(json)?;
+        let version = saved.get("version")
+            .and_then(serde_json::Value::as_u64)
+            .ok_or_else(|| OpoError::InvalidFormat("no format version".to_string()))?;
+        if version > FORMAT_VERSION as u64 {
+            return Err(OpoError::UnsupportedVersion(version));
+        }
+        let tournament = saved.get_mut("tournament")
+            .map(serde_json::Value::take)
+            .ok_or_else(|| OpoError::InvalidFormat("no tournament".to_string()))?;
+        let tournament: Tournament = serde_json::from_value(tournament)?;
+        tournament.scoring.validate()?;
+        Ok(tournament)
+    }
+
+    /// save the tournament to `path` as JSON
+    ///
+    /// the file is written next to `path` first and then renamed,
+    /// so the previous save is kept if the process stops while writing.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), OpoError> {
+        let path = path.as_ref();
+        let mut temporary = path.as_os_str().to_owned();
+        temporary.push(".tmp");
+        let json = self.to_json()?;
+        fs::write(&temporary, json)?;
+        Ok(fs::rename(&temporary, path)?)
+    }
+
+    /// load a tournament saved by `save`
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, OpoError> {
+        let json = fs::read_to_string(path)?;
+        Self::from_json(&json)
+    }
+
+    /// the operations on the tournament, oldest first
+    ///
+    /// a tournament saved before the journal existed has an empty journal.
+    pub fn journal(&self) -> &[Event] {
+        &self.journal
+    }
+
+    /// rebuild a tournament from the events of a journal ( or from its first events, to see the tournament at that point )
+    ///
+    /// the pairings are published as they were, not paired again.
+    pub fn replay(events: &[Event]) -> Result<Self, OpoError> {
+        let mut tournament: Tournament = Default::default();
+        for event in events {
+            tournament.apply(event.clone())?;
+        }
+        Ok(tournament)
+    }
+
+    fn apply(&mut self, event: Event) -> Result<(), OpoError> {
+        match event {
+            Event::PlayerAdded { player } => self.add_player(player),
+            Event::PlayerDropped { player_id } => self.drop_player(player_id)?,
+            Event::PairingSystemChanged { pairing_system } => self.set_pairing_system(pairing_system),
+            Event::TiebreaksChanged { tiebreaks } => {
+                let tiebreaks = tiebreaks.iter()
+                    .map(|name| builtin(name).ok_or_else(|| OpoError::InvalidFormat(format!("unknown tiebreaker: {}", name))))
+                    .collect::<Result<_, _>>()?;
+                self.set_tiebreaks(tiebreaks);
+            }
+            Event::ScoringChanged { scoring } => self.set_scoring_system(scoring)?,
+            Event::TableSettingsChanged { settings } => self.set_table_settings(settings),
+            Event::ByeAssigned { round_number, player_id, points } => self.assign_bye(round_number, player_id, points)?,
+            Event::ByeUnassigned { round_number, player_id } => {
+                self.unassign_bye(round_number, player_id)?;
+            }
+            Event::ResultsImported { matches } => self.aggregate_matches(matches)?,
+            Event::PairingPublished { round_number, pairings, colors } => {
+                self.check_pairable()?;
+                if round_number != self.round_number() + 1 {
+                    return Err(OpoError::WrongRound { expected: self.round_number() + 1, found: round_number });
+                }
+                self.publish_pairings(round_number, pairings, colors);
+            }
+            Event::PairingWithdrawn { .. } => self.unpair_round()?,
+            Event::RoundStarted { .. } => self.start_round()?,
+            Event::FeatureMatchSet { table_number, player_id, .. } => self.set_feature_match(table_number, player_id)?,
+            Event::ResultEntered { matching } => self.record_result(matching)?,
+            Event::RoundClosed { .. } => self.close_round()?,
+            Event::ResultCorrected { matching } => {
+                self.amend_result(matching)?;
+            }
+            Event::ResultDeleted { round_number, player_id } => {
+                self.delete_result(round_number, player_id)?;
+            }
+            Event::Finished => self.finish()?,
+        }
+        Ok(())
+    }
 
     pub fn add_player(&mut self, player: Player) {
+        self.journal.push(Event::PlayerAdded { player: player.clone() });
         self.players.push(player);
         self.player_number += 1;
     }
 
+    /// drop the player from the tournament ( the player is not paired any more )
+    ///
+    /// a published pairing of the player stays, and its result is entered as a withdrawal.
+    pub fn drop_player(&mut self, player_id: usize) -> Result<(), OpoError> {
+        let player = self.players.get_mut(player_id).ok_or(OpoError::UnknownPlayer(player_id))?;
+        if !player.is_dropped() {
+            player.set_dropped(true);
+            self.journal.push(Event::PlayerDropped { player_id });
+        }
+        Ok(())
+    }
+
     fn calculate_points(&mut self) {
         for player in &mut self.players {
-            player.calculate_points();
+            player.calculate_points(&self.scoring);
         }
     }
 
     fn calculate_match_win_percentages(&mut self) {
         for player in &mut self.players {
-            player.calculate_match_win_percentages();
+            player.calculate_match_win_percentages(&self.scoring);
         }
     }
 
@@ -45,7 +263,7 @@ impl Tournament {
 
     fn calculate_game_win_percentages(&mut self) {
         for player in &mut self.players {
-            player.calculate_game_win_percentages();
+            player.calculate_game_win_percentages(&self.scoring);
         }
     }
 
@@ -67,42 +285,625 @@ impl Tournament {
         self.calculate_opponent_game_win_percentages();
     }
 
-    pub fn aggregate_matches(&mut self, matches: Vec<Matching>) {
+    /// add the results of a round to the players, and recalculate the points
+    ///
+    /// a matching with opponent is added to the opponent too ( reversed ), so each pair is given once.
+    /// nothing is added if a matching has an unknown player, or a player has more than one result.
+    /// the results are not checked against the pairings ( e.g. to import past rounds ), see `submit_results`.
+    pub fn aggregate_matches(&mut self, matches: Vec<Matching>) -> Result<(), OpoError> {
+        self.add_results(matches.clone())?;
+        self.journal.push(Event::ResultsImported { matches });
+        Ok(())
+    }
+
+    fn add_results(&mut self, matches: Vec<Matching>) -> Result<(), OpoError> {
         // そのラウンドでマッチ結果の集計、マッチしてない人の特定
-        let mut matched_id = vec![None; self.player_number as usize];
-        for matching in matches {
-            let player_id = matching.player_id();
-            let opponent_id = matching.opponent_id();
+        let mut matched = vec![false; self.players.len()];
+        for matching in &matches {
+            let mut ids = vec![matching.player_id()];
             if matching.is_reversible() {
-                self.players[opponent_id].add_matching(Matching::rev(&matching));
-                matched_id[opponent_id] = Some(player_id);
+                ids.push(matching.opponent_id());
+            }
+            for id in ids {
+                let seen = matched.get_mut(id).ok_or(OpoError::UnknownPlayer(id))?;
+                if *seen {
+                    return Err(OpoError::DuplicateResult { round_number: matching.round_number(), player_id: id });
+                }
+                *seen = true;
             }
-            if matched_id[player_id].is_some() {
-                panic!("Duplicated Matching!: \n{:?} and \n{:?}", matching, self.players[player_id].matching_list().last().unwrap());
+        }
+        for matching in matches {
+            if matching.is_reversible() {
+                self.players[matching.opponent_id()].add_matching(Matching::rev(&matching));
             }
-            self.players[player_id].add_matching(matching);
-            matched_id[player_id] = Some(opponent_id);
+            self.players[matching.player_id()].add_matching(matching);
         }
 
         // マッチ結果に基づき計算を行う
         self.aggregate_points();
+        Ok(())
+    }
+
+    pub fn state(&self) -> RoundState {
+        self.state
+    }
+
+    /// the published pairings of the current round ( empty before the first round )
+    pub fn pairings(&self) -> &[Option<usize>] {
+        self.pairings.values().next_back().map_or(&[], |pairings| &pairings[..])
+    }
+
+    /// the round of the published pairings ( 0 before the first round )
+    pub fn pairing_round(&self) -> i32 {
+        self.pairings.keys().next_back().copied().unwrap_or(0)
+    }
+
+    /// the published pairings of the round ( empty if the round was not paired by `pair_round` )
+    pub fn round_pairings(&self, round_number: i32) -> &[Option<usize>] {
+        self.pairings.get(&round_number).map_or(&[], |pairings| &pairings[..])
+    }
+
+    /// the colours of the published pairings
+    pub fn pairing_colors(&self) -> &[Option<Color>] {
+        &self.pairing_colors
+    }
+
+    /// the tables of the published pairings by number, numbered when the pairings were published ( see `tables::number_tables` )
+    ///
+    /// the white player is first when the colours are known.
+    pub fn tables(&self) -> Vec<Table> {
+        match self.tables.get(&self.pairing_round()) {
+            Some(tables) => tables.clone(),
+            // pairings saved before the tables were numbered
+            None => number_tables(&self.games(), &self.table_settings, &[]),
+        }
+    }
+
+    /// the players without opponent in the published pairings ( who have not dropped ), in the order of the standings
+    pub fn byes(&self) -> Vec<usize> {
+        let pairings = self.pairings();
+        self.standings().into_iter()
+            .filter(|player| pairings.get(player.id()) == Some(&None) && !player.is_dropped())
+            .map(|player| player.id())
+            .collect()
+    }
+
+    /// the games of the published pairings as `(player, opponent, score group)`, in the order of the standings of their higher-placed player
+    fn games(&self) -> Vec<(usize, usize, i64)> {
+        let pairings = self.pairings();
+        let mut seated = vec![false; pairings.len()];
+        let mut games = Vec::new();
+        for player in self.standings() {
+            let id = player.id();
+            if let Some(Some(opponent_id)) = pairings.get(id) {
+                if !seated[id] {
+                    seated[id] = true;
+                    seated[*opponent_id] = true;
+                    let group = scaled_points(player.points());
+                    match self.pairing_colors.get(id).copied().flatten() {
+                        Some(Color::Black) => games.push((*opponent_id, id, group)),
+                        _ => games.push((id, *opponent_id, group)),
+                    }
+                }
+            }
+        }
+        games
+    }
+
+    /// pair the next round and publish the pairings and their colours
+    ///
+    /// only in registration or after a complete round.
+    pub fn pair_round(&mut self) -> Result<&[Option<usize>], OpoError> {
+        self.check_pairable()?;
+        let pairings = self.matching_build()?;
+        let colors = self.allocate_colors(&pairings);
+        let round_number = self.round_number() + 1;
+        self.publish_pairings(round_number, pairings, colors);
+        Ok(&self.pairings[&round_number])
+    }
 
+    fn check_pairable(&self) -> Result<(), OpoError> {
+        match self.state {
+            RoundState::Registration | RoundState::Complete(_) => Ok(()),
+            state => Err(OpoError::InvalidState { state, action: "pair a round" }),
+        }
+    }
+
+    fn publish_pairings(&mut self, round_number: i32, pairings: Vec<Option<usize>>, colors: Vec<Option<Color>>) {
+        self.journal.push(Event::PairingPublished { round_number, pairings: pairings.clone(), colors: colors.clone() });
+        self.pairing_colors = colors;
+        self.pairings.insert(round_number, pairings);
+        let previous = self.tables.get(&(round_number - 1)).map_or(&[][..], |tables| &tables[..]);
+        let tables = number_tables(&self.games(), &self.table_settings, previous);
+        self.tables.insert(round_number, tables);
+        self.state = RoundState::Paired(round_number);
+    }
+
+    /// move the game of the player to a feature table of the current round ( see `TableSettings::feature` )
+    ///
+    /// a game already at that table takes the former number of the moved game.
+    pub fn set_feature_match(&mut self, table_number: usize, player_id: usize) -> Result<(), OpoError> {
+        let round_number = match self.state {
+            RoundState::Paired(round_number) | RoundState::ResultsPending(round_number) => round_number,
+            state => return Err(OpoError::InvalidState { state, action: "set a feature match" }),
+        };
+        if !self.table_settings.feature.contains(&table_number) {
+            return Err(OpoError::NotFeatureTable(table_number));
+        }
+        let mut tables = self.tables();
+        let index = tables.iter()
+            .position(|table| table.player_id == player_id || table.opponent_id == player_id)
+            .ok_or(OpoError::NotPaired { player_id, opponent_id: None })?;
+        let former_number = tables[index].number;
+        for table in tables.iter_mut().filter(|table| table.number == table_number) {
+            table.number = former_number;
+        }
+        tables[index].number = table_number;
+        tables.sort_by_key(|table| table.number);
+        self.tables.insert(round_number, tables);
+        self.journal.push(Event::FeatureMatchSet { round_number, table_number, player_id });
+        Ok(())
+    }
+
+    /// withdraw the published pairings of a round which has not started ( e.g. for a late entry )
+    pub fn unpair_round(&mut self) -> Result<(), OpoError> {
+        let round_number = match self.state {
+            RoundState::Paired(round_number) => round_number,
+            state => return Err(OpoError::InvalidState { state, action: "unpair the round" }),
+        };
+        self.pairings.remove(&round_number);
+        self.tables.remove(&round_number);
+        self.pairing_colors.clear();
+        self.state = match self.round_number() {
+            0 => RoundState::Registration,
+            round_number => RoundState::Complete(round_number),
+        };
+        self.journal.push(Event::PairingWithdrawn { round_number });
+        Ok(())
+    }
+
+    /// start the paired round, and wait for its results
+    pub fn start_round(&mut self) -> Result<(), OpoError> {
+        match self.state {
+            RoundState::Paired(round_number) => {
+                self.state = RoundState::ResultsPending(round_number);
+                self.journal.push(Event::RoundStarted { round_number });
+                Ok(())
+            }
+            state => Err(OpoError::InvalidState { state, action: "start a round" }),
+        }
+    }
+
+    /// pair the next round and start it, enter the byes, and return the round number
+    ///
+    /// a round of byes only is closed at once.
+    pub fn pair_next_round(&mut self) -> Result<i32, OpoError> {
+        let pairings = self.pair_round()?.to_vec();
+        self.start_round()?;
+        let round_number = self.round_number() + 1;
+        for (id, opponent) in pairings.iter().enumerate() {
+            if opponent.is_none() && !self.players[id].is_dropped() {
+                self.record_result(Matching::no_opponent_new(round_number, id))?;
+            }
+        }
+        if self.outstanding_tables().is_empty() {
+            self.close_round()?;
+        }
+        Ok(round_number)
+    }
+
+    /// the round whose results are pending
+    fn pending_round(&self, action: &'static str) -> Result<i32, OpoError> {
+        match self.state {
+            RoundState::ResultsPending(round_number) => Ok(round_number),
+            state => Err(OpoError::InvalidState { state, action }),
+        }
+    }
+
+    /// check a result of the pending round against the published pairings, and give it the published colour
+    /// ( or the points of an assigned bye )
+    ///
+    /// a paired player has a matching against the paired opponent ( withdrawals included ),
+    /// and a player without opponent has a no-opponent or a dropped matching.
+    /// a table which already has a result is refused.
+    fn check_result(&self, round_number: i32, matching: &mut Matching) -> Result<(), OpoError> {
+        if matching.round_number() != round_number {
+            return Err(OpoError::WrongRound { expected: round_number, found: matching.round_number() });
+        }
+        let player_id = matching.player_id();
+        let opponent_id = if matching.is_reversible() { Some(matching.opponent_id()) } else { None };
+        if self.round_pairings(round_number).get(player_id) != Some(&opponent_id) {
+            return Err(OpoError::NotPaired { player_id, opponent_id });
+        }
+        let recorded = self.players[player_id].matching_list().iter().any(|m| m.round_number() == round_number);
+        if recorded {
+            return Err(OpoError::DuplicateResult { round_number, player_id });
+        }
+        if opponent_id.is_some() && matching.color().is_none() {
+            matching.set_color(self.pairing_colors[player_id]);
+        }
+        if matching.is_no_opponent() && matching.bye_points().is_none() {
+            matching.set_bye_points(self.assigned_byes(round_number).get(&player_id).copied().flatten());
+        }
+        Ok(())
+    }
+
+    /// give the player a bye in a round which is not paired yet ( e.g. for an invitation or a penalty ),
+    /// with its points ( `None` for the bye points of the scoring )
+    ///
+    /// the player is left out of the pairing of the round. like any bye, it keeps the player from the bye of an odd field later.
+    pub fn assign_bye(&mut self, round_number: i32, player_id: usize, points: Option<f64>) -> Result<(), OpoError> {
+        self.check_unpaired_round(round_number, player_id)?;
+        self.assigned_byes.entry(round_number).or_default().insert(player_id, points);
+        self.journal.push(Event::ByeAssigned { round_number, player_id, points });
+        Ok(())
+    }
+
+    /// take back an assigned bye of a round which is not paired yet, and tell whether the player had one
+    ///
+    /// an initial bye of the player is not taken back ( see `Player::set_initial_byes` ).
+    pub fn unassign_bye(&mut self, round_number: i32, player_id: usize) -> Result<bool, OpoError> {
+        self.check_unpaired_round(round_number, player_id)?;
+        let assigned = self.assigned_byes.get_mut(&round_number).is_some_and(|byes| byes.remove(&player_id).is_some());
+        if assigned {
+            self.journal.push(Event::ByeUnassigned { round_number, player_id });
+        }
+        Ok(assigned)
+    }
+
+    fn check_unpaired_round(&self, round_number: i32, player_id: usize) -> Result<(), OpoError> {
+        if player_id >= self.players.len() {
+            return Err(OpoError::UnknownPlayer(player_id));
+        }
+        let next_round = self.pairing_round().max(self.round_number()) + 1;
+        if round_number < next_round {
+            return Err(OpoError::WrongRound { expected: next_round, found: round_number });
+        }
+        Ok(())
+    }
+
+    /// the players given a bye in the round before the pairing, with the points of their byes
+    ///
+    /// these are the byes of `assign_bye` and the initial byes of the players who have not dropped ( see `Player::initial_byes` ),
+    /// which have the bye points of the scoring.
+    pub fn assigned_byes(&self, round_number: i32) -> BTreeMap<usize, Option<f64>> {
+        let mut byes: BTreeMap<usize, Option<f64>> = self.players.iter()
+            .filter(|player| !player.is_dropped() && player.has_initial_bye(round_number))
+            .map(|player| (player.id(), None))
+            .collect();
+        byes.extend(self.assigned_byes.get(&round_number).into_iter().flatten());
+        byes
+    }
+
+    /// enter the result of one table of the started round
+    ///
+    /// the result counts in the standings at once, so the standings are provisional until the round is closed.
+    pub fn record_result(&mut self, mut matching: Matching) -> Result<(), OpoError> {
+        let round_number = self.pending_round("record a result")?;
+        self.check_result(round_number, &mut matching)?;
+        self.add_results(vec![matching.clone()])?;
+        self.journal.push(Event::ResultEntered { matching });
+        Ok(())
+    }
+
+    /// tables of the started round without a result, as `(player, opponent)` with the smaller id first
+    ///
+    /// a player without opponent has a table of its own ( unless the player has dropped ).
+    /// empty when no round has started.
+    pub fn outstanding_tables(&self) -> Vec<(usize, Option<usize>)> {
+        let round_number = match self.state {
+            RoundState::ResultsPending(round_number) => round_number,
+            _ => return Vec::new(),
+        };
+        let has_result = |id: usize| {
+            self.players[id].matching_list().iter().any(|m| m.round_number() == round_number)
+        };
+        self.round_pairings(round_number).iter().enumerate()
+            .filter(|&(id, _)| !has_result(id))
+            .filter_map(|(id, opponent)| match *opponent {
+                Some(opponent_id) if id < opponent_id => Some((id, Some(opponent_id))),
+                None if !self.players[id].is_dropped() => Some((id, None)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// complete the started round, once every table has a result
+    pub fn close_round(&mut self) -> Result<(), OpoError> {
+        let round_number = self.pending_round("close the round")?;
+        if let Some(&(id, _)) = self.outstanding_tables().first() {
+            return Err(OpoError::MissingResult(id));
+        }
+        self.state = RoundState::Complete(round_number);
+        self.journal.push(Event::RoundClosed { round_number });
+        Ok(())
+    }
+
+    /// enter every outstanding result of the started round at once, and close it
+    ///
+    /// nothing is entered if a result does not match the pairings or a table is left without a result.
+    pub fn submit_results(&mut self, mut matches: Vec<Matching>) -> Result<(), OpoError> {
+        let round_number = self.pending_round("submit results")?;
+        for matching in &mut matches {
+            self.check_result(round_number, matching)?;
+        }
+        let covered = |id: usize| matches.iter().any(|m| m.player_id() == id || (m.is_reversible() && m.opponent_id() == id));
+        if let Some(&(id, _)) = self.outstanding_tables().iter().find(|&&(id, _)| !covered(id)) {
+            return Err(OpoError::MissingResult(id));
+        }
+        self.add_results(matches.clone())?;
+        self.journal.extend(matches.into_iter().map(|matching| Event::ResultEntered { matching }));
+        self.close_round()
+    }
+
+    fn recorded_result(&self, round_number: i32, player_id: usize) -> Result<Matching, OpoError> {
+        let player = self.players.get(player_id).ok_or(OpoError::UnknownPlayer(player_id))?;
+        player.matching_list().iter()
+            .find(|m| m.round_number() == round_number)
+            .cloned()
+            .ok_or(OpoError::NoSuchResult { round_number, player_id })
+    }
+
+    /// correct a recorded result of any round, and recalculate the points and the tiebreakers
+    ///
+    /// the matching replaces the result of its player in its round, and the reversed one replaces the opponent's.
+    /// the opponent must be the same as in the recorded result, and a matching without colour keeps the recorded one.
+    /// return the later rounds whose published pairings would have been different ( see `changed_pairings` ).
+    pub fn amend_result(&mut self, mut matching: Matching) -> Result<Vec<i32>, OpoError> {
+        let (round_number, player_id) = (matching.round_number(), matching.player_id());
+        let recorded = self.recorded_result(round_number, player_id)?;
+        let opponent_of = |m: &Matching| if m.is_reversible() { Some(m.opponent_id()) } else { None };
+        let opponent_id = opponent_of(&matching);
+        if opponent_id != opponent_of(&recorded) {
+            return Err(OpoError::NotPaired { player_id, opponent_id });
+        }
+        if matching.color().is_none() {
+            matching.set_color(recorded.color());
+        }
+        if let Some(opponent_id) = opponent_id {
+            self.players[opponent_id].replace_matching(Matching::rev(&matching));
+        }
+        self.players[player_id].replace_matching(matching.clone());
+        self.journal.push(Event::ResultCorrected { matching });
+        self.aggregate_points();
+        Ok(self.changed_pairings(round_number))
+    }
+
+    /// delete a recorded result of the last round ( of the opponent too ), and recalculate the points and the tiebreakers
+    ///
+    /// only the round whose results are pending or which is the last complete one can lose a result,
+    /// so that the table can be entered again: deleting a result of the last complete round reopens it, and the table is outstanding again.
+    /// return the later rounds whose published pairings would have been different ( see `changed_pairings` ).
+    pub fn delete_result(&mut self, round_number: i32, player_id: usize) -> Result<Vec<i32>, OpoError> {
+        match self.state {
+            RoundState::ResultsPending(current) | RoundState::Complete(current) if current != round_number => {
+                return Err(OpoError::WrongRound { expected: current, found: round_number });
+            }
+            RoundState::ResultsPending(_) | RoundState::Complete(_) => {}
+            state => return Err(OpoError::InvalidState { state, action: "delete a result" }),
+        }
+        let recorded = self.recorded_result(round_number, player_id)?;
+        if recorded.is_reversible() {
+            self.players[recorded.opponent_id()].remove_matching(round_number);
+        }
+        self.players[player_id].remove_matching(round_number);
+        self.journal.push(Event::ResultDeleted { round_number, player_id });
+        if self.state == RoundState::Complete(round_number) && self.pairings.contains_key(&round_number) {
+            self.state = RoundState::ResultsPending(round_number);
+        }
+        self.aggregate_points();
+        Ok(self.changed_pairings(round_number))
+    }
+
+    /// rounds after `round_number` whose published pairings differ from the pairings the recorded results give now
+    ///
+    /// each round is paired again with the results of the rounds before it and the current pairing system.
+    /// a round which can not be paired any more has changed too.
+    fn changed_pairings(&mut self, round_number: i32) -> Vec<i32> {
+        let published: Vec<(i32, Vec<Option<usize>>)> = self.pairings.range(round_number + 1..)
+            .map(|(&later, pairings)| (later, pairings.clone()))
+            .collect();
+        published.into_iter()
+            .filter(|(later, pairings)| match self.pair_again(*later, pairings) {
+                Ok(again) => again != *pairings,
+                Err(_) => true,
+            })
+            .map(|(later, _)| later)
+            .collect()
+    }
+
+    /// pair the round again with the results of the rounds before it, in the field of its published `pairings`
+    ///
+    /// only the players who had entered when the round was paired take part, and a player who dropped later
+    /// is back in the field if the round paired them or gave them the bye of an odd field ( a bye which was not assigned ).
+    fn pair_again(&mut self, round_number: i32, pairings: &[Option<usize>]) -> Result<Vec<Option<usize>>, OpoError> {
+        let assigned = self.assigned_byes(round_number);
+        let past = self.players.iter()
+            .take(pairings.len())
+            .map(|player| {
+                let odd_bye = !assigned.contains_key(&player.id()) && player.matching_list().iter()
+                    .any(|m| m.round_number() == round_number && m.is_no_opponent());
+                let mut player = player.clone();
+                if pairings[player.id()].is_some() || odd_bye {
+                    player.set_dropped(false);
+                }
+                player.truncate_rounds(round_number);
+                player
+            })
+            .collect();
+        // the points of the past players are calculated in place, and the current players are put back as they were
+        let current = std::mem::replace(&mut self.players, past);
+        self.aggregate_points();
+        let pairings = self.matching_build();
+        self.players = current;
+        pairings
+    }
+
+    /// finish the tournament after a complete round
+    pub fn finish(&mut self) -> Result<(), OpoError> {
+        match self.state {
+            RoundState::Complete(_) => {
+                self.state = RoundState::Finished;
+                self.journal.push(Event::Finished);
+                Ok(())
+            }
+            state => Err(OpoError::InvalidState { state, action: "finish the tournament" }),
+        }
     }
 
     /// return `Vec<Option<usize>>` whose `Some(n)` n-th element number has n-th id player's opponent i
     /// if element is `None`, no-opponent or player is dropped (No matching)
-    pub fn matching_build(&self) -> Result<Vec<Option<usize>>, String> {
-        crate::tournament_model::matching_algorithm::matching_build(&self.players)
+    ///
+    /// players are paired by the tournament's `PairingSystem`
+    pub fn matching_build(&self) -> Result<Vec<Option<usize>>, OpoError> {
+        let players = self.pairing_field();
+        match self.pairing_system {
+            PairingSystem::Points => crate::tournament_model::matching_algorithm::matching_build(&players, &self.tiebreaks, &self.scoring),
+            PairingSystem::Dutch => crate::tournament_model::dutch_system::matching_build_dutch(&players, &self.scoring),
+        }
+    }
+
+    /// the players as the pairing of the next round sees them: a player with an assigned bye sits out like a dropped player
+    fn pairing_field(&self) -> Cow<'_, [Player]> {
+        let byes = self.assigned_byes(self.round_number() + 1);
+        if byes.is_empty() {
+            return Cow::Borrowed(&self.players);
+        }
+        let mut players = self.players.clone();
+        for &id in byes.keys() {
+            players[id].set_dropped(true);
+        }
+        Cow::Owned(players)
+    }
+
+    /// allocate white and black to the pairs of `matching_list` ( see `color::allocate_colors` )
+    pub fn allocate_colors(&self, matching_list: &[Option<usize>]) -> Vec<Option<Color>> {
+        crate::tournament_model::color::allocate_colors(&self.players, matching_list)
     }
 
-    pub fn greedy_matching_build(&self) -> Result<Vec<Option<usize>>, String> {
-        crate::tournament_model::matching_algorithm::matching_build_greed(&self.players)
+    pub fn greedy_matching_build(&self) -> Result<Vec<Option<usize>>, OpoError> {
+        crate::tournament_model::matching_algorithm::matching_build_greed(&self.pairing_field(), &self.tiebreaks, &self.scoring)
     }
 
     pub fn player_number(&self) -> usize {
         self.players.len()
     }
 
+    pub fn players(&self) -> &[Player] {
+        &self.players
+    }
+
+    /// the latest round which has results ( 0 before the first round )
+    pub fn round_number(&self) -> i32 {
+        self.players.iter()
+            .flat_map(|player| player.matching_list())
+            .map(|matching| matching.round_number())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// the values of the tiebreakers of the player, in order ( see `ranked_standings` )
+    pub fn tiebreak_values(&self, player: &Player) -> Vec<f64> {
+        self.ranked_standings().into_iter()
+            .find(|(p, _)| p.id() == player.id())
+            .map_or_else(Vec::new, |(_, values)| values)
+    }
+
+    /// the standings with the values of the tiebreakers of each player, in order
+    ///
+    /// a tiebreaker which depends on the tied players ( e.g. head-to-head ) is given among the players
+    /// who were still level when it was applied, as in the order of the standings ( see `tiebreak::ranked` ).
+    pub fn ranked_standings(&self) -> Vec<Ranked<'_>> {
+        ranked(&self.players, &self.tiebreaks, &self.scoring)
+    }
+
+    /// players from the first place to the last, by points and then by the tiebreakers
+    ///
+    /// while results are pending, only the recorded ones count.
+    pub fn standings(&self) -> Vec<&Player> {
+        standings(&self.players, &self.tiebreaks, &self.scoring)
+    }
+
+    /// single-elimination playoff of the top `size` players of the standings ( see `SingleElimination` )
+    pub fn top_cut(&self, size: usize) -> Result<SingleElimination, OpoError> {
+        SingleElimination::new(&self.standings(), size)
+    }
+
+    /// every round of a round robin of the players who are not dropped ( see `round_robin::round_robin` )
+    ///
+    /// the pairing numbers are given by rating.
+    pub fn round_robin(&self, double: bool) -> Vec<Vec<Option<usize>>> {
+        round_robin(&seed_by_rating(&self.players), self.players.len(), double)
+    }
+
+    /// split the players who are not dropped into `group_number` balanced groups
+    pub fn groups(&self, group_number: usize, seeding: GroupSeeding) -> Result<Vec<Vec<usize>>, OpoError> {
+        let seeded = match seeding {
+            GroupSeeding::Rating => seed_by_rating(&self.players),
+            GroupSeeding::Standings => self.standings().iter()
+                .filter(|p| !p.is_dropped())
+                .map(|p| p.id())
+                .collect(),
+        };
+        split_groups(&seeded, group_number)
+    }
+
+    /// every round of a round robin in each of `group_number` groups ( see `round_robin::group_stage` )
+    pub fn group_stage(&self, group_number: usize, seeding: GroupSeeding, double: bool) -> Result<Vec<Vec<Option<usize>>>, OpoError> {
+        let groups = self.groups(group_number, seeding)?;
+        Ok(group_stage(&groups, self.players.len(), double))
+    }
+
+    /// double-elimination bracket of the top `size` players of the standings ( see `DoubleElimination` )
+    pub fn double_elimination(&self, size: usize, bracket_reset: bool) -> Result<DoubleElimination, OpoError> {
+        DoubleElimination::new(&self.standings(), size, bracket_reset)
+    }
+
+    pub fn tiebreaks(&self) -> &[Box<dyn Tiebreak>] {
+        &self.tiebreaks
+    }
+
+    /// set the tiebreakers in order ( the default is OMW%, GW% and OGW% )
+    pub fn set_tiebreaks(&mut self, tiebreaks: Vec<Box<dyn Tiebreak>>) {
+        let names = tiebreaks.iter().map(|tiebreak| tiebreak.name().to_string()).collect();
+        self.journal.push(Event::TiebreaksChanged { tiebreaks: names });
+        self.tiebreaks = tiebreaks;
+    }
+
+    pub fn pairing_system(&self) -> PairingSystem {
+        self.pairing_system
+    }
+
+    pub fn set_pairing_system(&mut self, pairing_system: PairingSystem) {
+        self.journal.push(Event::PairingSystemChanged { pairing_system });
+        self.pairing_system = pairing_system;
+    }
+
+    pub fn scoring_system(&self) -> &ScoringSystem {
+        &self.scoring
+    }
+
+    pub fn table_settings(&self) -> &TableSettings {
+        &self.table_settings
+    }
+
+    /// set the fixed and the feature tables, for the numbering of the next pairings
+    pub fn set_table_settings(&mut self, settings: TableSettings) {
+        self.journal.push(Event::TableSettingsChanged { settings: settings.clone() });
+        self.table_settings = settings;
+    }
+
+    /// set the points for each kind of result ( the default is 3/1/0 ), and recalculate the points
+    ///
+    /// the points are checked by `ScoringSystem::validate`.
+    pub fn set_scoring_system(&mut self, scoring: ScoringSystem) -> Result<(), OpoError> {
+        scoring.validate()?;
+        self.journal.push(Event::ScoringChanged { scoring });
+        self.scoring = scoring;
+        self.aggregate_points();
+        Ok(())
+    }
+
 }
 
 #[test]
@@ -127,20 +928,20 @@ fn test_aggregate_matches() {
         t.add_player(p);
     }
     let m = vec![
-        Matching::new(1, 0, 1, 3, 0, 1, false, false),
-        Matching::new(1, 2, 3, 0, 0, 2, false, false),
+        Matching::new(1, 0, 1, 3, 0, 1, false, false).unwrap(),
+        Matching::new(1, 2, 3, 0, 0, 2, false, false).unwrap(),
     ];
-    t.aggregate_matches(m);
+    t.aggregate_matches(m).unwrap();
     let m = vec![
-        Matching::new(2, 0, 3, 3, 0, 1, false, false),
+        Matching::new(2, 0, 3, 3, 0, 1, false, false).unwrap(),
         Matching::dropped_new(2,1),
         Matching::no_opponent_new(2,2),
     ];
-    t.aggregate_matches(m);
-    assert_eq!(t.players[0].points(), 6);
-    assert_eq!(t.players[1].points(), 0);
-    assert_eq!(t.players[2].points(), 3);
-    assert_eq!(t.players[3].points(), 3);
+    t.aggregate_matches(m).unwrap();
+    assert_eq!(t.players[0].points(), 6.0);
+    assert_eq!(t.players[1].points(), 0.0);
+    assert_eq!(t.players[2].points(), 3.0);
+    assert_eq!(t.players[3].points(), 3.0);
     assert_eq!(t.players[0].match_win_percentage(), 1.0);
     assert_eq!(t.players[1].match_win_percentage(), 0.0);
     assert_eq!(t.players[2].match_win_percentage(), 0.5);
@@ -192,7 +993,7 @@ fn test_matching_build() {
                     if !mt[n] {
                         mt[i] = true;
                         mt[n] = true;
-                        ml.push(Matching::new(1,i,n,1,0,0,false,false));
+                        ml.push(Matching::new(1,i,n,1,0,0,false,false).unwrap());
                     }
                 },
                 None => {
@@ -201,7 +1002,7 @@ fn test_matching_build() {
             }
         }
 
-        t.aggregate_matches(ml);
+        t.aggregate_matches(ml).unwrap();
     }
 }
 
@@ -238,7 +1039,7 @@ fn test_greedy_matching_build() {
                     if !mt[n] {
                         mt[i] = true;
                         mt[n] = true;
-                        ml.push(Matching::new(1,i,n,1,0,0,false,false));
+                        ml.push(Matching::new(1,i,n,1,0,0,false,false).unwrap());
                     }
                 },
                 None => {
@@ -247,6 +1048,691 @@ fn test_greedy_matching_build() {
             }
         }
 
-        t.aggregate_matches(ml);
+        t.aggregate_matches(ml).unwrap();
+    }
+}
+
+#[test]
+fn test_matching_build_large_field() {
+    let mut t: Tournament = Default::default();
+    for i in 0..5001 {
+        let p: Player = Player::new(i, format!("{}abcd", i));
+        t.add_player(p);
+    }
+
+    for round in 1..9 {
+        let ol = t.matching_build().unwrap();
+        let mut ml = Vec::new();
+        let mut byes = 0;
+        for (i, o) in ol.into_iter().enumerate() {
+            match o {
+                Some(n) => {
+                    assert!(!t.players[i].had_matched_id(Some(n)));
+                    if i < n {
+                        ml.push(Matching::new(round, i, n, 2, 0, (i + n) as i32 % 2, false, false).unwrap());
+                    }
+                },
+                None => {
+                    assert!(!t.players[i].had_matched_id(None));
+                    byes += 1;
+                    ml.push(Matching::no_opponent_new(round, i));
+                }
+            }
+        }
+        assert_eq!(byes, 1);
+        t.aggregate_matches(ml).unwrap();
+    }
+}
+
+#[test]
+fn test_matching_build_minimum_cost() {
+    let mut t: Tournament = Default::default();
+    for i in 0..5 {
+        let p: Player = Player::new(i, format!("{}abcd", i));
+        t.add_player(p);
+    }
+    t.aggregate_matches(vec![
+        Matching::new(1, 0, 1, 2, 0, 0, false, false).unwrap(),
+        Matching::new(1, 2, 3, 2, 0, 0, false, false).unwrap(),
+        Matching::no_opponent_new(1, 4),
+    ]).unwrap();
+    // 0, 2 and 4 have 3 points, 1 and 3 have no point
+    // 4 had no-opponent, so 3 ( the lowest ) gets no opponent and others are paired by points
+    let ol = t.matching_build().unwrap();
+    assert_eq!(ol[3], None);
+    assert_eq!(ol[1].map(|o| t.players[o].points()), Some(3.0));
+    assert_eq!(ol.iter().filter(|o| o.is_some()).count(), 4);
+    for (i, o) in ol.iter().enumerate() {
+        if let Some(n) = o {
+            assert_eq!(ol[*n], Some(i));
+            assert!(!t.players[i].had_matched_id(Some(*n)));
+        }
+    }
+
+    // every opponent of 0 was already matched
+    let mut t: Tournament = Default::default();
+    for i in 0..4 {
+        let p: Player = Player::new(i, format!("{}abcd", i));
+        t.add_player(p);
+    }
+    t.aggregate_matches(vec![
+        Matching::new(1, 0, 1, 2, 0, 0, false, false).unwrap(),
+        Matching::new(1, 2, 3, 2, 0, 0, false, false).unwrap(),
+    ]).unwrap();
+    t.aggregate_matches(vec![
+        Matching::new(2, 0, 2, 2, 0, 0, false, false).unwrap(),
+        Matching::new(2, 1, 3, 2, 0, 0, false, false).unwrap(),
+    ]).unwrap();
+    assert_eq!(t.matching_build().unwrap(), vec![Some(3), Some(2), Some(1), Some(0)]);
+    t.aggregate_matches(vec![
+        Matching::new(3, 0, 3, 2, 0, 0, false, false).unwrap(),
+        Matching::new(3, 1, 2, 2, 0, 0, false, false).unwrap(),
+    ]).unwrap();
+    assert!(matches!(t.matching_build(), Err(OpoError::PairingInfeasible)));
+}
+
+#[test]
+fn test_dutch_matching_build() {
+    let mut t: Tournament = Default::default();
+    t.set_pairing_system(PairingSystem::Dutch);
+    for (i, rating) in [1500, 2000, 1800, 1200].iter().enumerate() {
+        let mut p: Player = Player::new(i, format!("{}abcd", i));
+        p.set_rating(*rating);
+        t.add_player(p);
+    }
+    // pairing numbers are 1, 2, 0, 3
+    assert_eq!(t.matching_build().unwrap(), vec![Some(1), Some(0), Some(3), Some(2)]);
+    t.aggregate_matches(vec![
+        Matching::new(1, 1, 0, 2, 0, 0, false, false).unwrap(),
+        Matching::new(1, 2, 3, 2, 0, 0, false, false).unwrap(),
+    ]).unwrap();
+    assert_eq!(t.matching_build().unwrap(), vec![Some(3), Some(2), Some(1), Some(0)]);
+}
+
+#[test]
+fn test_allocate_colors() {
+    let mut t: Tournament = Default::default();
+    for i in 0..4 {
+        let p: Player = Player::new(i, format!("{}abcd", i));
+        t.add_player(p);
+    }
+    for round in 1..4 {
+        let ol = t.matching_build().unwrap();
+        let colors = t.allocate_colors(&ol);
+        let mut ml = Vec::new();
+        for (i, o) in ol.into_iter().enumerate() {
+            let n = o.unwrap();
+            assert_eq!(colors[i].map(Color::opposite), colors[n]);
+            if i < n {
+                let mut m = Matching::new(round, i, n, 1, 0, 0, false, false).unwrap();
+                m.set_color(colors[i]);
+                ml.push(m);
+            }
+        }
+        t.aggregate_matches(ml).unwrap();
+    }
+    for p in &t.players {
+        assert_eq!(p.color_history().len(), 3);
+        assert!(p.color_difference().abs() <= 1);
+    }
+}
+
+#[test]
+fn test_standings() {
+    let mut t: Tournament = Default::default();
+    for i in 0..4 {
+        let p: Player = Player::new(i, format!("{}abcd", i));
+        t.add_player(p);
+    }
+    t.aggregate_matches(vec![
+        Matching::new(1, 0, 1, 3, 0, 1, false, false).unwrap(),
+        Matching::new(1, 2, 3, 0, 0, 2, false, false).unwrap(),
+    ]).unwrap();
+    t.aggregate_matches(vec![
+        Matching::new(2, 0, 3, 3, 0, 1, false, false).unwrap(),
+        Matching::dropped_new(2,1),
+        Matching::no_opponent_new(2,2),
+    ]).unwrap();
+    let ids = |t: &Tournament| t.standings().iter().map(|p| p.id()).collect::<Vec<usize>>();
+    // 2 and 3 have 3 points, and 3 has better OMW%
+    assert_eq!(ids(&t), vec![0, 3, 2, 1]);
+    t.set_tiebreaks(vec![]);
+    assert_eq!(ids(&t), vec![0, 2, 3, 1]);
+    t.set_tiebreaks(vec![Box::new(crate::tournament_model::tiebreak::HeadToHead)]);
+    assert_eq!(ids(&t), vec![0, 3, 2, 1]);
+}
+
+#[test]
+fn test_scoring_system() {
+    use crate::assert_ap;
+    let mut t: Tournament = Default::default();
+    for i in 0..4 {
+        let p: Player = Player::new(i, format!("{}abcd", i));
+        t.add_player(p);
+    }
+    t.aggregate_matches(vec![
+        Matching::new(1, 0, 1, 1, 0, 0, false, false).unwrap(),
+        Matching::new(1, 2, 3, 0, 1, 0, false, false).unwrap(),
+    ]).unwrap();
+    t.aggregate_matches(vec![
+        Matching::new(2, 0, 2, 0, 1, 0, false, false).unwrap(),
+        Matching::no_opponent_new(2, 1),
+        Matching::dropped_new(2, 3),
+    ]).unwrap();
+    assert_eq!(t.players[0].points(), 4.0);
+    t.set_scoring_system(ScoringSystem::chess()).unwrap();
+    let points: Vec<f64> = t.players.iter().map(|p| p.points()).collect();
+    assert_eq!(points, vec![1.5, 1.0, 1.0, 0.5]);
+    assert_ap!(t.players[0].match_win_percentage(), 0.75, 1e-5);
+    assert_ap!(t.players[2].match_win_percentage(), 0.5, 1e-5);
+    assert_ap!(t.players[3].match_win_percentage(), 0.5, 1e-5);
+    assert_ap!(t.players[0].game_win_percentage(), 0.75, 1e-5);
+    t.set_scoring_system(ScoringSystem::new(3.0, 1.0, 0.0, 2.0, 3.0, 0.0).unwrap()).unwrap();
+    assert_eq!(t.players[1].points(), 2.0);
+    assert_eq!(t.standings()[0].id(), 0);
+}
+
+#[test]
+fn test_json() {
+    let mut t: Tournament = Default::default();
+    for i in 0..4 {
+        let mut p: Player = Player::new(i, format!("{}abcd", i));
+        p.set_rating(1500 + i as i32);
+        t.add_player(p);
+    }
+    t.set_pairing_system(PairingSystem::Dutch);
+    t.set_tiebreaks(vec![Box::new(crate::tournament_model::tiebreak::Buchholz)]);
+    t.set_scoring_system(ScoringSystem::chess()).unwrap();
+    let mut m = Matching::new(1, 0, 1, 1, 0, 0, false, false).unwrap();
+    m.set_color(Some(Color::White));
+    t.aggregate_matches(vec![m, Matching::new(1, 2, 3, 0, 1, 0, false, false).unwrap()]).unwrap();
+    t.aggregate_matches(vec![Matching::dropped_new(2, 3)]).unwrap();
+
+    let loaded = Tournament::from_json(&t.to_json().unwrap()).unwrap();
+    assert_eq!(loaded.to_json().unwrap(), t.to_json().unwrap());
+    assert_eq!(loaded.round_number(), 2);
+    assert_eq!(loaded.pairing_system(), PairingSystem::Dutch);
+    assert_eq!(loaded.tiebreaks()[0].name(), "Buchholz");
+    assert_eq!(*loaded.scoring_system(), ScoringSystem::chess());
+    assert_eq!(loaded.players()[1].matching_list()[0].color(), Some(Color::Black));
+    assert_eq!(loaded.players()[3].matching_list().len(), 2);
+    assert_eq!(loaded.player_number(), 4);
+
+    // fields missing in older files get the default value
+    let loaded = Tournament::from_json(r#"{ "version": 1, "tournament": { "player_number": 0 } }"#).unwrap();
+    assert_eq!(loaded.tiebreaks().len(), 3);
+    assert!(matches!(Tournament::from_json(r#"{ "version": 2, "tournament": {} }"#), Err(OpoError::UnsupportedVersion(2))));
+    assert!(matches!(Tournament::from_json(r#"{ "tournament": {} }"#), Err(OpoError::InvalidFormat(_))));
+    assert!(matches!(Tournament::from_json(r#"{ "version": 1, "tournament": { "tiebreaks": ["coin toss"] } }"#), Err(OpoError::Json(_))));
+    let zero_win = r#"{ "version": 1, "tournament": { "scoring": { "win": 0, "draw": 0, "loss": 0, "bye": 0, "forfeit_win": 0, "forfeit_loss": 0 } } }"#;
+    assert!(matches!(Tournament::from_json(zero_win), Err(OpoError::InvalidScoring(_))));
+    assert!(matches!(t.set_scoring_system(ScoringSystem::chess().with_bye(f64::NAN)), Err(OpoError::InvalidScoring(_))));
+}
+
+#[test]
+fn test_save_load() {
+    let mut t: Tournament = Default::default();
+    for i in 0..2 {
+        t.add_player(Player::new(i, format!("{}abcd", i)));
+    }
+    t.aggregate_matches(vec![Matching::new(1, 0, 1, 2, 0, 1, false, false).unwrap()]).unwrap();
+    let path = std::env::temp_dir().join(format!("opo_test_save_load_{}.json", std::process::id()));
+    t.save(&path).unwrap();
+    let loaded = Tournament::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded.players()[0].points(), 3.0);
+    assert_eq!(loaded.to_json().unwrap(), t.to_json().unwrap());
+    assert!(matches!(Tournament::load(&path), Err(OpoError::Io(_))));
+}
+
+#[test]
+fn test_top_cut() {
+    let mut t: Tournament = Default::default();
+    for i in 0..4 {
+        t.add_player(Player::new(i, format!("{}abcd", i)));
+    }
+    t.aggregate_matches(vec![
+        Matching::new(1, 3, 0, 2, 0, 0, false, false).unwrap(),
+        Matching::new(1, 2, 1, 2, 0, 0, false, false).unwrap(),
+    ]).unwrap();
+    let bracket = t.top_cut(2).unwrap();
+    assert_eq!(bracket.pairings(), vec![(2, 3)]);
+    assert!(t.top_cut(8).is_err());
+    let bracket = t.double_elimination(4, true).unwrap();
+    assert_eq!(bracket.seeds(), &[2, 3, 0, 1]);
+}
+
+#[test]
+fn test_round_robin_and_groups() {
+    let mut t: Tournament = Default::default();
+    for i in 0..5 {
+        let mut p: Player = Player::new(i, format!("{}abcd", i));
+        p.set_rating(1000 + i as i32);
+        t.add_player(p);
+    }
+    let rounds = t.round_robin(true);
+    assert_eq!(rounds.len(), 10);
+    assert!(rounds.iter().all(|round| round.iter().filter(|o| o.is_none()).count() == 1));
+
+    t.aggregate_matches(vec![
+        Matching::new(1, 0, 1, 2, 0, 0, false, false).unwrap(),
+        Matching::new(1, 2, 3, 2, 0, 0, false, false).unwrap(),
+        Matching::no_opponent_new(1, 4),
+    ]).unwrap();
+    assert_eq!(t.groups(2, GroupSeeding::Rating).unwrap(), vec![vec![4, 1, 0], vec![3, 2]]);
+    let by_standings = t.groups(2, GroupSeeding::Standings).unwrap();
+    assert_eq!(by_standings.concat().len(), 5);
+    assert!(by_standings[0].contains(&t.standings()[0].id()));
+    assert!(t.groups(6, GroupSeeding::Rating).is_err());
+    let rounds = t.group_stage(2, GroupSeeding::Rating, false).unwrap();
+    assert_eq!(rounds.len(), 3);
+    assert_eq!(rounds[0][3], Some(2));
+}
+
+#[test]
+fn test_aggregate_matches_errors() {
+    let mut t: Tournament = Default::default();
+    for i in 0..4 {
+        t.add_player(Player::new(i, format!("{}abcd", i)));
+    }
+    let result = t.aggregate_matches(vec![Matching::new(1, 0, 4, 2, 0, 0, false, false).unwrap()]);
+    assert!(matches!(result, Err(OpoError::UnknownPlayer(4))));
+    let result = t.aggregate_matches(vec![
+        Matching::new(1, 0, 1, 2, 0, 0, false, false).unwrap(),
+        Matching::new(1, 2, 1, 2, 0, 0, false, false).unwrap(),
+    ]);
+    assert!(matches!(result, Err(OpoError::DuplicateResult { round_number: 1, player_id: 1 })));
+    let result = t.aggregate_matches(vec![
+        Matching::new(1, 0, 1, 2, 0, 0, false, false).unwrap(),
+        Matching::no_opponent_new(1, 0),
+    ]);
+    assert!(matches!(result, Err(OpoError::DuplicateResult { round_number: 1, player_id: 0 })));
+    // nothing is added by the rejected rounds
+    assert!(t.players.iter().all(|p| p.matching_list().is_empty()));
+    assert_eq!(OpoError::UnknownPlayer(4).to_string(), "unknown player: 4");
+}
+
+#[cfg(test)]
+fn results_of(round_number: i32, pairings: &[Option<usize>]) -> Vec<Matching> {
+    // the player of the smaller id wins
+    pairings.iter().enumerate()
+        .filter_map(|(i, o)| match *o {
+            Some(n) if i < n => Some(Matching::new(round_number, i, n, 2, 0, 0, false, false).unwrap()),
+            Some(_) => None,
+            None => Some(Matching::no_opponent_new(round_number, i)),
+        })
+        .collect()
+}
+
+#[test]
+fn test_round_lifecycle() {
+    let mut t: Tournament = Default::default();
+    for i in 0..5 {
+        t.add_player(Player::new(i, format!("{}abcd", i)));
+    }
+    assert_eq!(t.state(), RoundState::Registration);
+    assert!(matches!(t.start_round(), Err(OpoError::InvalidState { state: RoundState::Registration, .. })));
+    assert!(matches!(t.finish(), Err(OpoError::InvalidState { .. })));
+
+    for round in 1..4 {
+        let pairings = t.pair_round().unwrap().to_vec();
+        assert_eq!(t.state(), RoundState::Paired(round));
+        // can not pair twice, nor submit results before the round starts
+        assert!(matches!(t.pair_round(), Err(OpoError::InvalidState { .. })));
+        assert!(matches!(t.submit_results(results_of(round, &pairings)), Err(OpoError::InvalidState { .. })));
+        t.start_round().unwrap();
+        assert_eq!(t.state(), RoundState::ResultsPending(round));
+        t.submit_results(results_of(round, &pairings)).unwrap();
+        assert_eq!(t.state(), RoundState::Complete(round));
+        assert_eq!(t.round_number(), round);
+    }
+    // results carry the published colours
+    for p in t.players() {
+        assert!(p.matching_list().iter().all(|m| m.is_no_opponent() || m.color().is_some()));
+    }
+    t.finish().unwrap();
+    assert_eq!(t.state(), RoundState::Finished);
+    assert!(matches!(t.pair_round(), Err(OpoError::InvalidState { state: RoundState::Finished, .. })));
+}
+
+#[test]
+fn test_submit_results_checks_pairings() {
+    let mut t: Tournament = Default::default();
+    for i in 0..5 {
+        t.add_player(Player::new(i, format!("{}abcd", i)));
+    }
+    let pairings = t.pair_round().unwrap().to_vec();
+    t.unpair_round().unwrap();
+    assert_eq!(t.state(), RoundState::Registration);
+    assert!(t.pairings().is_empty());
+    assert_eq!(t.pair_round().unwrap(), &pairings[..]);
+    t.start_round().unwrap();
+
+    let bye = pairings.iter().position(Option::is_none).unwrap();
+    let a = (0..5).find(|&i| i != bye).unwrap();
+    let b = (0..5).find(|&i| i != bye && i != a && pairings[a] != Some(i)).unwrap();
+    let result = t.submit_results(vec![Matching::new(1, a, b, 2, 0, 0, false, false).unwrap()]);
+    assert!(matches!(result, Err(OpoError::NotPaired { opponent_id: Some(_), .. })));
+    let result = t.submit_results(vec![Matching::no_opponent_new(1, a)]);
+    assert!(matches!(result, Err(OpoError::NotPaired { opponent_id: None, .. })));
+    assert!(matches!(t.submit_results(results_of(2, &pairings)), Err(OpoError::WrongRound { expected: 1, found: 2 })));
+    let mut partial = results_of(1, &pairings);
+    let removed = partial.pop().unwrap();
+    assert!(matches!(t.submit_results(partial), Err(OpoError::MissingResult(id)) if id == removed.player_id()));
+    assert_eq!(t.state(), RoundState::ResultsPending(1));
+    assert!(t.players().iter().all(|p| p.matching_list().is_empty()));
+
+    t.submit_results(results_of(1, &pairings)).unwrap();
+    let loaded = Tournament::from_json(&t.to_json().unwrap()).unwrap();
+    assert_eq!(loaded.state(), RoundState::Complete(1));
+    assert_eq!(loaded.pairings(), &pairings[..]);
+}
+
+#[test]
+fn test_partial_results() {
+    let mut t: Tournament = Default::default();
+    t.set_scoring_system(ScoringSystem::chess()).unwrap();
+    for i in 0..5 {
+        t.add_player(Player::new(i, format!("{}abcd", i)));
+    }
+    assert!(t.outstanding_tables().is_empty());
+    let pairings = t.pair_round().unwrap().to_vec();
+    assert!(matches!(t.record_result(Matching::no_opponent_new(1, 0)), Err(OpoError::InvalidState { .. })));
+    t.start_round().unwrap();
+    let mut tables = t.outstanding_tables();
+    assert_eq!(tables.len(), 3);
+    assert_eq!(tables.iter().filter(|(_, o)| o.is_none()).count(), 1);
+
+    let (a, b) = tables.iter().copied().find_map(|(a, b)| b.map(|b| (a, b))).unwrap();
+    t.record_result(Matching::new(1, b, a, 1, 0, 0, false, false).unwrap()).unwrap();
+    tables.retain(|&(id, _)| id != a);
+    assert_eq!(t.outstanding_tables(), tables);
+    // the same table can not be entered twice
+    let result = t.record_result(Matching::new(1, a, b, 1, 0, 0, false, false).unwrap());
+    assert!(matches!(result, Err(OpoError::DuplicateResult { .. })));
+    // provisional standings count the finished table only
+    let standings = t.standings();
+    assert_eq!(standings[0].id(), b);
+    assert_eq!(standings[0].points(), 1.0);
+    assert!(standings[1..].iter().all(|p| p.points() == 0.0));
+    assert!(matches!(t.close_round(), Err(OpoError::MissingResult(_))));
+    assert_eq!(t.state(), RoundState::ResultsPending(1));
+
+    // the rest of the round at once
+    let rest: Vec<Matching> = results_of(1, &pairings).into_iter().filter(|m| m.player_id() != a).collect();
+    t.submit_results(rest).unwrap();
+    assert!(t.outstanding_tables().is_empty());
+    assert_eq!(t.state(), RoundState::Complete(1));
+}
+
+#[test]
+fn test_amend_after_drops() {
+    let mut t: Tournament = Default::default();
+    t.set_scoring_system(ScoringSystem::chess()).unwrap();
+    for i in 0..5 {
+        t.add_player(Player::new(i, format!("{}abcd", i)));
+    }
+    for round in 1..4 {
+        let pairings = t.pair_round().unwrap().to_vec();
+        t.start_round().unwrap();
+        t.submit_results(results_of(round, &pairings)).unwrap();
+    }
+    let unchanged = t.recorded_result(1, 0).unwrap();
+    assert!(t.amend_result(unchanged.clone()).unwrap().is_empty());
+    // the later rounds are paired again in their own fields: the players who dropped afterwards are still in them
+    let odd_bye = t.round_pairings(2).iter().position(Option::is_none).unwrap();
+    let paired = (0..5).find(|&id| id != odd_bye && t.round_pairings(3)[id].is_some()).unwrap();
+    t.drop_player(odd_bye).unwrap();
+    t.drop_player(paired).unwrap();
+    assert!(t.amend_result(unchanged).unwrap().is_empty());
+}
+
+#[test]
+fn test_amend_and_delete_results() {
+    let mut t: Tournament = Default::default();
+    t.set_scoring_system(ScoringSystem::chess()).unwrap();
+    for i in 0..4 {
+        t.add_player(Player::new(i, format!("{}abcd", i)));
+    }
+    for round in 1..3 {
+        let pairings = t.pair_round().unwrap().to_vec();
+        t.start_round().unwrap();
+        t.submit_results(results_of(round, &pairings)).unwrap();
+    }
+    // the winners of round 1 met in round 2
+    let round1 = t.round_pairings(1).to_vec();
+    let (winner, loser) = (0, round1[0].unwrap());
+    let other_winner = (1..4).find(|&id| id != loser && round1[id].unwrap() > id).unwrap();
+    assert_eq!(t.round_pairings(2)[winner], Some(other_winner));
+
+    // the same result again changes nothing
+    let same = t.amend_result(Matching::new(1, winner, loser, 2, 0, 0, false, false).unwrap()).unwrap();
+    assert!(same.is_empty());
+    // the loser won after all, and would have met the other winner
+    let changed = t.amend_result(Matching::new(1, loser, winner, 2, 0, 0, false, false).unwrap()).unwrap();
+    assert_eq!(changed, vec![2]);
+    let result = |id: usize| t.players()[id].matching_list().iter().find(|m| m.round_number() == 1).unwrap().clone();
+    assert!(result(loser).is_win() && result(winner).is_lose());
+    assert_eq!(result(winner).opponent_id(), loser);
+    assert_eq!(result(winner).color().map(Color::opposite), result(loser).color());
+    assert_eq!(t.players()[loser].points(), t.players()[loser].matching_list().iter().filter(|m| m.is_win()).count() as f64);
+
+    let result = t.amend_result(Matching::new(1, loser, other_winner, 2, 0, 0, false, false).unwrap());
+    assert!(matches!(result, Err(OpoError::NotPaired { .. })));
+    assert!(matches!(t.amend_result(Matching::no_opponent_new(3, 0)), Err(OpoError::NoSuchResult { round_number: 3, player_id: 0 })));
+
+    // deleting a result of the last round reopens the table
+    let opponent = t.round_pairings(2)[0].unwrap();
+    assert!(t.delete_result(2, 0).unwrap().is_empty());
+    assert_eq!(t.state(), RoundState::ResultsPending(2));
+    assert_eq!(t.outstanding_tables(), vec![(0, Some(opponent))]);
+    assert!(t.players()[opponent].matching_list().iter().all(|m| m.round_number() == 1));
+    assert!(matches!(t.delete_result(2, opponent), Err(OpoError::NoSuchResult { .. })));
+    // a result of an earlier round can not be deleted, since it could not be entered again
+    assert!(matches!(t.delete_result(1, 0), Err(OpoError::WrongRound { expected: 2, found: 1 })));
+    let reentered = Matching::new(2, 0, opponent, 1, 0, 0, false, false).unwrap();
+    t.record_result(reentered).unwrap();
+    t.close_round().unwrap();
+    assert_eq!(t.state(), RoundState::Complete(2));
+    t.pair_round().unwrap();
+    assert!(matches!(t.delete_result(2, 0), Err(OpoError::InvalidState { state: RoundState::Paired(3), .. })));
+    t.start_round().unwrap();
+    assert!(matches!(t.delete_result(2, 0), Err(OpoError::WrongRound { expected: 3, found: 2 })));
+}
+
+#[test]
+fn test_journal_replay() {
+    let mut t: Tournament = Default::default();
+    t.set_scoring_system(ScoringSystem::chess()).unwrap();
+    for i in 0..6 {
+        t.add_player(Player::new(i, format!("{}abcd", i)));
+    }
+    for round in 1..4 {
+        let pairings = t.pair_round().unwrap().to_vec();
+        t.start_round().unwrap();
+        t.submit_results(results_of(round, &pairings)).unwrap();
+    }
+    let before_correction = t.journal().len();
+    let loser = t.round_pairings(2)[0].unwrap();
+    t.amend_result(Matching::new(2, loser, 0, 2, 0, 0, false, false).unwrap()).unwrap();
+    t.drop_player(5).unwrap();
+    assert!(matches!(t.journal().last(), Some(Event::PlayerDropped { player_id: 5 })));
+
+    // the whole journal gives the same tournament
+    let replayed = Tournament::replay(t.journal()).unwrap();
+    assert_eq!(replayed.to_json().unwrap(), t.to_json().unwrap());
+    let loaded = Tournament::from_json(&t.to_json().unwrap()).unwrap();
+    assert_eq!(loaded.journal().len(), t.journal().len());
+
+    // the standings after round 3 before the correction
+    let before = Tournament::replay(&t.journal()[..before_correction]).unwrap();
+    assert_eq!(before.state(), RoundState::Complete(3));
+    assert!(before.players()[0].matching_list()[1].is_win());
+    assert!(t.players()[0].matching_list()[1].is_lose());
+    assert_eq!(before.standings()[0].points(), 3.0);
+    assert!(!before.players()[5].is_dropped());
+
+    // the first events only
+    let start = t.journal().iter().position(|e| matches!(e, Event::RoundStarted { round_number: 2 })).unwrap();
+    let during = Tournament::replay(&t.journal()[..=start]).unwrap();
+    assert_eq!(during.state(), RoundState::ResultsPending(2));
+    assert_eq!(during.outstanding_tables().len(), 3);
+    assert!(matches!(t.drop_player(6), Err(OpoError::UnknownPlayer(6))));
+}
+
+#[test]
+fn test_pair_next_round() {
+    let mut t: Tournament = Default::default();
+    for i in 0..3 {
+        t.add_player(Player::new(i, format!("{}abcd", i)));
+    }
+    assert_eq!(t.pair_next_round().unwrap(), 1);
+    assert_eq!(t.state(), RoundState::ResultsPending(1));
+    assert_eq!(t.outstanding_tables().len(), 1);
+    let bye = t.pairings().iter().position(Option::is_none).unwrap();
+    assert!(t.players()[bye].matching_list()[0].is_no_opponent());
+    assert!(matches!(t.pair_next_round(), Err(OpoError::InvalidState { .. })));
+}
+
+#[test]
+fn test_table_numbers() {
+    let mut t: Tournament = Default::default();
+    for i in 0..8 {
+        t.add_player(Player::new(i, format!("{}abcd", i)));
+    }
+    let mut settings = TableSettings::default();
+    settings.fixed.insert(7, 2);
+    settings.feature.insert(1);
+    t.set_table_settings(settings);
+    assert!(matches!(t.set_feature_match(1, 0), Err(OpoError::InvalidState { .. })));
+    t.pair_next_round().unwrap();
+    let numbers = |t: &Tournament| t.tables().iter().map(|table| table.number).collect::<Vec<_>>();
+    assert_eq!(numbers(&t), vec![2, 3, 4, 5]);
+    let fixed = t.tables()[0];
+    assert!(fixed.player_id == 7 || fixed.opponent_id == 7);
+
+    // a feature match moves to the feature table, and a second one swaps with it
+    let (first, second) = (t.tables()[1], t.tables()[2]);
+    t.set_feature_match(1, first.opponent_id).unwrap();
+    assert_eq!(numbers(&t), vec![1, 2, 4, 5]);
+    t.set_feature_match(1, second.player_id).unwrap();
+    assert_eq!(t.tables()[0], Table { number: 1, ..second });
+    assert_eq!(t.tables()[2], Table { number: 4, ..first });
+    assert!(matches!(t.set_feature_match(3, 0), Err(OpoError::NotFeatureTable(3))));
+
+    let replayed = Tournament::replay(t.journal()).unwrap();
+    assert_eq!(replayed.tables(), t.tables());
+    assert!(t.byes().is_empty());
+}
+
+#[test]
+fn test_bye_policy() {
+    let mut t: Tournament = Default::default();
+    t.set_scoring_system(ScoringSystem::chess().with_bye(0.5).with_bye_game_win(true)).unwrap();
+    for i in 0..5 {
+        t.add_player(Player::new(i, format!("{}abcd", i)));
+    }
+    t.aggregate_matches(vec![
+        Matching::new(1, 0, 1, 2, 0, 0, false, false).unwrap(),
+        Matching::new(1, 2, 3, 2, 0, 1, false, false).unwrap(),
+        Matching::no_opponent_new(1, 4),
+    ]).unwrap();
+    assert_eq!(t.players()[4].points(), 0.5);
+    assert_eq!(t.players()[4].game_win_percentage(), 1.0);
+
+    // the bye goes to the lowest-ranked player who has not had one
+    let lowest = t.standings().iter().rev().map(|p| p.id()).find(|&id| id != 4).unwrap();
+    assert_eq!(lowest, 1);
+    assert_eq!(t.matching_build().unwrap()[lowest], None);
+    assert_eq!(t.greedy_matching_build().unwrap()[lowest], None);
+    // the Dutch system ranks by pairing number ( score, rating and id ), not by the tiebreakers
+    t.set_pairing_system(PairingSystem::Dutch);
+    assert_eq!(t.matching_build().unwrap()[3], None);
+
+    // assigned byes sit out the pairing, with their own points
+    t.assign_bye(2, 0, None).unwrap();
+    t.assign_bye(2, 2, Some(0.0)).unwrap();
+    t.assign_bye(3, 3, None).unwrap();
+    assert!(t.unassign_bye(3, 3).unwrap());
+    assert!(!t.unassign_bye(3, 3).unwrap());
+    assert!(matches!(t.assign_bye(1, 3, None), Err(OpoError::WrongRound { expected: 2, found: 1 })));
+    assert!(matches!(t.assign_bye(2, 5, None), Err(OpoError::UnknownPlayer(5))));
+    t.set_pairing_system(PairingSystem::Points);
+    t.pair_next_round().unwrap();
+    assert_eq!(t.byes().len(), 3);
+    assert_eq!(t.pairings()[0], None);
+    assert_eq!(t.pairings()[2], None);
+    assert_eq!(t.pairings()[lowest], None);
+    assert_eq!(t.players()[0].points(), 1.5);
+    assert_eq!(t.players()[2].points(), 1.0);
+    assert!(matches!(t.assign_bye(2, 1, None), Err(OpoError::WrongRound { .. })));
+
+    let replayed = Tournament::replay(t.journal()).unwrap();
+    assert_eq!(replayed.to_json().unwrap(), t.to_json().unwrap());
+}
+
+#[test]
+fn test_initial_byes() {
+    let mut t: Tournament = Default::default();
+    t.set_scoring_system(ScoringSystem::football().with_bye_game_win(true)).unwrap();
+    for i in 0..5 {
+        let mut player = Player::new(i, format!("{}abcd", i));
+        player.set_initial_byes([2, 1, 0, 0, 0][i]);
+        t.add_player(player);
+    }
+    assert_eq!(t.assigned_byes(1).keys().copied().collect::<Vec<_>>(), vec![0, 1]);
+    assert!(!t.unassign_bye(1, 0).unwrap());
+    let play_round = |t: &mut Tournament| {
+        let round_number = t.pair_next_round().unwrap();
+        for (id, opponent) in t.outstanding_tables() {
+            t.record_result(Matching::new(round_number, id, opponent.unwrap(), 2, 0, 0, false, false).unwrap()).unwrap();
+        }
+        t.close_round().unwrap();
+        t.pairings().to_vec()
+    };
+
+    // the invited players are not paired, and their byes have the bye points
+    let pairings = play_round(&mut t);
+    assert_eq!((pairings[0], pairings[1]), (None, None));
+    assert_eq!(pairings.iter().filter(|o| o.is_none()).count(), 3);
+    let pairings = play_round(&mut t);
+    assert_eq!(pairings[0], None);
+    assert!(pairings[1..].iter().all(Option::is_some));
+    let player = &t.players()[0];
+    assert!(player.matching_list().iter().all(Matching::is_no_opponent));
+    assert_eq!(player.points(), 6.0);
+    assert_eq!(player.match_win_percentage(), 1.0);
+    assert_eq!(player.game_win_percentage(), 1.0);
+    assert_eq!(player.opponent_match_win_percentage(), 0.0);
+
+    // the opponents' percentages leave the bye of the first round out
+    let player = &t.players()[1];
+    let opponent = &t.players()[pairings[1].unwrap()];
+    assert_eq!(player.opponent_match_win_percentage(), f64::max(1.0 / 3.0, opponent.match_win_percentage()));
+    assert_eq!(player.opponent_game_win_percentage(), opponent.game_win_percentage());
+
+    // the players with initial byes do not take the bye of an odd field
+    let pairings = play_round(&mut t);
+    assert!(pairings[0].is_some() && pairings[1].is_some());
+
+    let replayed = Tournament::replay(t.journal()).unwrap();
+    assert_eq!(replayed.to_json().unwrap(), t.to_json().unwrap());
+}
+
+#[test]
+fn test_bye_is_no_game_against_player_0() {
+    let mut t: Tournament = Default::default();
+    t.set_scoring_system(ScoringSystem::chess()).unwrap();
+    for i in 0..2 {
+        let mut player = Player::new(i, format!("{}abcd", i));
+        player.set_initial_byes(1 - i);
+        t.add_player(player);
     }
+    // player 0 sits out the first round and player 1 takes the bye of an odd field of one
+    assert_eq!(t.pair_next_round().unwrap(), 1);
+    assert_eq!(t.pairings(), &[None, None]);
+    assert_eq!(t.state(), RoundState::Complete(1));
+    assert!(!t.players()[1].had_matched_id(Some(0)));
+    assert_eq!(t.pair_next_round().unwrap(), 2);
+    assert_eq!(t.pairings(), &[Some(1), Some(0)]);
 }
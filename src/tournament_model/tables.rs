@@ -0,0 +1,93 @@
+//! table numbers of the pairings
+//!
+//! the games are numbered from the top score group down, around the fixed tables of some players
+//! ( e.g. for accessibility ) and the feature tables kept for chosen games.
+//! a game keeps the table one of its players had in the previous round when the number falls among the numbers of its score group,
+//! so the leaders who stay on the top table do not move.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// a game of a round at its table, the white player first when the colours are known
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Table {
+    pub number: usize,
+    pub player_id: usize,
+    pub opponent_id: usize,
+}
+
+/// how the tables are numbered
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TableSettings {
+    /// the table of a player in every round, by player id
+    pub fixed: BTreeMap<usize, usize>,
+    /// the tables left empty by the numbering, for the games chosen by `Tournament::set_feature_match`
+    pub feature: BTreeSet<usize>,
+}
+
+/// number the games `(player, opponent, score group)`, given in the order of the standings, and return the tables by number
+///
+/// - a game with a player who has a fixed table is at that table ( the first player's, if both have one ),
+///   unless another game or a feature table has the number
+/// - the other games take the free numbers from 1, a block of numbers for each score group,
+///   and a game takes the number of the previous table of one of its players when that number is in the block of its group
+pub fn number_tables(games: &[(usize, usize, i64)], settings: &TableSettings, previous: &[Table]) -> Vec<Table> {
+    let mut taken = settings.feature.clone();
+    let mut tables = Vec::new();
+    let mut others = Vec::new();
+    for &(player_id, opponent_id, group) in games {
+        let fixed = settings.fixed.get(&player_id).or_else(|| settings.fixed.get(&opponent_id));
+        match fixed {
+            Some(&number) if taken.insert(number) => tables.push(Table { number, player_id, opponent_id }),
+            _ => others.push((player_id, opponent_id, group)),
+        }
+    }
+
+    let previous_numbers: BTreeMap<usize, usize> = previous.iter()
+        .flat_map(|table| [(table.player_id, table.number), (table.opponent_id, table.number)])
+        .collect();
+    let mut free = (1..).filter(|number| !taken.contains(number));
+    let mut rest = &others[..];
+    while let Some(&(_, _, group)) = rest.first() {
+        let size = rest.iter().take_while(|game| game.2 == group).count();
+        let (score_group, next) = rest.split_at(size);
+        let mut numbers: BTreeSet<usize> = free.by_ref().take(size).collect();
+        let mut unnumbered = Vec::new();
+        for &(player_id, opponent_id, _) in score_group {
+            let kept = [player_id, opponent_id].iter()
+                .filter_map(|id| previous_numbers.get(id))
+                .find(|&&number| numbers.remove(&number));
+            match kept {
+                Some(&number) => tables.push(Table { number, player_id, opponent_id }),
+                None => unnumbered.push((player_id, opponent_id)),
+            }
+        }
+        for ((player_id, opponent_id), number) in unnumbered.into_iter().zip(numbers) {
+            tables.push(Table { number, player_id, opponent_id });
+        }
+        rest = next;
+    }
+    tables.sort_by_key(|table| table.number);
+    tables
+}
+
+#[test]
+fn test_number_tables() {
+    let numbers = |tables: &[Table]| tables.iter().map(|table| (table.number, table.player_id)).collect::<Vec<_>>();
+    let games = [(0, 1, 6), (2, 3, 6), (4, 5, 3), (6, 7, 0)];
+    let plain = number_tables(&games, &Default::default(), &[]);
+    assert_eq!(numbers(&plain), vec![(1, 0), (2, 2), (3, 4), (4, 6)]);
+
+    // player 6 keeps table 1, and table 3 is kept for a feature match
+    let mut settings = TableSettings::default();
+    settings.fixed.insert(7, 1);
+    settings.feature.insert(3);
+    let tables = number_tables(&games, &settings, &[]);
+    assert_eq!(numbers(&tables), vec![(1, 6), (2, 0), (4, 2), (5, 4)]);
+
+    // the players of the top table stay there while in the top score group, but not below it
+    let previous = [Table { number: 1, player_id: 3, opponent_id: 9 }, Table { number: 2, player_id: 4, opponent_id: 8 }];
+    let tables = number_tables(&games, &Default::default(), &previous);
+    assert_eq!(numbers(&tables), vec![(1, 2), (2, 0), (3, 4), (4, 6)]);
+}
@@ -0,0 +1,361 @@
+//! tiebreakers and standings
+//!
+//! players are ordered by dropping ( dropped players are the last ), points,
+//! and then by the tiebreakers of the tournament in order, and finally by id.
+
+use super::matching::Matching;
+use super::players::Player;
+use super::scoring::{scaled_points, ScoringSystem};
+use std::fmt::Debug;
+
+/// tiebreaker between players who have the same points
+///
+/// `Send + Sync` so that a tournament can be shared between threads ( see `server` ).
+pub trait Tiebreak: Debug + Send + Sync {
+    /// short name ( used for column headers )
+    fn name(&self) -> &str;
+    /// the value of `player`, higher is better
+    ///
+    /// `players` is every player indexed by id, and `tied` is the ids of the players
+    /// who are still level with `player` ( including `player` itself ).
+    /// `scoring` is the scoring system of the tournament.
+    fn value(&self, player: &Player, players: &[Player], tied: &[usize], scoring: &ScoringSystem) -> f64;
+}
+
+/// values closer than this are level
+const EPSILON: f64 = 1e-9;
+
+/// opponents' match-win percentage
+#[derive(Clone, Copy, Debug, Default)]
+pub struct OpponentMatchWinPercentage;
+
+/// game-win percentage
+#[derive(Clone, Copy, Debug, Default)]
+pub struct GameWinPercentage;
+
+/// opponents' game-win percentage
+#[derive(Clone, Copy, Debug, Default)]
+pub struct OpponentGameWinPercentage;
+
+/// sum of the points of the opponents
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Buchholz;
+
+/// Buchholz without the highest and the lowest opponent
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MedianBuchholz;
+
+/// sum of the points of the beaten opponents and half of the points of the drawn opponents
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SonnebornBerger;
+
+/// points gained against the tied players
+#[derive(Clone, Copy, Debug, Default)]
+pub struct HeadToHead;
+
+/// number of won matches ( no-opponent is not counted )
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Wins;
+
+/// sum of the points after each round
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ProgressiveScore;
+
+/// opponents of the player's matchings which have opponent
+fn opponents<'a>(player: &'a Player, players: &'a [Player]) -> impl Iterator<Item = (&'a Matching, &'a Player)> {
+    player.matching_list().iter()
+        .filter(|matching| matching.is_reversible())
+        .filter_map(move |matching| players.get(matching.opponent_id()).map(|opponent| (matching, opponent)))
+}
+
+impl Tiebreak for OpponentMatchWinPercentage {
+    fn name(&self) -> &str {
+        "OMW%"
+    }
+    fn value(&self, player: &Player, _players: &[Player], _tied: &[usize], _scoring: &ScoringSystem) -> f64 {
+        player.opponent_match_win_percentage()
+    }
+}
+
+impl Tiebreak for GameWinPercentage {
+    fn name(&self) -> &str {
+        "GW%"
+    }
+    fn value(&self, player: &Player, _players: &[Player], _tied: &[usize], _scoring: &ScoringSystem) -> f64 {
+        player.game_win_percentage()
+    }
+}
+
+impl Tiebreak for OpponentGameWinPercentage {
+    fn name(&self) -> &str {
+        "OGW%"
+    }
+    fn value(&self, player: &Player, _players: &[Player], _tied: &[usize], _scoring: &ScoringSystem) -> f64 {
+        player.opponent_game_win_percentage()
+    }
+}
+
+impl Tiebreak for Buchholz {
+    fn name(&self) -> &str {
+        "Buchholz"
+    }
+    fn value(&self, player: &Player, players: &[Player], _tied: &[usize], _scoring: &ScoringSystem) -> f64 {
+        opponents(player, players)
+            .map(|(_, opponent)| opponent.points())
+            .sum()
+    }
+}
+
+impl Tiebreak for MedianBuchholz {
+    fn name(&self) -> &str {
+        "Median-Buchholz"
+    }
+    fn value(&self, player: &Player, players: &[Player], _tied: &[usize], _scoring: &ScoringSystem) -> f64 {
+        let mut points: Vec<f64> = opponents(player, players)
+            .map(|(_, opponent)| opponent.points())
+            .collect();
+        if points.len() > 2 {
+            points.sort_by(f64::total_cmp);
+            points.pop();
+            points.remove(0);
+        }
+        points.into_iter().sum()
+    }
+}
+
+impl Tiebreak for SonnebornBerger {
+    fn name(&self) -> &str {
+        "Sonneborn-Berger"
+    }
+    fn value(&self, player: &Player, players: &[Player], _tied: &[usize], _scoring: &ScoringSystem) -> f64 {
+        opponents(player, players)
+            .map(|(matching, opponent)| if matching.is_win() {
+                opponent.points()
+            } else if matching.is_draw() {
+                opponent.points() / 2.0
+            } else {
+                0.0
+            })
+            .sum()
+    }
+}
+
+impl Tiebreak for HeadToHead {
+    fn name(&self) -> &str {
+        "Head-to-head"
+    }
+    fn value(&self, player: &Player, _players: &[Player], tied: &[usize], scoring: &ScoringSystem) -> f64 {
+        player.matching_list().iter()
+            .filter(|matching| matching.is_reversible())
+            .filter(|matching| tied.contains(&matching.opponent_id()))
+            .map(|matching| matching.matching_points(scoring))
+            .sum()
+    }
+}
+
+impl Tiebreak for Wins {
+    fn name(&self) -> &str {
+        "Wins"
+    }
+    fn value(&self, player: &Player, _players: &[Player], _tied: &[usize], _scoring: &ScoringSystem) -> f64 {
+        player.matching_list().iter()
+            .filter(|matching| !matching.is_no_opponent() && matching.is_win())
+            .count() as f64
+    }
+}
+
+impl Tiebreak for ProgressiveScore {
+    fn name(&self) -> &str {
+        "Progressive"
+    }
+    fn value(&self, player: &Player, _players: &[Player], _tied: &[usize], scoring: &ScoringSystem) -> f64 {
+        player.matching_list().iter()
+            .scan(0.0, |points, matching| {
+                *points += matching.matching_points(scoring);
+                Some(*points)
+            })
+            .sum()
+    }
+}
+
+/// OMW%, GW% and then OGW%
+pub fn default_tiebreaks() -> Vec<Box<dyn Tiebreak>> {
+    vec![
+        Box::new(OpponentMatchWinPercentage),
+        Box::new(GameWinPercentage),
+        Box::new(OpponentGameWinPercentage),
+    ]
+}
+
+/// the built-in tiebreaker whose `name` is `name`
+pub fn builtin(name: &str) -> Option<Box<dyn Tiebreak>> {
+    let tiebreak: Box<dyn Tiebreak> = match name {
+        "OMW%" => Box::new(OpponentMatchWinPercentage),
+        "GW%" => Box::new(GameWinPercentage),
+        "OGW%" => Box::new(OpponentGameWinPercentage),
+        "Buchholz" => Box::new(Buchholz),
+        "Median-Buchholz" => Box::new(MedianBuchholz),
+        "Sonneborn-Berger" => Box::new(SonnebornBerger),
+        "Head-to-head" => Box::new(HeadToHead),
+        "Wins" => Box::new(Wins),
+        "Progressive" => Box::new(ProgressiveScore),
+        _ => return None,
+    };
+    Some(tiebreak)
+}
+
+/// (de)serialise tiebreakers by their `name` ( use with `#[serde(with = "tiebreak::by_name")]` )
+///
+/// only the built-in tiebreakers can be deserialised.
+pub mod by_name {
+    use super::{builtin, Tiebreak};
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(tiebreaks: &[Box<dyn Tiebreak>], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(tiebreaks.iter().map(|tiebreak| tiebreak.name()))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Box<dyn Tiebreak>>, D::Error> {
+        Vec::<String>::deserialize(deserializer)?.iter()
+            .map(|name| builtin(name).ok_or_else(|| D::Error::custom(format!("unknown tiebreaker: {}", name))))
+            .collect()
+    }
+}
+
+/// players from the first place to the last
+///
+/// `players` is indexed by id. players who are level on every tiebreaker are ordered by id.
+pub fn standings<'a>(players: &'a [Player], tiebreaks: &[Box<dyn Tiebreak>], scoring: &ScoringSystem) -> Vec<&'a Player> {
+    ranked(players, tiebreaks, scoring).into_iter().map(|(player, _)| player).collect()
+}
+
+/// a player of the standings with the values of the tiebreakers
+pub type Ranked<'a> = (&'a Player, Vec<f64>);
+
+/// players from the first place to the last ( see `standings` ), with the values of the tiebreakers in order
+///
+/// a value is given among the players who were still level with the player when its tiebreaker was applied,
+/// so a tiebreaker which depends on the tied players ( e.g. head-to-head ) has the value which decided the order.
+pub fn ranked<'a>(players: &'a [Player], tiebreaks: &[Box<dyn Tiebreak>], scoring: &ScoringSystem) -> Vec<Ranked<'a>> {
+    let mut ranking: Vec<&Player> = players.iter().collect();
+    ranking.sort_by(|a, b| a.is_dropped().cmp(&b.is_dropped())
+        .then(scaled_points(b.points()).cmp(&scaled_points(a.points())))
+        .then(a.id().cmp(&b.id())));
+
+    let mut groups: Vec<Vec<Ranked>> = Vec::new();
+    for player in ranking {
+        match groups.last_mut() {
+            Some(group) if group[0].0.is_dropped() == player.is_dropped() && scaled_points(group[0].0.points()) == scaled_points(player.points()) => group.push((player, Vec::new())),
+            _ => groups.push(vec![(player, Vec::new())]),
+        }
+    }
+
+    for tiebreak in tiebreaks {
+        groups = groups.into_iter()
+            .flat_map(|group| break_tie(group, players, tiebreak.as_ref(), scoring))
+            .collect();
+    }
+    groups.into_iter().flatten().collect()
+}
+
+/// add the value of `tiebreak` to each player of `group`, and split the group by it, each part keeps id order
+fn break_tie<'a>(group: Vec<Ranked<'a>>, players: &[Player], tiebreak: &dyn Tiebreak, scoring: &ScoringSystem) -> Vec<Vec<Ranked<'a>>> {
+    let tied: Vec<usize> = group.iter().map(|(p, _)| p.id()).collect();
+    let mut valued: Vec<(f64, Ranked)> = group.into_iter()
+        .map(|(p, mut values)| {
+            let value = tiebreak.value(p, players, &tied, scoring);
+            values.push(value);
+            (value, (p, values))
+        })
+        .collect();
+    valued.sort_by(|a, b| b.0.total_cmp(&a.0).then(a.1.0.id().cmp(&b.1.0.id())));
+
+    let mut parts: Vec<(f64, Vec<Ranked>)> = Vec::new();
+    for (value, entry) in valued {
+        match parts.last_mut() {
+            Some((level, part)) if (*level - value).abs() < EPSILON => part.push(entry),
+            _ => parts.push((value, vec![entry])),
+        }
+    }
+    parts.into_iter().map(|(_, part)| part).collect()
+}
+
+#[cfg(test)]
+fn played(players: &mut [Player], round_number: i32, player_id: usize, opponent_id: usize, win_count: i32, lose_count: i32) {
+    let matching = Matching::new(round_number, player_id, opponent_id, win_count, 0, lose_count, false, false).unwrap();
+    players[opponent_id].add_matching(Matching::rev(&matching));
+    players[player_id].add_matching(matching);
+}
+
+#[cfg(test)]
+fn sample_players() -> Vec<Player> {
+    // 0: W W L, 1: L W W, 2: W L D, 3: L L D, 4: W L bye, 5: L W
+    let mut players: Vec<Player> = (0..6).map(|i| Player::new(i, format!("{}abcd", i))).collect();
+    played(&mut players, 1, 0, 1, 2, 0);
+    played(&mut players, 1, 2, 3, 2, 1);
+    played(&mut players, 1, 4, 5, 2, 0);
+    played(&mut players, 2, 0, 2, 2, 0);
+    played(&mut players, 2, 1, 3, 2, 1);
+    played(&mut players, 2, 5, 4, 2, 0);
+    played(&mut players, 3, 1, 0, 2, 1);
+    played(&mut players, 3, 2, 3, 1, 1);
+    players[4].add_matching(Matching::no_opponent_new(3, 4));
+    for p in players.iter_mut() {
+        p.calculate_points(&ScoringSystem::default());
+    }
+    players
+}
+
+#[test]
+fn test_tiebreak_values() {
+    let players = sample_players();
+    let all: Vec<usize> = (0..6).collect();
+    let s = ScoringSystem::default();
+    let points: Vec<f64> = players.iter().map(|p| p.points()).collect();
+    assert_eq!(points, vec![6.0, 6.0, 4.0, 1.0, 6.0, 3.0]);
+    // opponents of 0 are 1, 2 and 1
+    assert_eq!(Buchholz.value(&players[0], &players, &all, &s), 16.0);
+    assert_eq!(MedianBuchholz.value(&players[0], &players, &all, &s), 6.0);
+    assert_eq!(SonnebornBerger.value(&players[0], &players, &all, &s), 10.0);
+    assert_eq!(SonnebornBerger.value(&players[2], &players, &all, &s), 1.5);
+    assert_eq!(HeadToHead.value(&players[0], &players, &[0, 1, 4], &s), 3.0);
+    assert_eq!(HeadToHead.value(&players[4], &players, &[0, 1, 4], &s), 0.0);
+    assert_eq!(Wins.value(&players[4], &players, &all, &s), 1.0);
+    assert_eq!(ProgressiveScore.value(&players[0], &players, &all, &s), 15.0);
+    assert_eq!(ProgressiveScore.value(&players[1], &players, &all, &s), 9.0);
+    assert_eq!(ProgressiveScore.value(&players[0], &players, &all, &ScoringSystem::chess()), 5.0);
+    assert_eq!(builtin("Sonneborn-Berger").unwrap().name(), "Sonneborn-Berger");
+    assert!(builtin("coin toss").is_none());
+}
+
+#[test]
+fn test_standings() {
+    let mut players = sample_players();
+    let ids = |players: &[Player], tiebreaks: Vec<Box<dyn Tiebreak>>| standings(players, &tiebreaks, &ScoringSystem::default()).iter().map(|p| p.id()).collect::<Vec<usize>>();
+    assert_eq!(ids(&players, vec![]), vec![0, 1, 4, 2, 5, 3]);
+    // 0 and 1 are level on head-to-head, and 0 has better Buchholz
+    assert_eq!(ids(&players, vec![Box::new(HeadToHead), Box::new(Buchholz)]), vec![0, 1, 4, 2, 5, 3]);
+    assert_eq!(ids(&players, vec![Box::new(ProgressiveScore)]), vec![0, 4, 1, 2, 5, 3]);
+    assert_eq!(ids(&players, vec![Box::new(Wins), Box::new(ProgressiveScore)]), vec![0, 1, 4, 2, 5, 3]);
+    // dropped player is the last
+    players[0] = Player::dummy(0);
+    assert_eq!(ids(&players, vec![]), vec![1, 4, 2, 5, 3, 0]);
+}
+
+#[test]
+fn test_ranked_values_of_the_tied_groups() {
+    // 0 beat 2, 2 beat 1 and 1 beat 0: the progressive score puts 0 first, and head-to-head is then between 2 and 1 only
+    let mut players: Vec<Player> = (0..3).map(|i| Player::new(i, format!("{}abcd", i))).collect();
+    played(&mut players, 1, 0, 2, 2, 0);
+    played(&mut players, 2, 2, 1, 2, 0);
+    played(&mut players, 3, 1, 0, 2, 0);
+    for p in players.iter_mut() {
+        p.calculate_points(&ScoringSystem::default());
+    }
+    let tiebreaks: Vec<Box<dyn Tiebreak>> = vec![Box::new(ProgressiveScore), Box::new(HeadToHead)];
+    let ranked: Vec<(usize, Vec<f64>)> = ranked(&players, &tiebreaks, &ScoringSystem::default()).into_iter()
+        .map(|(player, values)| (player.id(), values))
+        .collect();
+    assert_eq!(ranked, vec![(0, vec![6.0, 0.0]), (2, vec![3.0, 3.0]), (1, vec![3.0, 0.0])]);
+}
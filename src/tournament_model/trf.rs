@@ -0,0 +1,337 @@
+//! FIDE tournament report files ( TRF16 )
+//!
+//! only the player lines ( `001` ) are read and written: the starting rank, the name, the rating, the points, the place
+//! and for each round the opponent, the colour and the result. the other lines are ignored on import.
+//! the starting rank of a player is the id + 1.
+
+use super::color::Color;
+use super::error::OpoError;
+use super::matching::Matching;
+use super::players::Player;
+use super::scoring::ScoringSystem;
+use super::swiss_system_tournament::Tournament;
+
+/// where the first round starts in a player line ( 0-based column )
+const FIRST_ROUND: usize = 91;
+/// the width of a round in a player line, with the separating spaces
+const ROUND_WIDTH: usize = 10;
+
+/// the opponent, the colour and the result of a round, e.g. `0012 w 1`
+///
+/// an assigned bye is written by its points in the scoring: `Z` for none, `H` for a draw and `F` for a win.
+fn round_field(matching: &Matching, scoring: &ScoringSystem) -> String {
+    if matching.is_dropped() {
+        // an absence, which `matching_of` reads back as dropped ( and not as a zero-point bye )
+        return "0000 - -".to_string();
+    }
+    if matching.is_no_opponent() {
+        // a bye with the points of the scoring is pairing-allocated, and an assigned one has its own points
+        return match matching.bye_points() {
+            Some(points) if points <= 0.0 => "0000 - Z",
+            Some(points) if points == scoring.draw() => "0000 - H",
+            Some(points) if points == scoring.win() => "0000 - F",
+            _ => "0000 - U",
+        }.to_string();
+    }
+    let color = match matching.color() {
+        Some(Color::White) => 'w',
+        Some(Color::Black) => 'b',
+        None => '-',
+    };
+    let result = if !matching.is_valid() {
+        if matching.is_win() { '+' } else { '-' }
+    } else if matching.is_win() {
+        '1'
+    } else if matching.is_lose() {
+        '0'
+    } else {
+        '='
+    };
+    format!("{:04} {} {}", matching.opponent_id() + 1, color, result)
+}
+
+/// the players of the tournament as TRF16 player lines, with the points and the places of the standings
+pub fn export(tournament: &Tournament) -> String {
+    let places: Vec<usize> = {
+        let mut places = vec![0; tournament.players().len()];
+        for (place, player) in tournament.standings().iter().enumerate() {
+            places[player.id()] = place + 1;
+        }
+        places
+    };
+    let mut trf = format!("062 {}\n", tournament.players().len());
+    for player in tournament.players() {
+        let name: String = player.name().chars().take(33).collect();
+        let rating = if player.rating() > 0 { player.rating().to_string() } else { String::new() };
+        let mut line = format!(
+            "001 {:>4} {:1}{:>3} {:<33} {:>4} {:>3} {:>11} {:>10} {:>4.1} {:>4}",
+            player.id() + 1, "", "", name, rating, "", "", "", player.points(), places[player.id()],
+        );
+        for round_number in 1..=tournament.round_number() {
+            let matching = player.matching_list().iter().find(|m| m.round_number() == round_number);
+            let field = matching.map_or_else(|| " ".repeat(ROUND_WIDTH - 2), |matching| round_field(matching, tournament.scoring_system()));
+            line.push_str("  ");
+            line.push_str(&field);
+        }
+        trf.push_str(line.trim_end());
+        trf.push('\n');
+    }
+    trf
+}
+
+/// a round of a player line: the opponent id, the colour and the result character
+type RoundField = (Option<usize>, Option<Color>, char);
+
+fn invalid(line_number: usize, message: &str) -> OpoError {
+    OpoError::InvalidFormat(format!("line {}: {}", line_number, message))
+}
+
+/// the characters `from..to` of the line ( empty beyond the end of the line )
+///
+/// the columns count characters and not bytes, as `export` pads the names by characters.
+fn slice(line: &str, from: usize, to: usize) -> &str {
+    let byte = |column: usize| line.char_indices().nth(column).map_or(line.len(), |(index, _)| index);
+    &line[byte(from)..byte(to.max(from))]
+}
+
+/// the columns `from..to` of the line, trimmed ( empty beyond the end of the line )
+fn columns(line: &str, from: usize, to: usize) -> &str {
+    slice(line, from, to).trim()
+}
+
+fn parse_round(field: &str, line_number: usize) -> Result<Option<RoundField>, OpoError> {
+    if field.trim().is_empty() {
+        return Ok(None);
+    }
+    let opponent = columns(field, 0, 4);
+    let opponent_id = match opponent.parse::<usize>() {
+        Ok(0) => None,
+        Ok(rank) => Some(rank - 1),
+        Err(_) => return Err(invalid(line_number, &format!("invalid opponent: {}", opponent))),
+    };
+    let color = match columns(field, 5, 6) {
+        "w" | "W" => Some(Color::White),
+        "b" | "B" => Some(Color::Black),
+        "-" | "" => None,
+        color => return Err(invalid(line_number, &format!("invalid colour: {}", color))),
+    };
+    let result = columns(field, 7, 8).chars().next().unwrap_or(' ');
+    Ok(Some((opponent_id, color, result)))
+}
+
+/// the matching of a round from the point of view of the player
+///
+/// a pairing-allocated or full-point bye ( `U`, `F` ) has no opponent,
+/// a zero-point or half-point bye ( `Z`, `H` ) has no opponent and its own points,
+/// and an absence ( `-` ) is a dropped matching which does not count.
+fn matching_of(round_number: i32, player_id: usize, field: RoundField, line_number: usize) -> Result<Matching, OpoError> {
+    let (opponent_id, color, result) = field;
+    let mut matching = match (opponent_id, result) {
+        (None, 'U') | (None, 'F') => Matching::no_opponent_new(round_number, player_id),
+        (None, 'Z') | (None, 'H') => {
+            let mut matching = Matching::no_opponent_new(round_number, player_id);
+            matching.set_bye_points(Some(if result == 'H' { 0.5 } else { 0.0 }));
+            matching
+        }
+        (None, '-') => Matching::dropped_new(round_number, player_id),
+        (Some(opponent_id), '1') | (Some(opponent_id), 'W') => Matching::new(round_number, player_id, opponent_id, 1, 0, 0, false, false)?,
+        (Some(opponent_id), '0') | (Some(opponent_id), 'L') => Matching::new(round_number, player_id, opponent_id, 0, 0, 1, false, false)?,
+        (Some(opponent_id), '=') | (Some(opponent_id), 'D') => Matching::new(round_number, player_id, opponent_id, 0, 1, 0, false, false)?,
+        (Some(opponent_id), '+') => Matching::new(round_number, player_id, opponent_id, 0, 0, 0, false, true)?,
+        (Some(opponent_id), '-') => Matching::new(round_number, player_id, opponent_id, 0, 0, 0, true, false)?,
+        _ => return Err(invalid(line_number, &format!("invalid result: {}", result))),
+    };
+    matching.set_color(color);
+    Ok(matching)
+}
+
+/// a tournament with the players and the results of a TRF16 file, scored as chess ( 1/0.5/0 )
+///
+/// the starting ranks must be 1 to the number of players, and both players of a game must list each other.
+/// a double forfeit is written `-` by both players.
+pub fn import(trf: &str) -> Result<Tournament, OpoError> {
+    let mut entries = Vec::new();
+    for (index, line) in trf.lines().enumerate() {
+        if !line.starts_with("001") {
+            continue;
+        }
+        let line_number = index + 1;
+        let rank = columns(line, 4, 8).parse::<usize>().map_err(|_| invalid(line_number, "invalid starting rank"))?;
+        let name = columns(line, 14, 47).to_string();
+        let rating = match columns(line, 48, 52) {
+            "" => 0,
+            rating => rating.parse::<i32>().map_err(|_| invalid(line_number, "invalid rating"))?,
+        };
+        let mut rounds = Vec::new();
+        let mut from = FIRST_ROUND;
+        let width = line.chars().count();
+        while from < width {
+            rounds.push(parse_round(slice(line, from, from + ROUND_WIDTH - 2), line_number)?);
+            from += ROUND_WIDTH;
+        }
+        entries.push((line_number, rank, name, rating, rounds));
+    }
+    entries.sort_by_key(|entry| entry.1);
+    if let Some(entry) = entries.iter().enumerate().find(|(i, entry)| entry.1 != i + 1).map(|(_, entry)| entry) {
+        return Err(invalid(entry.0, &format!("starting rank {} is duplicated or leaves a gap", entry.1)));
+    }
+
+    let mut tournament: Tournament = Default::default();
+    tournament.set_scoring_system(ScoringSystem::chess())?;
+    for (_, rank, name, rating, _) in &entries {
+        let mut player = Player::new(rank - 1, name.clone());
+        player.set_rating(*rating);
+        tournament.add_player(player);
+    }
+    let round_count = entries.iter().map(|entry| entry.4.len()).max().unwrap_or(0);
+    for round_index in 0..round_count {
+        let round_number = round_index as i32 + 1;
+        let field = |player_id: usize| entries.get(player_id).and_then(|entry| entry.4.get(round_index).copied().flatten());
+        let mut matches = Vec::new();
+        for (player_id, (line_number, ..)) in entries.iter().enumerate() {
+            let (opponent_id, color, result) = match field(player_id) {
+                Some(field) => field,
+                None => continue,
+            };
+            if let Some(opponent_id) = opponent_id {
+                let listed_back = matches!(field(opponent_id), Some((Some(id), _, _)) if id == player_id);
+                if !listed_back {
+                    return Err(invalid(*line_number, &format!("round {}: the opponent does not list the player", round_number)));
+                }
+                if opponent_id < player_id {
+                    continue;
+                }
+                // a double forfeit: both players withdrew
+                if result == '-' && matches!(field(opponent_id), Some((_, _, '-'))) {
+                    let mut matching = Matching::new(round_number, player_id, opponent_id, 0, 0, 0, true, true)?;
+                    matching.set_color(color);
+                    matches.push(matching);
+                    continue;
+                }
+            }
+            matches.push(matching_of(round_number, player_id, (opponent_id, color, result), *line_number)?);
+        }
+        tournament.aggregate_matches(matches)?;
+    }
+    Ok(tournament)
+}
+
+#[test]
+fn test_import() {
+    let trf = "\
+012 Test Open
+062 4
+001    1      Alice                             2100                             1.5    1  0003 w 1  0002 b =
+001    2      Bob                               1900                             1.0    2  0004 b +  0001 w =
+001    3      Carol                                                              0.5    3  0001 b 0  0004 w =
+001    4      Dave                              1800                             0.5    4  0002 w -  0003 b =
+";
+    let t = import(trf).unwrap();
+    assert_eq!(t.players().len(), 4);
+    assert_eq!(t.players()[0].name(), "Alice");
+    assert_eq!((t.players()[0].rating(), t.players()[2].rating()), (2100, 0));
+    assert_eq!(t.round_number(), 2);
+    let points: Vec<f64> = t.players().iter().map(|p| p.points()).collect();
+    assert_eq!(points, vec![1.5, 1.5, 0.5, 0.5]);
+    let first = &t.players()[0].matching_list()[0];
+    assert_eq!((first.opponent_id(), first.color()), (2, Some(Color::White)));
+    assert!(!t.players()[3].matching_list()[0].is_valid());
+
+    let broken = "001    1      Alice                             2100                             1.0    1  0002 w 1
+";
+    assert!(matches!(import(broken), Err(OpoError::InvalidFormat(_))));
+    let one_sided = format!("{}001    2      Bob                                                                0.0    2  0000 - U
+", broken);
+    assert!(matches!(import(&one_sided), Err(OpoError::InvalidFormat(_))));
+    let bad_result = "001    1      Alice                                                              0.0    1  0000 - X
+";
+    assert!(matches!(import(bad_result), Err(OpoError::InvalidFormat(_))));
+    // a half-point and a zero-point bye have no opponent
+    for (bye, points) in [("H", 0.5), ("Z", 0.0)] {
+        let t = import(&bad_result.replace('X', bye)).unwrap();
+        let matching = &t.players()[0].matching_list()[0];
+        assert!(matching.is_no_opponent());
+        assert_eq!((matching.bye_points(), t.players()[0].points()), (Some(points), points));
+    }
+}
+
+#[test]
+fn test_export_and_import() {
+    let mut t: Tournament = Default::default();
+    t.set_scoring_system(ScoringSystem::chess()).unwrap();
+    for i in 0..5 {
+        // a name of multi-byte characters keeps the columns of the line
+        let name = if i == 2 { "あ😁し Player 2".to_string() } else { format!("Player {}", i) };
+        let mut player = Player::new(i, name);
+        player.set_rating(2000 - 10 * i as i32);
+        t.add_player(player);
+    }
+    for round_number in 1..4 {
+        if round_number == 2 {
+            t.assign_bye(2, 4, Some(0.5)).unwrap();
+        }
+        let pairings = t.pair_round().unwrap().to_vec();
+        t.start_round().unwrap();
+        let matches = pairings.iter().enumerate()
+            .filter_map(|(i, o)| match *o {
+                Some(n) if i < n && round_number == 2 => Some(Matching::new(round_number, i, n, 0, 1, 0, false, false).unwrap()),
+                Some(n) if i < n => Some(Matching::new(round_number, i, n, 1, 0, 0, false, false).unwrap()),
+                Some(_) => None,
+                None => Some(Matching::no_opponent_new(round_number, i)),
+            })
+            .collect();
+        t.submit_results(matches).unwrap();
+    }
+    let trf = export(&t);
+    let line = trf.lines().nth(1).unwrap();
+    assert!(line.starts_with("001    1      Player 0                          2000"));
+    assert_eq!(&line[80..84], format!("{:>4.1}", t.players()[0].points()));
+    assert_eq!(line.len(), FIRST_ROUND + 3 * ROUND_WIDTH - 2);
+    assert!(trf.lines().nth(5).unwrap().contains("  0000 - H  "));
+
+    let imported = import(&trf).unwrap();
+    assert_eq!(export(&imported), trf);
+    for (a, b) in t.players().iter().zip(imported.players()) {
+        assert_eq!((a.name(), a.rating(), a.points()), (b.name(), b.rating(), b.points()));
+        for (m, n) in a.matching_list().iter().zip(b.matching_list()) {
+            assert_eq!((m.round_number(), m.color(), m.is_win(), m.is_draw()), (n.round_number(), n.color(), n.is_win(), n.is_draw()));
+            assert_eq!((m.is_no_opponent(), m.bye_points()), (n.is_no_opponent(), n.bye_points()));
+        }
+    }
+}
+
+#[test]
+fn test_export_and_import_absence() {
+    let mut t: Tournament = Default::default();
+    t.set_scoring_system(ScoringSystem::chess()).unwrap();
+    for i in 0..3 {
+        t.add_player(Player::new(i, format!("Player {}", i)));
+    }
+    t.aggregate_matches(vec![
+        Matching::new(1, 0, 1, 1, 0, 0, false, false).unwrap(),
+        Matching::dropped_new(1, 2),
+    ]).unwrap();
+    let trf = export(&t);
+    assert!(trf.lines().nth(3).unwrap().ends_with("  0000 - -"));
+
+    let imported = import(&trf).unwrap();
+    assert_eq!(export(&imported), trf);
+    let absent = &imported.players()[2];
+    assert!(absent.matching_list()[0].is_dropped());
+    assert!(!absent.had_matched_id(None));
+}
+
+#[test]
+fn test_bye_codes_follow_the_scoring() {
+    let scoring = ScoringSystem::football();
+    let bye = |points: Option<f64>| {
+        let mut matching = Matching::no_opponent_new(1, 0);
+        matching.set_bye_points(points);
+        round_field(&matching, &scoring)
+    };
+    assert_eq!(bye(None), "0000 - U");
+    assert_eq!(bye(Some(0.0)), "0000 - Z");
+    assert_eq!(bye(Some(scoring.draw())), "0000 - H");
+    assert_eq!(bye(Some(scoring.win())), "0000 - F");
+    assert_eq!(bye(Some(0.5)), "0000 - U");
+}
@@ -0,0 +1,834 @@
+//! maximum weight matching on general graphs
+//!
+//! An implementation of Edmonds' blossom algorithm with dual variables (Galil's O(n^3) formulation),
+//! following Joris van Rantwijk's well known reference implementation.
+//! Weights are integers, so every dual update stays exact.
+
+const NONE: usize = usize::MAX;
+
+/// compute a maximum weight matching of the undirected graph given by `edges` ( `(i, j, weight)` )
+///
+/// `vertex_number` is the number of vertices, they are `0..vertex_number`.
+/// if `max_cardinality` is `true`, only the maximum-cardinality matchings are considered.
+/// returns `mate` where `mate[v]` is `Some(w)` if `v` is matched with `w`
+pub fn max_weight_matching(vertex_number: usize, edges: &[(usize, usize, i64)], max_cardinality: bool) -> Vec<Option<usize>> {
+    if edges.is_empty() {
+        return vec![None; vertex_number];
+    }
+    let mut state = BlossomState::new(vertex_number, edges);
+    state.solve(max_cardinality);
+    state.mate.iter()
+        .map(|&p| if p == NONE { None } else { Some(state.endpoint[p]) })
+        .collect()
+}
+
+/// compute a minimum cost perfect matching of the graph given by `edges` ( `(i, j, cost)` )
+///
+/// returns `None` if the graph has no perfect matching.
+/// the search starts from a greedy matching on the cheapest edges of each vertex,
+/// so fields that are almost paired already ( like swiss rounds ) are solved in a few augmentations.
+pub fn min_cost_perfect_matching(vertex_number: usize, edges: &[(usize, usize, i64)]) -> Option<Vec<usize>> {
+    optimal_perfect_matching(vertex_number, edges).map(|matching| matching.mate)
+}
+
+/// `min_cost_perfect_matching`, with the dual solution which proves the matching optimal
+pub fn optimal_perfect_matching(vertex_number: usize, edges: &[(usize, usize, i64)]) -> Option<PerfectMatching> {
+    if vertex_number == 0 {
+        return Some(PerfectMatching { mate: Vec::new(), max_cost: 0, dualvar: Vec::new(), ancestors: Vec::new(), depth: Vec::new(), dualsum: Vec::new() });
+    }
+    let max_cost = edges.iter().map(|e| e.2).max()?;
+    let weighted: Vec<(usize, usize, i64)> = edges.iter().map(|&(i, j, c)| (i, j, max_cost - c)).collect();
+    let mut state = BlossomState::new(vertex_number, &weighted);
+    state.warm_start();
+    state.solve(true);
+    let mate = state.mate.iter()
+        .map(|&p| if p == NONE { None } else { Some(state.endpoint[p]) })
+        .collect::<Option<Vec<usize>>>()?;
+    // the depth and the dual sum from the outermost blossom of each blossom, parents before children
+    let parent = state.blossomparent;
+    let mut depth = vec![NONE; 2 * vertex_number];
+    let mut dualsum = vec![0; 2 * vertex_number];
+    for b in 0..2 * vertex_number {
+        let mut path = Vec::new();
+        let mut t = b;
+        while t != NONE && depth[t] == NONE {
+            path.push(t);
+            t = parent[t];
+        }
+        let (mut d, mut z) = if t == NONE { (0, 0) } else { (depth[t] + 1, dualsum[t]) };
+        for &t in path.iter().rev() {
+            if t >= vertex_number {
+                z += state.dualvar[t];
+            }
+            depth[t] = d;
+            dualsum[t] = z;
+            d += 1;
+        }
+    }
+    let mut ancestors = vec![parent];
+    while ancestors.last().unwrap().iter().any(|&b| b != NONE) {
+        let last = ancestors.last().unwrap();
+        ancestors.push(last.iter().map(|&b| if b == NONE { NONE } else { last[b] }).collect());
+    }
+    Some(PerfectMatching { mate, max_cost, dualvar: state.dualvar, ancestors, depth, dualsum })
+}
+
+/// a minimum cost perfect matching and its dual solution
+pub struct PerfectMatching {
+    /// `mate[v]` is the vertex matched with `v`
+    pub mate: Vec<usize>,
+    max_cost: i64,
+    dualvar: Vec<i64>,
+    /// `ancestors[k][b]` is the blossom `2^k` levels above the vertex or blossom `b`, or `NONE`
+    ancestors: Vec<Vec<usize>>,
+    depth: Vec<usize>,
+    /// the duals of the blossoms which contain each blossom, itself included
+    dualsum: Vec<i64>,
+}
+
+impl PerfectMatching {
+    /// whether the edge `(i, j, cost)`, which is not in the graph, has a negative reduced cost against the duals
+    ///
+    /// if no edge of a larger graph has, the matching is a minimum cost perfect matching of the larger graph too
+    /// ( the duals stay feasible and the complementary slackness still holds ).
+    pub fn could_improve(&self, i: usize, j: usize, cost: i64) -> bool {
+        let shared = match self.common_blossom(i, j) {
+            NONE => 0,
+            b => self.dualsum[b],
+        };
+        self.dualvar[i] + self.dualvar[j] - 4 * (self.max_cost - cost) + 2 * shared < 0
+    }
+
+    /// a bound on the edges which could improve the matching: `could_improve(i, j, cost)` only if
+    /// `4 * cost + 2 * z < price(i) + price(j)`, where `z` is the dual of the outermost blossom of both ( see `outermost_blossom` ), or 0
+    pub fn price(&self, v: usize) -> i64 {
+        2 * self.max_cost - self.dualvar[v]
+    }
+
+    /// the outermost blossom which contains the vertex, with its dual
+    pub fn outermost_blossom(&self, v: usize) -> Option<(usize, i64)> {
+        let mut b = v;
+        for level in self.ancestors.iter().rev() {
+            if level[b] != NONE {
+                b = level[b];
+            }
+        }
+        if b == v { None } else { Some((b, self.dualvar[b])) }
+    }
+
+    /// the innermost blossom which contains both vertices, or `NONE`
+    fn common_blossom(&self, i: usize, j: usize) -> usize {
+        let (mut bi, mut bj) = (i, j);
+        if self.depth[bi] < self.depth[bj] {
+            std::mem::swap(&mut bi, &mut bj);
+        }
+        for (k, level) in self.ancestors.iter().enumerate().rev() {
+            if self.depth[bi] >= self.depth[bj] + (1 << k) {
+                bi = level[bi];
+            }
+        }
+        if bi == bj {
+            return bi;
+        }
+        for level in self.ancestors.iter().rev() {
+            if level[bi] != level[bj] {
+                bi = level[bi];
+                bj = level[bj];
+            }
+        }
+        self.ancestors[0][bi]
+    }
+}
+
+struct BlossomState {
+    n: usize,
+    /// edges with doubled weights, so that every slack is even
+    edges: Vec<(usize, usize, i64)>,
+    /// `endpoint[p]` is the vertex to which endpoint `p` is attached ( edge `p / 2` )
+    endpoint: Vec<usize>,
+    /// remote endpoints of the edges incident to each vertex
+    neighbend: Vec<Vec<usize>>,
+    /// remote endpoint of the matched edge, or `NONE`
+    mate: Vec<usize>,
+    /// 0: free, 1: S, 2: T ( 5 is a temporary mark while scanning )
+    label: Vec<u8>,
+    labelend: Vec<usize>,
+    inblossom: Vec<usize>,
+    blossomparent: Vec<usize>,
+    blossomchilds: Vec<Vec<usize>>,
+    blossombase: Vec<usize>,
+    blossomendps: Vec<Vec<usize>>,
+    bestedge: Vec<usize>,
+    blossombestedges: Vec<Option<Vec<usize>>>,
+    unusedblossoms: Vec<usize>,
+    dualvar: Vec<i64>,
+    allowedge: Vec<bool>,
+    queue: Vec<usize>,
+}
+
+/// python-like index ( negative value counts from the end )
+fn wrap(len: usize, j: isize) -> usize {
+    j.rem_euclid(len as isize) as usize
+}
+
+impl BlossomState {
+    fn new(vertex_number: usize, edges: &[(usize, usize, i64)]) -> Self {
+        let n = vertex_number;
+        let edges: Vec<(usize, usize, i64)> = edges.iter().map(|&(i, j, w)| (i, j, w * 2)).collect();
+        let max_weight = edges.iter().map(|e| e.2).max().unwrap_or(0).max(0);
+        let mut endpoint = Vec::with_capacity(2 * edges.len());
+        let mut neighbend = vec![Vec::new(); n];
+        for (k, &(i, j, _)) in edges.iter().enumerate() {
+            endpoint.push(i);
+            endpoint.push(j);
+            neighbend[i].push(2 * k + 1);
+            neighbend[j].push(2 * k);
+        }
+        let edge_number = edges.len();
+        BlossomState {
+            n,
+            edges,
+            endpoint,
+            neighbend,
+            mate: vec![NONE; n],
+            label: vec![0; 2 * n],
+            labelend: vec![NONE; 2 * n],
+            inblossom: (0..n).collect(),
+            blossomparent: vec![NONE; 2 * n],
+            blossomchilds: vec![Vec::new(); 2 * n],
+            blossombase: (0..n).chain(vec![NONE; n]).collect(),
+            blossomendps: vec![Vec::new(); 2 * n],
+            bestedge: vec![NONE; 2 * n],
+            blossombestedges: vec![None; 2 * n],
+            unusedblossoms: (n..2 * n).collect(),
+            dualvar: vec![max_weight; n].into_iter().chain(vec![0; n]).collect(),
+            allowedge: vec![false; edge_number],
+            queue: Vec::new(),
+        }
+    }
+
+    /// give each vertex the dual of its heaviest edge and match the tight edges greedily
+    ///
+    /// only valid for perfect matchings ( the duals of free vertices are no longer equal )
+    fn warm_start(&mut self) {
+        for v in 0..self.n {
+            self.dualvar[v] = self.neighbend[v].iter().map(|p| self.edges[p / 2].2).max().unwrap_or(0);
+        }
+        let mut order: Vec<usize> = (0..self.edges.len()).collect();
+        order.sort_by_key(|&k| std::cmp::Reverse(self.edges[k].2));
+        for k in order {
+            let (i, j, _) = self.edges[k];
+            if i != j && self.mate[i] == NONE && self.mate[j] == NONE && self.slack(k) == 0 {
+                self.mate[i] = 2 * k + 1;
+                self.mate[j] = 2 * k;
+            }
+        }
+    }
+
+    fn slack(&self, k: usize) -> i64 {
+        let (i, j, wt) = self.edges[k];
+        self.dualvar[i] + self.dualvar[j] - 2 * wt
+    }
+
+    fn blossom_leaves(&self, b: usize) -> Vec<usize> {
+        let mut leaves = Vec::new();
+        let mut stack = vec![b];
+        while let Some(t) = stack.pop() {
+            if t < self.n {
+                leaves.push(t);
+            } else {
+                stack.extend(self.blossomchilds[t].iter().rev());
+            }
+        }
+        leaves
+    }
+
+    fn assign_label(&mut self, w: usize, t: u8, p: usize) {
+        let mut w = w;
+        let mut t = t;
+        let mut p = p;
+        loop {
+            let b = self.inblossom[w];
+            self.label[w] = t;
+            self.label[b] = t;
+            self.labelend[w] = p;
+            self.labelend[b] = p;
+            self.bestedge[w] = NONE;
+            self.bestedge[b] = NONE;
+            if t == 1 {
+                let leaves = self.blossom_leaves(b);
+                self.queue.extend(leaves);
+                return;
+            }
+            let base = self.blossombase[b];
+            let mate_base = self.mate[base];
+            w = self.endpoint[mate_base];
+            t = 1;
+            p = mate_base ^ 1;
+        }
+    }
+
+    fn scan_blossom(&mut self, v: usize, w: usize) -> usize {
+        let mut path = Vec::new();
+        let mut base = NONE;
+        let mut v = v;
+        let mut w = w;
+        while v != NONE || w != NONE {
+            let mut b = self.inblossom[v];
+            if self.label[b] & 4 != 0 {
+                base = self.blossombase[b];
+                break;
+            }
+            path.push(b);
+            self.label[b] = 5;
+            if self.labelend[b] == NONE {
+                v = NONE;
+            } else {
+                v = self.endpoint[self.labelend[b]];
+                b = self.inblossom[v];
+                v = self.endpoint[self.labelend[b]];
+            }
+            if w != NONE {
+                std::mem::swap(&mut v, &mut w);
+            }
+        }
+        for b in path {
+            self.label[b] = 1;
+        }
+        base
+    }
+
+    fn add_blossom(&mut self, base: usize, k: usize) {
+        let (mut v, mut w, _) = self.edges[k];
+        let bb = self.inblossom[base];
+        let mut bv = self.inblossom[v];
+        let mut bw = self.inblossom[w];
+        let b = self.unusedblossoms.pop().unwrap();
+        self.blossombase[b] = base;
+        self.blossomparent[b] = NONE;
+        self.blossomparent[bb] = b;
+        let mut path = Vec::new();
+        let mut endps = Vec::new();
+        while bv != bb {
+            self.blossomparent[bv] = b;
+            path.push(bv);
+            endps.push(self.labelend[bv]);
+            v = self.endpoint[self.labelend[bv]];
+            bv = self.inblossom[v];
+        }
+        path.push(bb);
+        path.reverse();
+        endps.reverse();
+        endps.push(2 * k);
+        while bw != bb {
+            self.blossomparent[bw] = b;
+            path.push(bw);
+            endps.push(self.labelend[bw] ^ 1);
+            w = self.endpoint[self.labelend[bw]];
+            bw = self.inblossom[w];
+        }
+        self.label[b] = 1;
+        self.labelend[b] = self.labelend[bb];
+        self.dualvar[b] = 0;
+        for leaf in self.blossom_leaves_of(&path) {
+            if self.label[self.inblossom[leaf]] == 2 {
+                self.queue.push(leaf);
+            }
+            self.inblossom[leaf] = b;
+        }
+        let mut bestedgeto = vec![NONE; 2 * self.n];
+        for &bv in &path {
+            let nblists: Vec<Vec<usize>> = match self.blossombestedges[bv].take() {
+                Some(list) => vec![list],
+                None => self.blossom_leaves(bv).into_iter()
+                    .map(|leaf| self.neighbend[leaf].iter().map(|p| p / 2).collect())
+                    .collect(),
+            };
+            for nblist in nblists {
+                for k in nblist {
+                    let (mut i, mut j, _) = self.edges[k];
+                    if self.inblossom[j] == b {
+                        std::mem::swap(&mut i, &mut j);
+                    }
+                    let bj = self.inblossom[j];
+                    if bj != b && self.label[bj] == 1
+                        && (bestedgeto[bj] == NONE || self.slack(k) < self.slack(bestedgeto[bj])) {
+                        bestedgeto[bj] = k;
+                    }
+                }
+            }
+            self.bestedge[bv] = NONE;
+        }
+        let bestedges: Vec<usize> = bestedgeto.into_iter().filter(|&k| k != NONE).collect();
+        let mut best = NONE;
+        for &k in &bestedges {
+            if best == NONE || self.slack(k) < self.slack(best) {
+                best = k;
+            }
+        }
+        self.blossombestedges[b] = Some(bestedges);
+        self.bestedge[b] = best;
+        self.blossomchilds[b] = path;
+        self.blossomendps[b] = endps;
+    }
+
+    fn blossom_leaves_of(&self, blossoms: &[usize]) -> Vec<usize> {
+        blossoms.iter().flat_map(|&b| self.blossom_leaves(b)).collect()
+    }
+
+    fn expand_blossom(&mut self, b: usize, endstage: bool) {
+        let childs = self.blossomchilds[b].clone();
+        for &s in &childs {
+            self.blossomparent[s] = NONE;
+            if s < self.n {
+                self.inblossom[s] = s;
+            } else if endstage && self.dualvar[s] == 0 {
+                self.expand_blossom(s, endstage);
+            } else {
+                for leaf in self.blossom_leaves(s) {
+                    self.inblossom[leaf] = s;
+                }
+            }
+        }
+        if !endstage && self.label[b] == 2 {
+            let len = childs.len();
+            let endps = self.blossomendps[b].clone();
+            let entrychild = self.inblossom[self.endpoint[self.labelend[b] ^ 1]];
+            let mut j = childs.iter().position(|&c| c == entrychild).unwrap() as isize;
+            let (jstep, endptrick): (isize, usize) = if j & 1 == 1 {
+                j -= len as isize;
+                (1, 0)
+            } else {
+                (-1, 1)
+            };
+            let mut p = self.labelend[b];
+            while j != 0 {
+                self.label[self.endpoint[p ^ 1]] = 0;
+                let q = endps[wrap(len, j - endptrick as isize)] ^ endptrick ^ 1;
+                self.label[self.endpoint[q]] = 0;
+                self.assign_label(self.endpoint[p ^ 1], 2, p);
+                self.allowedge[endps[wrap(len, j - endptrick as isize)] / 2] = true;
+                j += jstep;
+                p = endps[wrap(len, j - endptrick as isize)] ^ endptrick;
+                self.allowedge[p / 2] = true;
+                j += jstep;
+            }
+            let bv = childs[wrap(len, j)];
+            self.label[self.endpoint[p ^ 1]] = 2;
+            self.label[bv] = 2;
+            self.labelend[self.endpoint[p ^ 1]] = p;
+            self.labelend[bv] = p;
+            self.bestedge[bv] = NONE;
+            j += jstep;
+            while childs[wrap(len, j)] != entrychild {
+                let bv = childs[wrap(len, j)];
+                if self.label[bv] == 1 {
+                    j += jstep;
+                    continue;
+                }
+                if let Some(v) = self.blossom_leaves(bv).into_iter().find(|&v| self.label[v] != 0) {
+                    self.label[v] = 0;
+                    self.label[self.endpoint[self.mate[self.blossombase[bv]]]] = 0;
+                    self.assign_label(v, 2, self.labelend[v]);
+                }
+                j += jstep;
+            }
+        }
+        self.label[b] = 0;
+        self.labelend[b] = NONE;
+        self.blossomchilds[b] = Vec::new();
+        self.blossomendps[b] = Vec::new();
+        self.blossombase[b] = NONE;
+        self.blossombestedges[b] = None;
+        self.bestedge[b] = NONE;
+        self.unusedblossoms.push(b);
+    }
+
+    fn augment_blossom(&mut self, b: usize, v: usize) {
+        let mut t = v;
+        while self.blossomparent[t] != b {
+            t = self.blossomparent[t];
+        }
+        if t >= self.n {
+            self.augment_blossom(t, v);
+        }
+        let childs = self.blossomchilds[b].clone();
+        let endps = self.blossomendps[b].clone();
+        let len = childs.len();
+        let i = childs.iter().position(|&c| c == t).unwrap();
+        let mut j = i as isize;
+        let (jstep, endptrick): (isize, usize) = if i & 1 == 1 {
+            j -= len as isize;
+            (1, 0)
+        } else {
+            (-1, 1)
+        };
+        while j != 0 {
+            j += jstep;
+            let t = childs[wrap(len, j)];
+            let p = endps[wrap(len, j - endptrick as isize)] ^ endptrick;
+            if t >= self.n {
+                self.augment_blossom(t, self.endpoint[p]);
+            }
+            j += jstep;
+            let t = childs[wrap(len, j)];
+            if t >= self.n {
+                self.augment_blossom(t, self.endpoint[p ^ 1]);
+            }
+            self.mate[self.endpoint[p]] = p ^ 1;
+            self.mate[self.endpoint[p ^ 1]] = p;
+        }
+        self.blossomchilds[b].rotate_left(i);
+        self.blossomendps[b].rotate_left(i);
+        self.blossombase[b] = self.blossombase[self.blossomchilds[b][0]];
+    }
+
+    fn augment_matching(&mut self, k: usize) {
+        let (v, w, _) = self.edges[k];
+        for &(s, p) in &[(v, 2 * k + 1), (w, 2 * k)] {
+            let mut s = s;
+            let mut p = p;
+            loop {
+                let bs = self.inblossom[s];
+                if bs >= self.n {
+                    self.augment_blossom(bs, s);
+                }
+                self.mate[s] = p;
+                if self.labelend[bs] == NONE {
+                    break;
+                }
+                let t = self.endpoint[self.labelend[bs]];
+                let bt = self.inblossom[t];
+                s = self.endpoint[self.labelend[bt]];
+                let j = self.endpoint[self.labelend[bt] ^ 1];
+                if bt >= self.n {
+                    self.augment_blossom(bt, j);
+                }
+                self.mate[j] = self.labelend[bt];
+                p = self.labelend[bt] ^ 1;
+            }
+        }
+    }
+
+    fn solve(&mut self, max_cardinality: bool) {
+        let n = self.n;
+        for _ in 0..n {
+            self.label.iter_mut().for_each(|l| *l = 0);
+            self.bestedge.iter_mut().for_each(|e| *e = NONE);
+            self.blossombestedges[n..].iter_mut().for_each(|e| *e = None);
+            self.allowedge.iter_mut().for_each(|a| *a = false);
+            self.queue.clear();
+            for v in 0..n {
+                if self.mate[v] == NONE && self.label[self.inblossom[v]] == 0 {
+                    self.assign_label(v, 1, NONE);
+                }
+            }
+            let mut augmented = false;
+            loop {
+                while !augmented {
+                    let v = match self.queue.pop() {
+                        Some(v) => v,
+                        None => break,
+                    };
+                    for idx in 0..self.neighbend[v].len() {
+                        let p = self.neighbend[v][idx];
+                        let k = p / 2;
+                        let w = self.endpoint[p];
+                        if self.inblossom[v] == self.inblossom[w] {
+                            continue;
+                        }
+                        let mut kslack = 0;
+                        if !self.allowedge[k] {
+                            kslack = self.slack(k);
+                            if kslack <= 0 {
+                                self.allowedge[k] = true;
+                            }
+                        }
+                        if self.allowedge[k] {
+                            if self.label[self.inblossom[w]] == 0 {
+                                self.assign_label(w, 2, p ^ 1);
+                            } else if self.label[self.inblossom[w]] == 1 {
+                                let base = self.scan_blossom(v, w);
+                                if base != NONE {
+                                    self.add_blossom(base, k);
+                                } else {
+                                    self.augment_matching(k);
+                                    augmented = true;
+                                    break;
+                                }
+                            } else if self.label[w] == 0 {
+                                self.label[w] = 2;
+                                self.labelend[w] = p ^ 1;
+                            }
+                        } else if self.label[self.inblossom[w]] == 1 {
+                            let b = self.inblossom[v];
+                            if self.bestedge[b] == NONE || kslack < self.slack(self.bestedge[b]) {
+                                self.bestedge[b] = k;
+                            }
+                        } else if self.label[w] == 0
+                            && (self.bestedge[w] == NONE || kslack < self.slack(self.bestedge[w])) {
+                            self.bestedge[w] = k;
+                        }
+                    }
+                }
+                if augmented {
+                    break;
+                }
+
+                // compute the dual update
+                let mut deltatype = 0;
+                let mut delta = 0;
+                let mut deltaedge = NONE;
+                let mut deltablossom = NONE;
+                if !max_cardinality {
+                    deltatype = 1;
+                    delta = *self.dualvar[..n].iter().min().unwrap();
+                }
+                for v in 0..n {
+                    if self.label[self.inblossom[v]] == 0 && self.bestedge[v] != NONE {
+                        let d = self.slack(self.bestedge[v]);
+                        if deltatype == 0 || d < delta {
+                            delta = d;
+                            deltatype = 2;
+                            deltaedge = self.bestedge[v];
+                        }
+                    }
+                }
+                for b in 0..2 * n {
+                    if self.blossomparent[b] == NONE && self.label[b] == 1 && self.bestedge[b] != NONE {
+                        let d = self.slack(self.bestedge[b]) / 2;
+                        if deltatype == 0 || d < delta {
+                            delta = d;
+                            deltatype = 3;
+                            deltaedge = self.bestedge[b];
+                        }
+                    }
+                }
+                for b in n..2 * n {
+                    if self.blossombase[b] != NONE && self.blossomparent[b] == NONE && self.label[b] == 2
+                        && (deltatype == 0 || self.dualvar[b] < delta) {
+                        delta = self.dualvar[b];
+                        deltatype = 4;
+                        deltablossom = b;
+                    }
+                }
+                if deltatype == 0 {
+                    deltatype = 1;
+                    delta = (*self.dualvar[..n].iter().min().unwrap()).max(0);
+                }
+
+                for v in 0..n {
+                    match self.label[self.inblossom[v]] {
+                        1 => self.dualvar[v] -= delta,
+                        2 => self.dualvar[v] += delta,
+                        _ => {}
+                    }
+                }
+                for b in n..2 * n {
+                    if self.blossombase[b] != NONE && self.blossomparent[b] == NONE {
+                        match self.label[b] {
+                            1 => self.dualvar[b] += delta,
+                            2 => self.dualvar[b] -= delta,
+                            _ => {}
+                        }
+                    }
+                }
+
+                match deltatype {
+                    1 => break,
+                    2 => {
+                        self.allowedge[deltaedge] = true;
+                        let (mut i, j, _) = self.edges[deltaedge];
+                        if self.label[self.inblossom[i]] == 0 {
+                            i = j;
+                        }
+                        self.queue.push(i);
+                    }
+                    3 => {
+                        self.allowedge[deltaedge] = true;
+                        let (i, _, _) = self.edges[deltaedge];
+                        self.queue.push(i);
+                    }
+                    _ => self.expand_blossom(deltablossom, false),
+                }
+            }
+
+            if !augmented {
+                break;
+            }
+
+            for b in n..2 * n {
+                if self.blossomparent[b] == NONE && self.blossombase[b] != NONE && self.label[b] == 1 && self.dualvar[b] == 0 {
+                    self.expand_blossom(b, true);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+fn matching_weight(mate: &[Option<usize>], edges: &[(usize, usize, i64)]) -> i64 {
+    edges.iter()
+        .filter(|&&(i, j, _)| mate[i] == Some(j))
+        .map(|e| e.2)
+        .sum()
+}
+
+#[test]
+fn test_simple_matchings() {
+    assert_eq!(max_weight_matching(0, &[], false), vec![]);
+    assert_eq!(max_weight_matching(2, &[(0, 1, 1)], false), vec![Some(1), Some(0)]);
+    assert_eq!(max_weight_matching(4, &[(1, 2, 10), (2, 3, 11)], false), vec![None, None, Some(3), Some(2)]);
+}
+
+#[test]
+fn test_max_cardinality() {
+    let edges = vec![(1, 2, 2), (1, 3, -2), (2, 3, 1), (2, 4, -1), (3, 4, -6)];
+    assert_eq!(max_weight_matching(5, &edges, false), vec![None, Some(2), Some(1), None, None]);
+    assert_eq!(max_weight_matching(5, &edges, true), vec![None, Some(3), Some(4), Some(1), Some(2)]);
+}
+
+#[test]
+fn test_blossoms() {
+    // create S-blossom and use it for augmentation
+    let edges = vec![(1, 2, 8), (1, 3, 9), (2, 3, 10), (3, 4, 7)];
+    assert_eq!(max_weight_matching(5, &edges, false), vec![None, Some(2), Some(1), Some(4), Some(3)]);
+    let edges = vec![(1, 2, 8), (1, 3, 9), (2, 3, 10), (3, 4, 7), (1, 6, 5), (4, 5, 6)];
+    assert_eq!(max_weight_matching(7, &edges, false), vec![None, Some(6), Some(3), Some(2), Some(5), Some(4), Some(1)]);
+    // create S-blossom, relabel as T-blossom, use for augmentation
+    let edges = vec![(1, 2, 9), (1, 3, 8), (2, 3, 10), (1, 4, 5), (4, 5, 4), (1, 6, 3)];
+    assert_eq!(max_weight_matching(7, &edges, false), vec![None, Some(6), Some(3), Some(2), Some(5), Some(4), Some(1)]);
+    // create nested S-blossom, use for augmentation
+    let edges = vec![(1, 2, 9), (1, 3, 9), (2, 3, 10), (2, 4, 8), (3, 5, 8), (4, 5, 10), (5, 6, 6)];
+    assert_eq!(max_weight_matching(7, &edges, false), vec![None, Some(3), Some(4), Some(1), Some(2), Some(6), Some(5)]);
+    // create S-blossom, relabel as S, include in nested S-blossom
+    let edges = vec![(1, 2, 10), (1, 7, 10), (2, 3, 12), (3, 4, 20), (3, 5, 20), (4, 5, 25), (5, 6, 10), (6, 7, 10), (7, 8, 8)];
+    assert_eq!(max_weight_matching(9, &edges, false), vec![None, Some(2), Some(1), Some(4), Some(3), Some(6), Some(5), Some(8), Some(7)]);
+    // create nested S-blossom, augment, expand recursively
+    let edges = vec![(1, 2, 8), (1, 3, 8), (2, 3, 10), (2, 4, 12), (3, 5, 12), (4, 5, 14), (4, 6, 12), (5, 7, 12), (6, 7, 14), (7, 8, 12)];
+    assert_eq!(max_weight_matching(9, &edges, false), vec![None, Some(2), Some(1), Some(5), Some(6), Some(3), Some(4), Some(8), Some(7)]);
+    // create S-blossom, relabel as T, expand
+    let edges = vec![(1, 2, 23), (1, 5, 22), (1, 6, 15), (2, 3, 25), (3, 4, 22), (4, 5, 25), (4, 8, 14), (5, 7, 13)];
+    assert_eq!(max_weight_matching(9, &edges, false), vec![None, Some(6), Some(3), Some(2), Some(8), Some(7), Some(1), Some(5), Some(4)]);
+    // create nested S-blossom, relabel as T, expand
+    let edges = vec![(1, 2, 19), (1, 3, 20), (1, 8, 8), (2, 3, 25), (2, 4, 18), (3, 5, 18), (4, 5, 13), (4, 7, 7), (5, 6, 7)];
+    assert_eq!(max_weight_matching(9, &edges, false), vec![None, Some(8), Some(3), Some(2), Some(7), Some(6), Some(5), Some(4), Some(1)]);
+    // nasty cases with nested blossoms expanding and relabeling
+    let edges = vec![(1, 2, 45), (1, 5, 45), (2, 3, 50), (3, 4, 45), (4, 5, 50), (1, 6, 30), (3, 9, 35), (4, 8, 35), (5, 7, 26), (9, 10, 5)];
+    assert_eq!(max_weight_matching(11, &edges, false), vec![None, Some(6), Some(3), Some(2), Some(8), Some(7), Some(1), Some(5), Some(4), Some(10), Some(9)]);
+    let edges = vec![(1, 2, 45), (1, 5, 45), (2, 3, 50), (3, 4, 45), (4, 5, 50), (1, 6, 30), (3, 9, 35), (4, 8, 26), (5, 7, 40), (9, 10, 5)];
+    assert_eq!(max_weight_matching(11, &edges, false), vec![None, Some(6), Some(3), Some(2), Some(8), Some(7), Some(1), Some(5), Some(4), Some(10), Some(9)]);
+    let edges = vec![(1, 2, 45), (1, 5, 45), (2, 3, 50), (3, 4, 45), (4, 5, 50), (1, 6, 30), (3, 9, 35), (4, 8, 28), (5, 7, 26), (9, 10, 5)];
+    assert_eq!(max_weight_matching(11, &edges, false), vec![None, Some(6), Some(3), Some(2), Some(8), Some(7), Some(1), Some(5), Some(4), Some(10), Some(9)]);
+    let edges = vec![(1, 2, 45), (1, 7, 45), (2, 3, 50), (3, 4, 45), (4, 5, 95), (4, 6, 94), (5, 6, 94), (6, 7, 50), (1, 8, 30), (3, 11, 35), (5, 9, 36), (7, 10, 26), (11, 12, 5)];
+    assert_eq!(max_weight_matching(13, &edges, false), vec![None, Some(8), Some(3), Some(2), Some(6), Some(9), Some(4), Some(10), Some(1), Some(5), Some(7), Some(12), Some(11)]);
+    let edges = vec![(1, 2, 40), (1, 3, 40), (2, 3, 60), (2, 4, 55), (3, 5, 55), (4, 5, 50), (1, 8, 15), (5, 7, 30), (7, 6, 10), (8, 10, 10), (4, 9, 30)];
+    assert_eq!(max_weight_matching(11, &edges, false), vec![None, Some(2), Some(1), Some(5), Some(9), Some(3), Some(7), Some(6), Some(10), Some(4), Some(8)]);
+}
+
+#[test]
+fn test_min_cost_perfect_matching() {
+    assert_eq!(min_cost_perfect_matching(0, &[]), Some(vec![]));
+    assert_eq!(min_cost_perfect_matching(2, &[]), None);
+    assert_eq!(min_cost_perfect_matching(3, &[(0, 1, 0), (1, 2, 0)]), None);
+    // the greedy start takes (1, 2), which has to be undone
+    let edges = vec![(0, 1, 5), (1, 2, 0), (2, 3, 5), (0, 3, 100)];
+    assert_eq!(min_cost_perfect_matching(4, &edges), Some(vec![1, 0, 3, 2]));
+    let edges = vec![(0, 1, 3), (0, 2, 1), (1, 2, 1), (2, 3, 4), (3, 4, 0), (3, 5, 2), (4, 5, 1)];
+    assert_eq!(min_cost_perfect_matching(6, &edges), Some(vec![1, 0, 3, 2, 5, 4]));
+}
+
+#[test]
+fn test_against_brute_force() {
+    // compare with exhaustive search on small pseudo-random graphs
+    fn brute(edges: &[(usize, usize, i64)], used: &mut Vec<bool>, from: usize) -> (usize, i64) {
+        let mut best = (0, 0);
+        for k in from..edges.len() {
+            let (i, j, w) = edges[k];
+            if used[i] || used[j] {
+                continue;
+            }
+            used[i] = true;
+            used[j] = true;
+            let (c, s) = brute(edges, used, k + 1);
+            used[i] = false;
+            used[j] = false;
+            if (c + 1, s + w) > best {
+                best = (c + 1, s + w);
+            }
+        }
+        best
+    }
+    let mut seed: u64 = 0x2545F4914F6CDD1D;
+    let mut next = || {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        seed
+    };
+    for _ in 0..200 {
+        let n = (next() % 9 + 2) as usize;
+        let mut edges = Vec::new();
+        for i in 0..n {
+            for j in i + 1..n {
+                if next() % 3 != 0 {
+                    edges.push((i, j, (next() % 20) as i64));
+                }
+            }
+        }
+        let mate = max_weight_matching(n, &edges, true);
+        for (v, m) in mate.iter().enumerate() {
+            if let Some(w) = m {
+                assert_eq!(mate[*w], Some(v));
+            }
+        }
+        let cardinality = mate.iter().filter(|m| m.is_some()).count() / 2;
+        let best = brute(&edges, &mut vec![false; n], 0);
+        assert_eq!((cardinality, matching_weight(&mate, &edges)), best);
+        let costs: Vec<(usize, usize, i64)> = edges.iter().map(|&(i, j, w)| (i, j, 20 - w)).collect();
+        match min_cost_perfect_matching(n, &costs) {
+            Some(perfect) => {
+                let perfect: Vec<Option<usize>> = perfect.into_iter().map(Some).collect();
+                assert_eq!((n / 2, matching_weight(&perfect, &edges)), best);
+            }
+            None => assert!(best.0 < n / 2 || n % 2 == 1),
+        }
+    }
+}
+
+#[test]
+fn test_optimality_certificate() {
+    // the matching of a subgraph is the minimum of the whole graph when no other edge could improve it
+    let mut seed: u64 = 0x9E3779B97F4A7C15;
+    let mut next = || {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        seed
+    };
+    let cost = |m: &[usize], edges: &[(usize, usize, i64)]| edges.iter().filter(|&&(i, j, _)| m[i] == j).map(|e| e.2).sum::<i64>();
+    let (mut certified, mut refused) = (0, 0);
+    for _ in 0..300 {
+        let n = 2 * (next() % 5 + 2) as usize;
+        let edges: Vec<(usize, usize, i64)> = (0..n).flat_map(|i| (i + 1..n).map(move |j| (i, j)))
+            .map(|(i, j)| (i, j, (next() % 10) as i64))
+            .collect();
+        let (inside, outside): (Vec<_>, Vec<_>) = edges.iter().partition(|_| next() % 3 != 0);
+        let full = min_cost_perfect_matching(n, &edges).unwrap();
+        if let Some(matching) = optimal_perfect_matching(n, &inside) {
+            assert!(outside.iter().all(|&(i, j, c)| !matching.could_improve(i, j, c) || 4 * c < matching.price(i) + matching.price(j)));
+            let shared = |i: usize, j: usize| match (matching.outermost_blossom(i), matching.outermost_blossom(j)) {
+                (Some((bi, z)), Some((bj, _))) if bi == bj => 2 * z,
+                _ => 0,
+            };
+            assert!(outside.iter().all(|&(i, j, c)| !matching.could_improve(i, j, c) || 4 * c + shared(i, j) < matching.price(i) + matching.price(j)));
+            if outside.iter().any(|&(i, j, c)| matching.could_improve(i, j, c)) {
+                refused += 1;
+            } else {
+                assert_eq!(cost(&matching.mate, &edges), cost(&full, &edges));
+                certified += 1;
+            }
+        }
+    }
+    assert!(certified > 0 && refused > 0);
+}
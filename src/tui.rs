@@ -0,0 +1,315 @@
+//! `opo tui`: the interactive scorekeeping screen ( feature `tui` )
+//!
+//! every change is saved to the tournament file at once, as with the other subcommands.
+
+use super::parse_score;
+use opo::tournament_model::error::OpoError;
+use opo::tournament_model::matching::Matching;
+use opo::tournament_model::swiss_system_tournament::{RoundState, Tournament};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::widgets::{Block, Paragraph, Row, Table, TableState};
+use ratatui::Frame;
+use std::path::PathBuf;
+
+const HELP_PAIRINGS: &str = "w/l/d: the left player won/lost/drew  s: type the games  f/F: left/right forfeits  p: pair  tab: standings  q: quit";
+const HELP_STANDINGS: &str = "x: drop the player  p: pair  tab: pairings  q: quit";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum View {
+    Pairings,
+    Standings,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Mode {
+    Normal,
+    /// typing the games of the selected table as W-L or W-D-L
+    Score(String),
+    /// waiting for `y` to drop the player
+    ConfirmDrop(usize),
+}
+
+pub struct App {
+    tournament: Tournament,
+    path: PathBuf,
+    view: View,
+    mode: Mode,
+    selected: usize,
+    message: String,
+    quit: bool,
+}
+
+impl App {
+    pub fn new(tournament: Tournament, path: PathBuf) -> Self {
+        App { tournament, path, view: View::Pairings, mode: Mode::Normal, selected: 0, message: String::new(), quit: false }
+    }
+
+    /// the rows of the pairings: the tables as `(number, player, opponent)`, then the byes
+    fn seats(&self) -> Vec<(Option<usize>, usize, Option<usize>)> {
+        self.tournament.tables().into_iter()
+            .map(|table| (Some(table.number), table.player_id, Some(table.opponent_id)))
+            .chain(self.tournament.byes().into_iter().map(|id| (None, id, None)))
+            .collect()
+    }
+
+    fn row_count(&self) -> usize {
+        match self.view {
+            View::Pairings => self.seats().len(),
+            View::Standings => self.tournament.players().len(),
+        }
+    }
+
+    /// apply the change and save the tournament, and show what happened
+    fn change<F: FnOnce(&mut Tournament) -> Result<String, OpoError>>(&mut self, change: F) {
+        let result = change(&mut self.tournament)
+            .and_then(|message| self.tournament.save(&self.path).map(|_| message));
+        self.message = match result {
+            Ok(message) => message,
+            Err(e) => format!("error: {}", e),
+        };
+    }
+
+    /// enter the result of the selected table for its left player, or correct it
+    fn enter_result(&mut self, (win, draw, lose): (i32, i32, i32), player_withdraw: bool, opponent_withdraw: bool) {
+        let (player_id, opponent_id) = match self.seats().get(self.selected) {
+            Some(&(_, player_id, Some(opponent_id))) => (player_id, opponent_id),
+            Some(_) => {
+                self.message = "a bye needs no result".to_string();
+                return;
+            }
+            None => return,
+        };
+        self.change(|tournament| {
+            let round_number = match tournament.state() {
+                RoundState::ResultsPending(round_number) | RoundState::Complete(round_number) => round_number,
+                state => return Err(OpoError::InvalidState { state, action: "record a result" }),
+            };
+            let matching = Matching::new(round_number, player_id, opponent_id, win, draw, lose, player_withdraw, opponent_withdraw)?;
+            let recorded = tournament.players()[player_id].matching_list().iter().any(|m| m.round_number() == round_number);
+            if recorded {
+                tournament.amend_result(matching)?;
+                return Ok(format!("corrected the result of {}", tournament.players()[player_id].name()));
+            }
+            tournament.record_result(matching)?;
+            if tournament.outstanding_tables().is_empty() {
+                tournament.close_round()?;
+                return Ok(format!("round {} is complete", round_number));
+            }
+            Ok(format!("{} tables outstanding", tournament.outstanding_tables().len()))
+        });
+    }
+
+    pub fn handle_key(&mut self, key: KeyCode) {
+        match std::mem::replace(&mut self.mode, Mode::Normal) {
+            Mode::Score(mut score) => match key {
+                KeyCode::Enter => match parse_score(&score) {
+                    Ok(games) => self.enter_result(games, false, false),
+                    Err(e) => self.message = format!("error: {}", e),
+                },
+                KeyCode::Esc => self.message.clear(),
+                KeyCode::Backspace => {
+                    score.pop();
+                    self.mode = Mode::Score(score);
+                }
+                KeyCode::Char(c) if c.is_ascii_digit() || c == '-' => {
+                    score.push(c);
+                    self.mode = Mode::Score(score);
+                }
+                _ => self.mode = Mode::Score(score),
+            },
+            Mode::ConfirmDrop(player_id) => match key {
+                KeyCode::Char('y') => self.change(|tournament| {
+                    tournament.drop_player(player_id)?;
+                    Ok(format!("dropped {}", tournament.players()[player_id].name()))
+                }),
+                _ => self.message = "drop cancelled".to_string(),
+            },
+            Mode::Normal => self.handle_normal_key(key),
+        }
+    }
+
+    fn handle_normal_key(&mut self, key: KeyCode) {
+        match (self.view, key) {
+            (_, KeyCode::Char('q')) | (_, KeyCode::Esc) => self.quit = true,
+            (_, KeyCode::Tab) => {
+                self.view = if self.view == View::Pairings { View::Standings } else { View::Pairings };
+                self.selected = 0;
+            }
+            (_, KeyCode::Up) | (_, KeyCode::Char('k')) => self.selected = self.selected.saturating_sub(1),
+            (_, KeyCode::Down) | (_, KeyCode::Char('j')) => {
+                self.selected = (self.selected + 1).min(self.row_count().saturating_sub(1));
+            }
+            (_, KeyCode::Char('p')) => {
+                self.change(|tournament| tournament.pair_next_round().map(|round_number| format!("round {} is paired", round_number)));
+                self.view = View::Pairings;
+                self.selected = 0;
+            }
+            (View::Pairings, KeyCode::Char('w')) => self.enter_result((1, 0, 0), false, false),
+            (View::Pairings, KeyCode::Char('l')) => self.enter_result((0, 0, 1), false, false),
+            (View::Pairings, KeyCode::Char('d')) => self.enter_result((0, 1, 0), false, false),
+            (View::Pairings, KeyCode::Char('f')) => self.enter_result((0, 0, 0), true, false),
+            (View::Pairings, KeyCode::Char('F')) => self.enter_result((0, 0, 0), false, true),
+            (View::Pairings, KeyCode::Char('s')) => self.mode = Mode::Score(String::new()),
+            (View::Standings, KeyCode::Char('x')) => {
+                if let Some(player) = self.tournament.standings().get(self.selected) {
+                    self.mode = Mode::ConfirmDrop(player.id());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn header(&self) -> String {
+        let round = match self.tournament.state() {
+            RoundState::Registration => "registration".to_string(),
+            RoundState::Paired(round_number) => format!("round {} paired", round_number),
+            RoundState::ResultsPending(round_number) => format!("round {}: results pending", round_number),
+            RoundState::Complete(round_number) => format!("round {} complete", round_number),
+            RoundState::Finished => "finished".to_string(),
+        };
+        format!("{}  |  {} players  |  {} tables outstanding", round, self.tournament.players().len(), self.tournament.outstanding_tables().len())
+    }
+
+    fn pairings_table(&self) -> Table<'static> {
+        let players = self.tournament.players();
+        let round_number = match self.tournament.state() {
+            RoundState::Paired(round_number) | RoundState::ResultsPending(round_number) | RoundState::Complete(round_number) => round_number,
+            _ => self.tournament.round_number(),
+        };
+        let rows = self.seats().into_iter().map(|(number, id, opponent_id)| {
+            let result = players[id].matching_list().iter().find(|m| m.round_number() == round_number);
+            let (result, style) = match (opponent_id, result) {
+                (None, _) => ("bye".to_string(), Style::default().add_modifier(Modifier::DIM)),
+                (Some(_), None) => ("outstanding".to_string(), Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+                (Some(_), Some(m)) if !m.is_valid() => ("forfeit".to_string(), Style::default()),
+                (Some(_), Some(m)) => (format!("{}-{}-{}", m.win_count(), m.draw_count(), m.lose_count()), Style::default()),
+            };
+            let opponent = opponent_id.map_or_else(String::new, |opponent_id| players[opponent_id].name().clone());
+            let number = number.map_or_else(String::new, |number| number.to_string());
+            Row::new(vec![number, players[id].name().clone(), opponent, result]).style(style)
+        });
+        Table::new(rows, [Constraint::Length(5), Constraint::Fill(1), Constraint::Fill(1), Constraint::Length(12)])
+            .header(Row::new(vec!["table", "player", "opponent", "result"]).add_modifier(Modifier::UNDERLINED))
+            .block(Block::bordered().title(" pairings "))
+    }
+
+    fn standings_table(&self) -> Table<'static> {
+        let percentage = |value: f64| format!("{:.2}", value * 100.0);
+        let rows = self.tournament.standings().into_iter().enumerate().map(|(rank, player)| {
+            let name = if player.is_dropped() { format!("{} (dropped)", player.name()) } else { player.name().clone() };
+            Row::new(vec![
+                (rank + 1).to_string(), name, player.points().to_string(),
+                percentage(player.opponent_match_win_percentage()),
+                percentage(player.game_win_percentage()),
+                percentage(player.opponent_game_win_percentage()),
+            ])
+        });
+        let widths = [Constraint::Length(4), Constraint::Fill(1), Constraint::Length(6), Constraint::Length(7), Constraint::Length(7), Constraint::Length(7)];
+        Table::new(rows, widths)
+            .header(Row::new(vec!["rank", "player", "points", "OMW%", "GW%", "OGW%"]).add_modifier(Modifier::UNDERLINED))
+            .block(Block::bordered().title(" standings "))
+    }
+
+    pub fn draw(&self, frame: &mut Frame) {
+        let [header, body, footer] = Layout::vertical([Constraint::Length(1), Constraint::Fill(1), Constraint::Length(2)]).areas(frame.area());
+        frame.render_widget(Paragraph::new(self.header()), header);
+        let table = match self.view {
+            View::Pairings => self.pairings_table(),
+            View::Standings => self.standings_table(),
+        };
+        let table = table.row_highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(table, body, &mut TableState::default().with_selected(Some(self.selected)));
+        let prompt = match &self.mode {
+            Mode::Score(score) => format!("games of the left player ( W-L or W-D-L ): {}", score),
+            Mode::ConfirmDrop(player_id) => format!("drop {}? ( y/n )", self.tournament.players()[*player_id].name()),
+            Mode::Normal => self.message.clone(),
+        };
+        let help = if self.view == View::Pairings { HELP_PAIRINGS } else { HELP_STANDINGS };
+        frame.render_widget(Paragraph::new(format!("{}\n{}", prompt, help)), footer);
+    }
+}
+
+/// run the screen on the tournament file until `q`
+pub fn run(path: PathBuf) -> Result<(), OpoError> {
+    let mut app = App::new(Tournament::load(&path)?, path);
+    let mut terminal = ratatui::init();
+    let mut result = Ok(());
+    while !app.quit {
+        if let Err(e) = terminal.draw(|frame| app.draw(frame)) {
+            result = Err(e.into());
+            break;
+        }
+        match event::read() {
+            Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => app.handle_key(key.code),
+            Ok(_) => {}
+            Err(e) => {
+                result = Err(e.into());
+                break;
+            }
+        }
+    }
+    ratatui::restore();
+    result
+}
+
+#[cfg(test)]
+use opo::tournament_model::players::Player;
+
+#[test]
+fn test_app() {
+    let path = std::env::temp_dir().join(format!("opo_test_tui_{}.json", std::process::id()));
+    let mut t: Tournament = Default::default();
+    for i in 0..5 {
+        t.add_player(Player::new(i, format!("{}abcd", i)));
+    }
+    t.save(&path).unwrap();
+    let mut app = App::new(t, path.clone());
+    let keys = |app: &mut App, keys: &[KeyCode]| keys.iter().for_each(|&key| app.handle_key(key));
+
+    keys(&mut app, &[KeyCode::Char('p')]);
+    assert_eq!(app.tournament.state(), RoundState::ResultsPending(1));
+    assert_eq!(app.tournament.outstanding_tables().len(), 2);
+    keys(&mut app, &[KeyCode::Char('w'), KeyCode::Down]);
+    assert_eq!(app.message, "1 tables outstanding");
+    keys(&mut app, &[KeyCode::Char('s'), KeyCode::Char('2'), KeyCode::Char('x'), KeyCode::Char('-'), KeyCode::Char('1'), KeyCode::Enter]);
+    assert_eq!(app.message, "round 1 is complete");
+    let id = app.tournament.tables()[1].player_id;
+    assert_eq!(app.tournament.players()[id].matching_list()[0].win_count(), 2);
+    // a second result corrects the first one
+    keys(&mut app, &[KeyCode::Char('l')]);
+    assert!(app.tournament.players()[id].matching_list()[0].is_lose());
+    // a bye has no result
+    keys(&mut app, &[KeyCode::Down, KeyCode::Char('w')]);
+    assert_eq!(app.message, "a bye needs no result");
+
+    // drops are confirmed
+    keys(&mut app, &[KeyCode::Tab, KeyCode::Char('x'), KeyCode::Char('n')]);
+    assert!(app.tournament.players().iter().all(|p| !p.is_dropped()));
+    let first = app.tournament.standings()[0].id();
+    keys(&mut app, &[KeyCode::Char('x'), KeyCode::Char('y')]);
+    assert!(app.tournament.players()[first].is_dropped());
+
+    let saved = Tournament::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(saved.players()[first].is_dropped());
+    keys(&mut app, &[KeyCode::Char('q')]);
+    assert!(app.quit);
+}
+
+#[test]
+fn test_draw() {
+    let mut t: Tournament = Default::default();
+    for i in 0..4 {
+        t.add_player(Player::new(i, format!("{}abcd", i)));
+    }
+    t.pair_round().unwrap();
+    t.start_round().unwrap();
+    let app = App::new(t, PathBuf::from("unused.json"));
+    let mut terminal = ratatui::Terminal::new(ratatui::backend::TestBackend::new(100, 12)).unwrap();
+    terminal.draw(|frame| app.draw(frame)).unwrap();
+    let screen: String = terminal.backend().buffer().content().iter().map(|cell| cell.symbol()).collect();
+    assert!(screen.contains("round 1: results pending"));
+    assert_eq!(screen.matches("outstanding").count(), 3);
+}